@@ -0,0 +1,253 @@
+//! # keypropdecode-derive
+//! The derive macro of [keypropdecode](https://docs.rs/keypropdecode/latest/keypropdecode/).
+//! It maps a struct with boolean fields to the Windows file attributes, so you don't have to write
+//! the conversions to and from `Props` by hand.
+//! It is re-exported by keypropdecode when its `derive` feature is enabled.
+use proc_macro::TokenStream;
+use proc_macro2::{Ident, Span};
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Type};
+
+/// The attribute words that can go inside `#[attr(...)]`, with the constant that stores them
+const ATTRIBUTES: [(&str, &str); 21] = [
+    ("read_only", "READ_ONLY"),
+    ("hidden", "HIDDEN"),
+    ("system", "SYSTEM"),
+    ("directory", "DIRECTORY"),
+    ("device", "DEVICE"),
+    ("normal", "NORMAL"),
+    ("temporary", "TEMPORARY"),
+    ("sparse", "SPARSE"),
+    ("reparse", "REPARSE"),
+    ("compressed", "COMPRESSED"),
+    ("offline", "OFFLINE"),
+    ("not_content_indexed", "NOT_CONTENT_INDEXED"),
+    ("encrypted", "ENCRYPTED"),
+    ("integrity_stream", "INTEGRITY_STREAM"),
+    ("virtual_file", "VIRTUAL_FILE"),
+    ("no_scrub_data", "NO_SCRUB_DATA"),
+    ("extended_attributes", "EXTENDED_ATTRIBUTES"),
+    ("pinned", "PINNED"),
+    ("unpinned", "UNPINNED"),
+    ("recall_on_open", "RECALL_ON_OPEN"),
+    ("recall_on_data_access", "RECALL_ON_DATA_ACCESS"),
+];
+
+/// Generates `From<YourStruct> for Props` and `TryFrom<Props> for YourStruct`.
+///
+/// Every `bool` field annotated with `#[attr(name)]` is mapped to the attribute with that name.
+/// The names are the ones the setters of `Props` use, plus `directory`.
+/// `normal` can't be mapped together with any other attribute, since `Props` only allows it
+/// alone.
+/// Fields without the annotation are left out of the conversion to `Props` and filled with
+/// `Default::default()` when converting back.
+///
+/// If the struct has no `directory` field it can only hold files: converting it gives a file, and
+/// converting a directory `Props` into it returns a `NotAFile` error.
+/// When it has one, the field decides if the element is a directory or a file, and the
+/// file-exclusive attributes are dropped for directories, the same way `Props::from(u32)` does.
+/// ```
+/// use keypropdecode::props::*;
+/// use keypropdecode_derive::FileAttributes;
+///
+/// #[derive(FileAttributes, Debug, PartialEq)]
+/// struct CatalogEntry {
+///     #[attr(hidden)]
+///     hidden: bool,
+///     #[attr(read_only)]
+///     locked: bool,
+///     name: String,
+/// }
+///
+/// let entry = CatalogEntry { hidden: true, locked: true, name: "report.pdf".to_string() };
+/// let props = Props::from(entry);
+/// assert!(props.is_hidden());
+/// assert_eq!(props.is_read_only(), Ok(true));
+///
+/// let entry = CatalogEntry::try_from(props).unwrap();
+/// assert_eq!(entry, CatalogEntry { hidden: true, locked: true, name: String::new() });
+/// ```
+/// Attribute names are checked at compile time:
+/// ```compile_fail
+/// use keypropdecode_derive::FileAttributes;
+///
+/// #[derive(FileAttributes)]
+/// struct CatalogEntry {
+///     #[attr(hiden)]
+///     hidden: bool,
+/// }
+/// ```
+/// So are the types of the mapped fields:
+/// ```compile_fail
+/// use keypropdecode_derive::FileAttributes;
+///
+/// #[derive(FileAttributes)]
+/// struct CatalogEntry {
+///     #[attr(hidden)]
+///     hidden: u8,
+/// }
+/// ```
+/// And `normal` with other attributes:
+/// ```compile_fail
+/// use keypropdecode_derive::FileAttributes;
+///
+/// #[derive(FileAttributes)]
+/// struct CatalogEntry {
+///     #[attr(normal)]
+///     normal: bool,
+///     #[attr(hidden)]
+///     hidden: bool,
+/// }
+/// ```
+#[proc_macro_derive(FileAttributes, attributes(attr))]
+pub fn derive_file_attributes(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let name = &input.ident;
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &input.ident,
+                    "FileAttributes can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(syn::Error::new_spanned(
+                &input.ident,
+                "FileAttributes can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut mapped: Vec<(Ident, Ident)> = Vec::new();
+    let mut unmapped: Vec<Ident> = Vec::new();
+    let mut used: Vec<&str> = Vec::new();
+    let mut has_directory = false;
+    for field in fields {
+        let field_name = field.ident.clone().expect("named fields have a name");
+        let mut attribute: Option<Ident> = None;
+        for attr in field
+            .attrs
+            .iter()
+            .filter(|attr| attr.path().is_ident("attr"))
+        {
+            let word: Ident = attr.parse_args()?;
+            let Some((word_name, constant)) =
+                ATTRIBUTES.iter().find(|(word_name, _)| word == word_name)
+            else {
+                return Err(syn::Error::new_spanned(
+                    &word,
+                    format!("Unknown file attribute `{}`", word),
+                ));
+            };
+            if attribute.is_some() {
+                return Err(syn::Error::new_spanned(
+                    attr,
+                    "A field can only be mapped to one attribute",
+                ));
+            }
+            if used.contains(word_name) {
+                return Err(syn::Error::new_spanned(
+                    &word,
+                    format!("The `{}` attribute is mapped twice", word_name),
+                ));
+            }
+            if *word_name == "directory" {
+                has_directory = true;
+            }
+            used.push(word_name);
+            attribute = Some(Ident::new(constant, Span::call_site()));
+        }
+        if attribute.is_some() && !is_bool(&field.ty) {
+            return Err(syn::Error::new_spanned(
+                &field.ty,
+                "The fields mapped to an attribute must be `bool`",
+            ));
+        }
+        match attribute {
+            Some(constant) => mapped.push((field_name, constant)),
+            None => unmapped.push(field_name),
+        }
+    }
+
+    if used.contains(&"normal") && used.len() > 1 {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "The `normal` attribute can't be mapped together with other attributes",
+        ));
+    }
+
+    let constants = quote!(::keypropdecode::props::constants);
+    let to_bits = mapped.iter().map(|(field, constant)| {
+        quote! {
+            if value.#field {
+                attributes |= #constants::#constant;
+            }
+        }
+    });
+    let from_bits = mapped.iter().map(|(field, constant)| {
+        quote! {
+            #field: attributes & #constants::#constant == #constants::#constant,
+        }
+    });
+    let directory_check = if has_directory {
+        quote!()
+    } else {
+        let message = format!("`{}` can only hold files", name);
+        quote! {
+            if value.is_directory() {
+                return Err(::keypropdecode::error::Error {
+                    kind: ::keypropdecode::error::ErrorKind::NotAFile(#message.to_string()),
+                });
+            }
+        }
+    };
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::core::convert::From<#name #type_generics> for ::keypropdecode::Props #where_clause {
+            fn from(value: #name #type_generics) -> Self {
+                let mut attributes: u32 = 0;
+                #(#to_bits)*
+                if attributes & #constants::DIRECTORY != #constants::DIRECTORY {
+                    attributes |= #constants::ARCHIVE;
+                }
+                ::keypropdecode::Props::from(attributes)
+            }
+        }
+        impl #impl_generics ::core::convert::TryFrom<::keypropdecode::Props> for #name #type_generics #where_clause {
+            type Error = ::keypropdecode::error::Error;
+            fn try_from(value: ::keypropdecode::Props) -> ::core::result::Result<Self, Self::Error> {
+                #directory_check
+                let attributes = u32::from(value);
+                Ok(Self {
+                    #(#from_bits)*
+                    #(#unmapped: ::core::default::Default::default(),)*
+                })
+            }
+        }
+    })
+}
+
+/// Checks if a type is `bool`, however it's written
+fn is_bool(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) if path.qself.is_none() => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "bool" && segment.arguments.is_none()),
+        Type::Paren(paren) => is_bool(&paren.elem),
+        Type::Group(group) => is_bool(&group.elem),
+        _ => false,
+    }
+}
@@ -0,0 +1,89 @@
+use keypropdecode::props::*;
+use keypropdecode_derive::FileAttributes;
+
+#[derive(FileAttributes, Debug, PartialEq, Default)]
+struct CatalogEntry {
+    #[attr(hidden)]
+    hidden: bool,
+    #[attr(read_only)]
+    locked: bool,
+    #[attr(temporary)]
+    scratch: bool,
+    name: String,
+}
+
+#[derive(FileAttributes, Debug, PartialEq)]
+struct Element {
+    #[attr(directory)]
+    directory: bool,
+    #[attr(system)]
+    system: bool,
+    #[attr(read_only)]
+    read_only: bool,
+}
+
+#[test]
+fn struct_to_props() {
+    let entry = CatalogEntry {
+        hidden: true,
+        locked: true,
+        ..Default::default()
+    };
+    let mut props = Props::default();
+    props.change_element_type(ArcDir::Archive(ArchiveProps::default()));
+    props.hidden(true);
+    props.read_only(true).unwrap();
+    assert_eq!(Props::from(entry), props);
+}
+#[test]
+fn empty_struct_is_a_file() {
+    let props = Props::from(CatalogEntry::default());
+    assert!(props.is_archive());
+    assert_eq!(u32::from(props), 1 << 5);
+}
+#[test]
+fn props_to_struct() {
+    let entry = CatalogEntry::try_from(Props::from(0b100100011)).unwrap();
+    assert_eq!(
+        entry,
+        CatalogEntry {
+            hidden: true,
+            locked: true,
+            scratch: true,
+            name: String::new(),
+        }
+    );
+}
+#[test]
+fn directory_into_file_only_struct() {
+    assert_eq!(
+        CatalogEntry::try_from(Props::default()),
+        Err(keypropdecode::error::Error {
+            kind: keypropdecode::error::ErrorKind::NotAFile(
+                "`CatalogEntry` can only hold files".to_string()
+            )
+        })
+    );
+}
+#[test]
+fn directory_field() {
+    let element = Element {
+        directory: true,
+        system: true,
+        read_only: true,
+    };
+    let props = Props::from(element);
+    assert!(props.is_directory());
+    assert!(props.is_system());
+    assert_eq!(
+        Element::try_from(props).unwrap(),
+        Element {
+            directory: true,
+            system: true,
+            read_only: false,
+        }
+    );
+    let file = Element::try_from(Props::from(0b100001)).unwrap();
+    assert!(!file.directory);
+    assert!(file.read_only);
+}
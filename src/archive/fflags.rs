@@ -0,0 +1,135 @@
+//! libarchive writes the attributes of Windows files as a list of file flag names, the same
+//! `chflags` uses on BSD, in the `SCHILY.fflags` PAX record and in the `flags` keyword of mtree.
+//! A name with `no` in front clears the flag instead of setting it, and names of flags that
+//! aren't Windows attributes, like `nodump` or `uchg`, are kept as they are so they can be
+//! written back.
+//!
+//! | Name | Aliases | Attribute |
+//! |------|---------|-----------|
+//! | `rdonly` | `readonly`, `urdonly` | read_only |
+//! | `hidden` | `uhidden` | hidden |
+//! | `system` | `usystem` | system |
+//! | `archive` | `uarchive`, `uarch`, `arch` | archive |
+//! | `offline` | `uoffline` | offline |
+//! | `sparse` | `usparse` | sparse |
+//! | `reparse` | `ureparse` | reparse |
+//! ```
+//! use keypropdecode::archive::fflags::*;
+//! use keypropdecode::props;
+//!
+//! let flags = FileFlags::parse("hidden,rdonly,nodump");
+//! assert_eq!(flags.props(false), props!(file: hidden, read_only));
+//! assert_eq!(flags.others, ["nodump"]);
+//! assert_eq!(flags.to_string(), "rdonly,hidden,nodump");
+//! assert_eq!(FileFlags::from(props!(dir: system)).to_string(), "system");
+//! ```
+use crate::props::constants::*;
+use crate::Props;
+
+/// The PAX record libarchive stores the flags in
+pub const FFLAGS_RECORD: &str = "SCHILY.fflags";
+
+/// Every known name with its attribute, the canonical ones first and in the order they're written
+const NAMES: [(&str, u32); 17] = [
+    ("rdonly", READ_ONLY),
+    ("hidden", HIDDEN),
+    ("system", SYSTEM),
+    ("archive", ARCHIVE),
+    ("offline", OFFLINE),
+    ("sparse", SPARSE),
+    ("reparse", REPARSE),
+    ("readonly", READ_ONLY),
+    ("urdonly", READ_ONLY),
+    ("uhidden", HIDDEN),
+    ("usystem", SYSTEM),
+    ("uarchive", ARCHIVE),
+    ("uarch", ARCHIVE),
+    ("uoffline", OFFLINE),
+    ("usparse", SPARSE),
+    ("ureparse", REPARSE),
+    ("arch", ARCHIVE),
+];
+/// The attributes the names can hold
+pub const FFLAGS_ATTRIBUTES: u32 =
+    READ_ONLY | HIDDEN | SYSTEM | ARCHIVE | OFFLINE | SPARSE | REPARSE;
+
+/// A parsed list of file flags
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct FileFlags {
+    /// The attributes the list sets
+    pub set: u32,
+    /// The attributes the list clears, with names like `nohidden`
+    pub clear: u32,
+    /// The names that aren't Windows attributes, in order and as they were written
+    pub others: Vec<String>,
+}
+impl FileFlags {
+    /// Parses a list of names separated by commas, spaces or tabs, like libarchive does.
+    /// An empty list and `none` have no flags.
+    pub fn parse(text: &str) -> Self {
+        let mut flags = FileFlags::default();
+        let names = text
+            .split([',', ' ', '\t'])
+            .filter(|name| !name.is_empty() && *name != "none");
+        for name in names {
+            if let Some(attribute) = attribute(name) {
+                flags.set |= attribute;
+                flags.clear &= !attribute;
+            } else if let Some(attribute) = name.strip_prefix("no").and_then(attribute) {
+                flags.clear |= attribute;
+                flags.set &= !attribute;
+            } else {
+                flags.others.push(name.to_string());
+            }
+        }
+        flags
+    }
+    /// Gets the Props with the attributes of the list, of a file or, if `directory`, a directory
+    pub fn props(&self, directory: bool) -> Props {
+        Props::from(crate::backends::with_element_type(self.set, directory))
+    }
+    /// Sets and clears the attributes of `props` like `chflags` would
+    pub fn apply(&self, props: Props) -> Props {
+        let attributes = (u32::from(props) | self.set) & !self.clear;
+        Props::from(crate::backends::with_element_type(
+            attributes,
+            props.is_directory(),
+        ))
+    }
+}
+impl From<Props> for FileFlags {
+    /// Sets the attributes of `props` that have a name, leaving the rest out
+    fn from(props: Props) -> Self {
+        FileFlags {
+            set: u32::from(props) & FFLAGS_ATTRIBUTES,
+            clear: 0,
+            others: Vec::new(),
+        }
+    }
+}
+impl std::fmt::Display for FileFlags {
+    /// Writes the canonical names separated by commas, the set ones first, then the cleared ones
+    /// and then the other names
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let canonical = &NAMES[..7];
+        let set = canonical
+            .iter()
+            .filter(|(_, attribute)| self.set & attribute != 0)
+            .map(|(name, _)| name.to_string());
+        let clear = canonical
+            .iter()
+            .filter(|(_, attribute)| self.clear & attribute != 0)
+            .map(|(name, _)| format!("no{}", name));
+        let names: Vec<String> = set
+            .chain(clear)
+            .chain(self.others.iter().cloned())
+            .collect();
+        write!(f, "{}", names.join(","))
+    }
+}
+fn attribute(name: &str) -> Option<u32> {
+    NAMES
+        .iter()
+        .find(|(known, _)| *known == name)
+        .map(|(_, attribute)| *attribute)
+}
@@ -0,0 +1,148 @@
+//! Archive formats keep the attributes of every entry in their own way, often next to a Unix
+//! mode when the archive was made on Unix. These modules read and write them as Props, without
+//! extracting anything.
+/// The file flag names libarchive and bsdtar use for the attributes
+pub mod fflags;
+/// The attributes of 7z entries and the `7z l -slt` output
+pub mod sevenzip;
+/// The `MSWINDOWS.fileattr` PAX records of Windows container layers
+pub mod tar;
+#[cfg(test)]
+mod tests;
+/// The external attributes of ZIP central directory entries
+pub mod zip;
+
+use std::io::Read;
+
+use crate::error::*;
+use crate::props::constants::*;
+
+const S_IFMT: u32 = 0o170_000;
+const S_IFDIR: u32 = 0o040_000;
+const S_IFREG: u32 = 0o100_000;
+const S_IFLNK: u32 = 0o120_000;
+/// The `ls -l` letter of every file type
+const FILE_TYPES: [(char, u32); 7] = [
+    ('-', S_IFREG),
+    ('d', S_IFDIR),
+    ('l', S_IFLNK),
+    ('c', 0o020_000),
+    ('b', 0o060_000),
+    ('p', 0o010_000),
+    ('s', 0o140_000),
+];
+
+/// Gets the attribute word of a Unix mode: directories, symbolic links as reparse points, and
+/// files without the owner write bit as read only
+pub(crate) fn attributes_from_mode(mode: u32) -> u32 {
+    match mode & S_IFMT {
+        S_IFDIR => DIRECTORY,
+        S_IFLNK => ARCHIVE | REPARSE,
+        _ if mode & 0o200 == 0 => ARCHIVE | READ_ONLY,
+        _ => ARCHIVE,
+    }
+}
+/// Gets the Unix mode archivers give an element with the attributes of `attributes`
+pub(crate) fn mode_from_attributes(attributes: u32) -> u32 {
+    if attributes & DIRECTORY != 0 {
+        S_IFDIR | 0o755
+    } else if attributes & REPARSE != 0 {
+        S_IFLNK | 0o777
+    } else if attributes & READ_ONLY != 0 {
+        S_IFREG | 0o444
+    } else {
+        S_IFREG | 0o644
+    }
+}
+/// Gets the mode of a string like `ls -l` prints, such as `drwxr-xr-x` or `-rwsr-xr-T`
+pub(crate) fn parse_mode_string(text: &str) -> Option<u32> {
+    let letters: Vec<char> = text.chars().collect();
+    if letters.len() != 10 {
+        return None;
+    }
+    let (_, mut mode) = FILE_TYPES
+        .iter()
+        .find(|(letter, _)| *letter == letters[0])?;
+    // The special bit of each triplet shows in place of the execute letter
+    let special = [(0o4000, 's'), (0o2000, 's'), (0o1000, 't')];
+    for (triplet, (special_bit, special_letter)) in special.into_iter().enumerate() {
+        let shift = 6 - triplet * 3;
+        let [read, write, execute] = [
+            letters[1 + triplet * 3],
+            letters[2 + triplet * 3],
+            letters[3 + triplet * 3],
+        ];
+        match read {
+            'r' => mode |= 0o4 << shift,
+            '-' => (),
+            _ => return None,
+        }
+        match write {
+            'w' => mode |= 0o2 << shift,
+            '-' => (),
+            _ => return None,
+        }
+        match execute {
+            'x' => mode |= 0o1 << shift,
+            '-' => (),
+            letter if letter == special_letter => mode |= special_bit | 0o1 << shift,
+            letter if letter == special_letter.to_ascii_uppercase() => mode |= special_bit,
+            _ => return None,
+        }
+    }
+    Some(mode)
+}
+/// Gets the string `ls -l` prints for `mode`
+pub(crate) fn format_mode_string(mode: u32) -> String {
+    let file_type = FILE_TYPES
+        .iter()
+        .find(|(_, file_type)| *file_type == mode & S_IFMT)
+        .map_or('?', |(letter, _)| *letter);
+    let mut text = String::from(file_type);
+    let special = [(0o4000, 's'), (0o2000, 's'), (0o1000, 't')];
+    for (triplet, (special_bit, special_letter)) in special.into_iter().enumerate() {
+        let bits = mode >> (6 - triplet * 3) & 0o7;
+        text.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        text.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        text.push(match (bits & 0o1 != 0, mode & special_bit != 0) {
+            (true, true) => special_letter,
+            (false, true) => special_letter.to_ascii_uppercase(),
+            (true, false) => 'x',
+            (false, false) => '-',
+        });
+    }
+    text
+}
+
+/// Fills `buffer`, or returns false if the reader ended before the first byte
+pub(crate) fn read_or_end(reader: &mut impl Read, buffer: &mut [u8], what: &str) -> Result<bool> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) if filled == 0 => return Ok(false),
+            Ok(0) => return Err(truncated(what)),
+            Ok(read) => filled += read,
+            Err(error) if error.kind() == std::io::ErrorKind::Interrupted => (),
+            Err(error) => return Err(io_error(error)),
+        }
+    }
+    Ok(true)
+}
+pub(crate) fn read_exact(reader: &mut impl Read, buffer: &mut [u8], what: &str) -> Result<()> {
+    reader
+        .read_exact(buffer)
+        .map_err(|error| match error.kind() {
+            std::io::ErrorKind::UnexpectedEof => truncated(what),
+            _ => io_error(error),
+        })
+}
+pub(crate) fn truncated(what: &str) -> Error {
+    Error {
+        kind: ErrorKind::InvalidData(format!("The {} is truncated", what)),
+    }
+}
+pub(crate) fn io_error(error: std::io::Error) -> Error {
+    Error {
+        kind: ErrorKind::Other(error.to_string()),
+    }
+}
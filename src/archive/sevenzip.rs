@@ -0,0 +1,173 @@
+//! 7z archives store a 32 bit attribute for every entry. The low 16 bits are the attribute word
+//! and, when the `0x8000` extension bit is set, the high 16 bits are the Unix mode of the file,
+//! as p7zip and 7-Zip on Linux write it. `7z l -slt` prints it as the letters of the attributes
+//! followed by the mode, like `A -rw-r--r--` or `D_ drwxr-xr-x`.
+//! ```
+//! use keypropdecode::archive::sevenzip::*;
+//! use keypropdecode::props;
+//!
+//! let attributes = to_7z_attributes(props!(file: hidden), Some(0o100644));
+//! assert_eq!(attributes, 0x81A4_8022);
+//! assert_eq!(from_7z_attributes(attributes), props!(file: hidden));
+//! assert_eq!(unix_mode(attributes), Some(0o100644));
+//! assert_eq!(format_slt_attributes(attributes), "HA_ -rw-r--r--");
+//!
+//! let entries = parse_slt("----------\nPath = docs\nFolder = +\nAttributes = D_ drwxr-xr-x\n").unwrap();
+//! assert_eq!(entries[0].path, "docs");
+//! assert_eq!(entries[0].props, props!(dir));
+//! ```
+use crate::error::*;
+use crate::props::constants::*;
+use crate::Props;
+
+/// The bit that marks a Unix mode in the high 16 bits
+pub const UNIX_EXTENSION: u32 = 0x8000;
+/// The letter `7z` prints for each of the low 16 bits, the `g_WinAttribChars` of p7zip
+const LETTERS: [char; 16] = [
+    'R', 'H', 'S', '8', 'D', 'A', 'd', 'N', 'T', 's', 'L', 'C', 'O', 'n', 'E', '_',
+];
+/// The letters 7-Zip 21 and later print instead for some bits, from `RHS8DAdNTsLCOIEV`
+const NEW_LETTERS: [(char, u32); 2] = [('I', NOT_CONTENT_INDEXED), ('V', UNIX_EXTENSION)];
+
+/// Gets the Props of the attribute of a 7z entry.
+/// When the low bits only have the extension bit, like some Unix archivers write them, the
+/// attributes come from the mode. Otherwise the mode only decides if it's a directory.
+pub fn from_7z_attributes(attributes: u32) -> Props {
+    let mode = unix_mode(attributes);
+    let mut word = attributes & 0xFFFF & !UNIX_EXTENSION;
+    let mut directory = word & DIRECTORY != 0;
+    if let Some(mode) = mode {
+        let from_mode = super::attributes_from_mode(mode);
+        directory |= from_mode & DIRECTORY != 0;
+        if word == 0 {
+            word = from_mode;
+        }
+    }
+    Props::from(crate::backends::with_element_type(word, directory))
+}
+/// Gets the Unix mode of the attribute of a 7z entry, if it has one
+pub fn unix_mode(attributes: u32) -> Option<u32> {
+    match attributes & UNIX_EXTENSION {
+        0 => None,
+        _ => Some(attributes >> 16),
+    }
+}
+/// Gets the attribute of a 7z entry with `props` and, if there's one, a Unix mode.
+/// The attributes above the low 16 bits are lost.
+pub fn to_7z_attributes(props: Props, mode: Option<u32>) -> u32 {
+    let word = u32::from(props) & 0xFFFF & !UNIX_EXTENSION;
+    match mode {
+        Some(mode) => word | UNIX_EXTENSION | (mode & 0xFFFF) << 16,
+        None => word,
+    }
+}
+/// Gets the string `7z l -slt` prints for the attribute of an entry
+pub fn format_slt_attributes(attributes: u32) -> String {
+    let mut text: String = LETTERS
+        .iter()
+        .enumerate()
+        .filter(|(bit, _)| attributes & 1 << bit != 0)
+        .map(|(_, letter)| letter)
+        .collect();
+    if let Some(mode) = unix_mode(attributes) {
+        text.push(' ');
+        text.push_str(&super::format_mode_string(mode));
+    }
+    text
+}
+/// Gets the attribute of an entry from the string `7z l -slt` prints, like `RA` or
+/// `A_ -rw-r--r--`. When there's a mode the extension bit is set, even if it isn't printed.
+/// The letters of both p7zip and 7-Zip 21 are accepted.
+pub fn parse_slt_attributes(text: &str) -> Result<u32> {
+    let invalid = || Error {
+        kind: ErrorKind::InvalidData(format!("\"{}\" isn't a 7z attribute string", text)),
+    };
+    let (letters, mode) = match text.split_once(' ') {
+        Some((letters, mode)) => (
+            letters,
+            Some(super::parse_mode_string(mode).ok_or_else(invalid)?),
+        ),
+        None => (text, None),
+    };
+    let mut attributes = 0;
+    for letter in letters.chars() {
+        attributes |= match LETTERS.iter().position(|known| *known == letter) {
+            Some(bit) => 1 << bit,
+            None => NEW_LETTERS
+                .iter()
+                .find(|(known, _)| *known == letter)
+                .map(|(_, attribute)| *attribute)
+                .ok_or_else(invalid)?,
+        };
+    }
+    if let Some(mode) = mode {
+        attributes |= UNIX_EXTENSION | mode << 16;
+    }
+    Ok(attributes)
+}
+
+/// An entry of the `7z l -slt` output
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SltEntry {
+    /// The `Path` of the entry
+    pub path: String,
+    /// The parsed `Attributes`, if the archive stores them
+    pub attributes: Option<u32>,
+    /// The Props of the attributes or, without them, of `Folder`
+    pub props: Props,
+}
+/// Gets the entries of the output of `7z l -slt`.
+/// The properties of the archive itself, before the `----------` line, are skipped.
+pub fn parse_slt(output: &str) -> Result<Vec<SltEntry>> {
+    let output = output.replace("\r\n", "\n");
+    let entries = match output.split_once("\n----------") {
+        Some((_, entries)) => entries,
+        None => output.strip_prefix("----------").unwrap_or(&output),
+    };
+    let mut parsed = Vec::new();
+    for block in entries.split("\n\n") {
+        let mut path = None;
+        let mut attributes = None;
+        let mut folder = None;
+        for line in block.lines() {
+            let Some((key, value)) = line.split_once(" = ") else {
+                continue;
+            };
+            match key {
+                "Path" => path = Some(value.to_string()),
+                "Attributes" => attributes = Some(parse_slt_attributes(value.trim_end())?),
+                "Folder" => folder = Some(value.trim_end() == "+"),
+                _ => (),
+            }
+        }
+        let Some(path) = path else {
+            continue;
+        };
+        let props = match (attributes, folder) {
+            (Some(attributes), folder) => {
+                let props = from_7z_attributes(attributes);
+                match folder {
+                    Some(true) if !props.is_directory() => {
+                        Props::from(crate::backends::with_element_type(u32::from(props), true))
+                    }
+                    _ => props,
+                }
+            }
+            (None, Some(folder)) => Props::from(crate::backends::with_element_type(0, folder)),
+            (None, None) => {
+                return Err(Error {
+                    kind: ErrorKind::AttributesNotFound(format!(
+                        "{} has no Attributes or Folder",
+                        path
+                    )),
+                })
+            }
+        };
+        parsed.push(SltEntry {
+            path,
+            attributes,
+            props,
+        });
+    }
+    Ok(parsed)
+}
@@ -0,0 +1,380 @@
+//! Windows container image layers are tar streams where every entry carries its attributes in
+//! the `MSWINDOWS.fileattr` PAX record, as a decimal number, next to the security descriptor in
+//! `MSWINDOWS.rawsd`. [`TarEntries`](crate::archive::tar::TarEntries) streams a layer giving the
+//! Props of every entry, and [`fileattr_record`](crate::archive::tar::fileattr_record) makes the
+//! record for a new one.
+//! ```
+//! use keypropdecode::archive::tar::*;
+//! use keypropdecode::props;
+//!
+//! let (key, value) = fileattr_record(props!(file: hidden, system)).unwrap();
+//! assert_eq!((key, value.as_str()), ("MSWINDOWS.fileattr", "38"));
+//! assert_eq!(encode_pax_records(&[(key, &value)]), b"25 MSWINDOWS.fileattr=38\n");
+//! // Windows can't restore the compressed attribute from a layer
+//! assert!(fileattr_record(props!(file: compressed)).is_err());
+//! ```
+//! ```no_run
+//! use keypropdecode::archive::tar::TarEntries;
+//!
+//! let layer = std::fs::File::open("layer.tar").unwrap();
+//! for entry in TarEntries::new(layer) {
+//!     let entry = entry.unwrap();
+//!     if let Some(props) = entry.props {
+//!         println!("{} {}", props, entry.path);
+//!     }
+//! }
+//! ```
+use std::collections::BTreeMap;
+use std::io::Read;
+
+use super::{io_error, read_exact, read_or_end};
+use crate::error::*;
+use crate::props::constants::*;
+use crate::Props;
+
+/// The PAX record with the attribute word
+pub const FILEATTR_RECORD: &str = "MSWINDOWS.fileattr";
+/// The PAX record with the security descriptor, in base64
+pub const RAWSD_RECORD: &str = "MSWINDOWS.rawsd";
+/// The PAX record that marks a directory symbolic link as a mount point
+pub const MOUNTPOINT_RECORD: &str = "MSWINDOWS.mountpoint";
+/// The attributes Windows sets when restoring a layer entry.
+/// The rest need their own APIs, and restoring a layer doesn't call them.
+pub const RESTORABLE_ATTRIBUTES: u32 = READ_ONLY
+    | HIDDEN
+    | SYSTEM
+    | DIRECTORY
+    | ARCHIVE
+    | NORMAL
+    | TEMPORARY
+    | OFFLINE
+    | NOT_CONTENT_INDEXED
+    | REPARSE;
+
+/// Gets the attribute word of the value of an `MSWINDOWS.fileattr` record
+pub fn parse_fileattr(value: &str) -> Result<u32> {
+    let invalid = || Error {
+        kind: ErrorKind::InvalidData(format!("\"{}\" isn't a valid {}", value, FILEATTR_RECORD)),
+    };
+    if value.is_empty() || !value.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(invalid());
+    }
+    value.parse().map_err(|_| invalid())
+}
+/// Checks that Windows restores every attribute of `props` from a layer.
+/// Returns a `NotSupported` error naming the ones it doesn't.
+pub fn validate_for_restore(props: Props) -> Result<()> {
+    let attributes: u32 = props.into();
+    let unsupported = attributes & !RESTORABLE_ATTRIBUTES;
+    if unsupported != 0 {
+        return Err(Error {
+            kind: ErrorKind::NotSupported(format!(
+                "Windows doesn't restore the attributes {:#x} from a layer",
+                unsupported
+            )),
+        });
+    }
+    Ok(())
+}
+/// Gets the `MSWINDOWS.fileattr` record of `props`, after checking that Windows restores them
+pub fn fileattr_record(props: Props) -> Result<(&'static str, String)> {
+    validate_for_restore(props)?;
+    Ok((FILEATTR_RECORD, u32::from(props).to_string()))
+}
+/// Gets the data of a PAX extended header with `records`, each one prefixed by its own length
+pub fn encode_pax_records(records: &[(&str, &str)]) -> Vec<u8> {
+    let mut data = Vec::new();
+    for (key, value) in records {
+        // " key=value\n", plus the digits of the length, which count themselves
+        let rest = key.len() + value.len() + 3;
+        let mut length = rest + rest.to_string().len();
+        if length.to_string().len() != rest.to_string().len() {
+            length = rest + length.to_string().len();
+        }
+        data.extend(format!("{} {}={}\n", length, key, value).into_bytes());
+    }
+    data
+}
+/// Gets the records of the data of a PAX extended header, in order
+pub fn parse_pax_records(data: &[u8]) -> Result<Vec<(String, String)>> {
+    let invalid = || Error {
+        kind: ErrorKind::InvalidData("The PAX extended header is malformed".to_string()),
+    };
+    let mut records = Vec::new();
+    let mut rest = data;
+    // Headers are padded with nul bytes
+    while rest.first().is_some_and(|byte| *byte != 0) {
+        let space = rest
+            .iter()
+            .position(|byte| *byte == b' ')
+            .ok_or_else(invalid)?;
+        let length: usize = std::str::from_utf8(&rest[..space])
+            .ok()
+            .and_then(|length| length.parse().ok())
+            .filter(|length| *length > space + 1 && *length <= rest.len())
+            .ok_or_else(invalid)?;
+        let record = rest[space + 1..length]
+            .strip_suffix(b"\n")
+            .ok_or_else(invalid)?;
+        let record = std::str::from_utf8(record).map_err(|_| invalid())?;
+        let (key, value) = record.split_once('=').ok_or_else(invalid)?;
+        records.push((key.to_string(), value.to_string()));
+        rest = &rest[length..];
+    }
+    Ok(records)
+}
+
+/// An entry of a tar stream
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct TarEntry {
+    /// The path of the entry, from the `path` record when there's one
+    pub path: String,
+    /// The type flag of the header, like `b'0'` for files and `b'5'` for directories
+    pub entry_type: u8,
+    /// The size of the data of the entry
+    pub size: u64,
+    /// The mode of the header
+    pub mode: u32,
+    /// The PAX records of the entry, the global ones included
+    pub records: BTreeMap<String, String>,
+    /// The Props of the `MSWINDOWS.fileattr` record, if there's one.
+    /// The type flag decides if it's a directory.
+    pub props: Option<Props>,
+}
+
+const BLOCK: usize = 512;
+const WHAT: &str = "tar stream";
+/// The biggest PAX extended header or GNU long name that is read, since their size comes from
+/// the stream
+const MAX_HEADER_DATA: u64 = 16 * 1024 * 1024;
+
+/// Reads the entries of a tar stream one by one, skipping their data.
+/// An entry with an invalid `MSWINDOWS.fileattr` gives an error and the next one is read, but
+/// after any other error the iterator ends.
+pub struct TarEntries<R> {
+    reader: R,
+    global: BTreeMap<String, String>,
+    done: bool,
+}
+impl<R: Read> TarEntries<R> {
+    /// Reads the entries from the start of `reader`
+    pub fn new(reader: R) -> Self {
+        TarEntries {
+            reader,
+            global: BTreeMap::new(),
+            done: false,
+        }
+    }
+    /// Reads headers until one of an entry, returning it with the records that apply to it
+    fn next_header(&mut self) -> Result<Option<(Header, BTreeMap<String, String>)>> {
+        let mut records = self.global.clone();
+        let mut long_name = None;
+        loop {
+            let mut block = [0; BLOCK];
+            if !read_or_end(&mut self.reader, &mut block, WHAT)?
+                || block.iter().all(|byte| *byte == 0)
+            {
+                return Ok(None);
+            }
+            let header = Header::parse(&block)?;
+            match header.entry_type {
+                b'x' | b'g' => {
+                    let data = self.read_data(header.size)?;
+                    for (key, value) in parse_pax_records(&data)? {
+                        if header.entry_type == b'g' {
+                            self.global.insert(key.clone(), value.clone());
+                        }
+                        records.insert(key, value);
+                    }
+                }
+                // GNU long names and long link names
+                b'L' => {
+                    let data = self.read_data(header.size)?;
+                    let end = data
+                        .iter()
+                        .position(|byte| *byte == 0)
+                        .unwrap_or(data.len());
+                    long_name = Some(String::from_utf8_lossy(&data[..end]).into_owned());
+                }
+                b'K' => {
+                    self.skip_data(header.size)?;
+                }
+                _ => {
+                    let mut header = header;
+                    if let Some(long_name) = long_name {
+                        header.path = long_name;
+                    }
+                    return Ok(Some((header, records)));
+                }
+            }
+        }
+    }
+    /// Reads the data of an extended header, with its padding
+    fn read_data(&mut self, size: u64) -> Result<Vec<u8>> {
+        if size > MAX_HEADER_DATA {
+            return Err(Error {
+                kind: ErrorKind::InvalidData(format!(
+                    "A tar extended header of {} bytes is too big",
+                    size
+                )),
+            });
+        }
+        let mut data = vec![0; padded(size)? as usize];
+        read_exact(&mut self.reader, &mut data, WHAT)?;
+        data.truncate(size as usize);
+        Ok(data)
+    }
+    fn skip_data(&mut self, size: u64) -> Result<()> {
+        let padded = padded(size)?;
+        let skipped = std::io::copy(&mut (&mut self.reader).take(padded), &mut std::io::sink())
+            .map_err(io_error)?;
+        if skipped != padded {
+            return Err(super::truncated(WHAT));
+        }
+        Ok(())
+    }
+}
+impl<R: Read> Iterator for TarEntries<R> {
+    type Item = Result<TarEntry>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let (header, records) = match self.next_header() {
+            Ok(Some(found)) => found,
+            Ok(None) => {
+                self.done = true;
+                return None;
+            }
+            Err(error) => {
+                self.done = true;
+                return Some(Err(error));
+            }
+        };
+        // The size record replaces the size of the header, which can't hold 8 GiB or more
+        let size = match records.get("size").map(|size| size.parse::<u64>()) {
+            Some(Ok(size)) => size,
+            Some(Err(_)) => {
+                self.done = true;
+                return Some(Err(Error {
+                    kind: ErrorKind::InvalidData(
+                        "The size record of a tar entry isn't a number".to_string(),
+                    ),
+                }));
+            }
+            None => header.size,
+        };
+        // Links and directories have no data, whatever the size says
+        let has_data = !matches!(header.entry_type, b'1' | b'2' | b'5');
+        if has_data {
+            if let Err(error) = self.skip_data(size) {
+                self.done = true;
+                return Some(Err(error));
+            }
+        }
+        let props = match records
+            .get(FILEATTR_RECORD)
+            .map(|value| parse_fileattr(value))
+        {
+            Some(Err(error)) => return Some(Err(error)),
+            Some(Ok(attributes)) => Some(Props::from(crate::backends::with_element_type(
+                attributes,
+                header.entry_type == b'5',
+            ))),
+            None => None,
+        };
+        Some(Ok(TarEntry {
+            path: records.get("path").cloned().unwrap_or(header.path),
+            entry_type: header.entry_type,
+            size,
+            mode: header.mode,
+            records,
+            props,
+        }))
+    }
+}
+
+struct Header {
+    path: String,
+    mode: u32,
+    size: u64,
+    entry_type: u8,
+}
+impl Header {
+    fn parse(block: &[u8; BLOCK]) -> Result<Header> {
+        let stored_checksum = octal(&block[148..156])?;
+        // The checksum is computed with its own field filled with spaces
+        let checksum: u64 = block
+            .iter()
+            .enumerate()
+            .map(|(index, byte)| match index {
+                148..156 => b' ' as u64,
+                _ => *byte as u64,
+            })
+            .sum();
+        if checksum != stored_checksum {
+            return Err(Error {
+                kind: ErrorKind::InvalidData("The checksum of a tar header is wrong".to_string()),
+            });
+        }
+        let mut path = text(&block[0..100]);
+        // GNU headers have "ustar  \0" instead, and their times where the prefix would be
+        if &block[257..263] == b"ustar\0" {
+            let prefix = text(&block[345..500]);
+            if !prefix.is_empty() {
+                path = format!("{}/{}", prefix, path);
+            }
+        }
+        Ok(Header {
+            path,
+            mode: octal(&block[100..108])? as u32,
+            size: number(&block[124..136])?,
+            entry_type: match block[156] {
+                // Old tar versions use a nul byte for files
+                0 => b'0',
+                entry_type => entry_type,
+            },
+        })
+    }
+}
+fn text(field: &[u8]) -> String {
+    let end = field
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+/// Reads an octal field, or a base 256 one if the high bit is set, like GNU tar writes sizes
+fn number(field: &[u8]) -> Result<u64> {
+    if field[0] & 0x80 != 0 {
+        // Base-256, which only fits in a u64 with up to 8 significant bytes
+        return field[1..]
+            .iter()
+            .try_fold(field[0] as u64 & 0x7F, |value, byte| {
+                value.checked_mul(256).map(|value| value | *byte as u64)
+            })
+            .ok_or_else(|| Error {
+                kind: ErrorKind::InvalidData(format!(
+                    "The base-256 number {:02x?} doesn't fit in 64 bits",
+                    field
+                )),
+            });
+    }
+    octal(field)
+}
+fn octal(field: &[u8]) -> Result<u64> {
+    let digits = text(field);
+    let digits = digits.trim_matches([' ', '\0']);
+    if digits.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(digits, 8).map_err(|_| Error {
+        kind: ErrorKind::InvalidData(format!("\"{}\" isn't an octal number", digits)),
+    })
+}
+fn padded(size: u64) -> Result<u64> {
+    size.checked_next_multiple_of(BLOCK as u64)
+        .ok_or_else(|| Error {
+            kind: ErrorKind::InvalidData(format!("A tar entry of {} bytes is too big", size)),
+        })
+}
@@ -0,0 +1,50 @@
+use crate::archive::fflags::*;
+use crate::props::constants::*;
+use crate::Props;
+
+#[test]
+fn canonical_names() {
+    let flags = FileFlags::parse("rdonly,hidden,system,archive,offline,sparse,reparse");
+    assert_eq!(flags.set, FFLAGS_ATTRIBUTES);
+    assert_eq!(
+        flags.to_string(),
+        "rdonly,hidden,system,archive,offline,sparse,reparse"
+    );
+    assert!(flags.others.is_empty());
+}
+#[test]
+fn aliases_and_separators() {
+    let flags = FileFlags::parse("uhidden  usystem\turdonly,uarch");
+    assert_eq!(flags.set, HIDDEN | SYSTEM | READ_ONLY | ARCHIVE);
+    assert_eq!(flags.to_string(), "rdonly,hidden,system,archive");
+}
+#[test]
+fn clearing() {
+    let flags = FileFlags::parse("hidden,nohidden,nordonly,uchg");
+    assert_eq!(flags.set, 0);
+    assert_eq!(flags.clear, HIDDEN | READ_ONLY);
+    assert_eq!(flags.to_string(), "nordonly,nohidden,uchg");
+    let props = Props::from(ARCHIVE | READ_ONLY | HIDDEN | SYSTEM);
+    assert_eq!(flags.apply(props), Props::from(ARCHIVE | SYSTEM));
+}
+#[test]
+fn unknown_names_are_kept() {
+    let flags = FileFlags::parse("nodump,sappnd,hidden,opaque");
+    assert_eq!(flags.others, ["nodump", "sappnd", "opaque"]);
+    assert_eq!(flags.props(true), Props::from(DIRECTORY | HIDDEN));
+    assert_eq!(FileFlags::parse(&flags.to_string()), flags);
+}
+#[test]
+fn empty_lists() {
+    assert_eq!(FileFlags::parse(""), FileFlags::default());
+    assert_eq!(FileFlags::parse("none"), FileFlags::default());
+    assert_eq!(FileFlags::default().to_string(), "");
+}
+#[test]
+fn from_props() {
+    let props = Props::from(ARCHIVE | TEMPORARY | SPARSE | COMPRESSED);
+    let flags = FileFlags::from(props);
+    assert_eq!(flags.to_string(), "archive,sparse");
+    // The attributes without a name are lost
+    assert_eq!(flags.props(false), Props::from(ARCHIVE | SPARSE));
+}
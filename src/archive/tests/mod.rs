@@ -0,0 +1,4 @@
+mod fflags;
+mod sevenzip;
+mod tar;
+mod zip;
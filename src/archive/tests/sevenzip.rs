@@ -0,0 +1,145 @@
+use crate::archive::sevenzip::*;
+use crate::error::*;
+use crate::props::constants::*;
+use crate::Props;
+
+const SLT: &str = "
+7-Zip 23.01 (x64) : Copyright (c) 1999-2023 Igor Pavlov : 2023-06-20
+
+Listing archive: layer.7z
+
+--
+Path = layer.7z
+Type = 7z
+Physical Size = 1234
+Headers Size = 200
+Method = LZMA2:24
+Solid = +
+Blocks = 1
+
+----------
+Path = docs
+Size = 0
+Packed Size = 0
+Modified = 2024-05-01 10:00:00
+Attributes = D_ drwxr-xr-x
+CRC = 
+Encrypted = -
+Method = 
+Block = 
+
+----------
+Path = docs/hidden.txt
+Size = 12
+Attributes = RHA
+Encrypted = -
+
+Path = docs/run.sh
+Folder = -
+Attributes = A_ -rwsr-x--x
+
+Path = docs/from-unix.txt
+Attributes =  -r--r--r--
+
+Path = empty
+Folder = +
+";
+
+#[test]
+fn attribute_words() {
+    assert_eq!(from_7z_attributes(0x21), Props::from(ARCHIVE | READ_ONLY));
+    // The directory bit can come from the mode alone
+    let directory = 0o040755 << 16 | UNIX_EXTENSION;
+    assert_eq!(from_7z_attributes(directory), Props::from(DIRECTORY));
+    assert_eq!(unix_mode(directory), Some(0o040755));
+    assert_eq!(unix_mode(0x10), None);
+    // Without the extension bit the high bits are ignored
+    assert_eq!(
+        from_7z_attributes(0o040755 << 16 | 0x20),
+        Props::from(ARCHIVE)
+    );
+    assert_eq!(to_7z_attributes(Props::from(ARCHIVE | PINNED), None), 0x20);
+}
+#[test]
+fn attribute_strings() {
+    assert_eq!(parse_slt_attributes("RHA"), Ok(0x23));
+    assert_eq!(
+        parse_slt_attributes("D_ drwxr-xr-x"),
+        Ok(0x8010 | 0o040755 << 16)
+    );
+    assert_eq!(
+        parse_slt_attributes("A -rw-r--r--"),
+        Ok(0x8020 | 0o100644 << 16)
+    );
+    assert!(parse_slt_attributes("AQ").is_err());
+    assert!(parse_slt_attributes("A -rw-r--r-").is_err());
+    for attributes in [
+        0x23,
+        0x8010 | 0o040755 << 16,
+        0x8020 | 0o104751 << 16,
+        0x4A20,
+    ] {
+        assert_eq!(
+            parse_slt_attributes(&format_slt_attributes(attributes)),
+            Ok(attributes)
+        );
+    }
+    assert_eq!(format_slt_attributes(0x4A20), "AsCE");
+}
+#[test]
+fn slt_output() {
+    let entries = parse_slt(SLT).unwrap();
+    let paths: Vec<&str> = entries.iter().map(|entry| entry.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "docs",
+            "docs/hidden.txt",
+            "docs/run.sh",
+            "docs/from-unix.txt",
+            "empty"
+        ]
+    );
+    assert_eq!(entries[0].props, Props::from(DIRECTORY));
+    assert_eq!(entries[1].props, Props::from(ARCHIVE | READ_ONLY | HIDDEN));
+    assert_eq!(unix_mode(entries[2].attributes.unwrap()), Some(0o104751));
+    assert_eq!(entries[3].props, Props::from(ARCHIVE | READ_ONLY));
+    assert_eq!(entries[4].attributes, None);
+    assert_eq!(entries[4].props, Props::from(DIRECTORY));
+}
+#[test]
+fn not_content_indexed() {
+    // An entry of 7-Zip on Windows for a file left out of the search index
+    let entries = parse_slt(
+        "----------\nPath = index.html\nSize = 1024\nPacked Size = 512\n\
+         Modified = 2024-03-02 09:15:30.1234567\nAttributes = An\nCRC = 3610A686\n\
+         Encrypted = -\nMethod = LZMA2:24\nBlock = 0\n",
+    )
+    .unwrap();
+    assert_eq!(entries[0].attributes, Some(0x2020));
+    assert_eq!(entries[0].props, Props::from(ARCHIVE | NOT_CONTENT_INDEXED));
+    assert_eq!(format_slt_attributes(0x2020), "An");
+    // 7-Zip 21 and later print I instead of n
+    assert_eq!(
+        parse_slt_attributes("AI"),
+        Ok(ARCHIVE | NOT_CONTENT_INDEXED)
+    );
+    assert_eq!(
+        parse_slt_attributes("DV drwxr-xr-x"),
+        parse_slt_attributes("D_ drwxr-xr-x")
+    );
+}
+#[test]
+fn windows_line_endings() {
+    let entries = parse_slt(&SLT.replace('\n', "\r\n")).unwrap();
+    assert_eq!(entries.len(), 5);
+}
+#[test]
+fn entries_without_attributes() {
+    assert_eq!(
+        parse_slt("----------\nPath = a.txt\nSize = 1\n"),
+        Err(Error {
+            kind: ErrorKind::AttributesNotFound("a.txt has no Attributes or Folder".to_string())
+        })
+    );
+}
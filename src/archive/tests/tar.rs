@@ -0,0 +1,225 @@
+use crate::archive::tar::*;
+use crate::error::*;
+use crate::props::constants::*;
+use crate::Props;
+
+fn header(path: &str, entry_type: u8, size: usize) -> Vec<u8> {
+    let mut block = vec![0; 512];
+    block[..path.len()].copy_from_slice(path.as_bytes());
+    block[100..108].copy_from_slice(b"0000644\0");
+    block[124..136].copy_from_slice(format!("{:011o}\0", size).as_bytes());
+    block[156] = entry_type;
+    block[257..263].copy_from_slice(b"ustar\0");
+    seal(&mut block);
+    block
+}
+/// Writes the checksum of a header
+fn seal(block: &mut [u8]) {
+    block[148..156].copy_from_slice(b"        ");
+    let checksum: u32 = block.iter().map(|byte| *byte as u32).sum();
+    block[148..156].copy_from_slice(format!("{:06o}\0 ", checksum).as_bytes());
+}
+fn entry(tar: &mut Vec<u8>, path: &str, entry_type: u8, data: &[u8]) {
+    tar.extend(header(path, entry_type, data.len()));
+    tar.extend(data);
+    tar.resize(tar.len().div_ceil(512) * 512, 0);
+}
+fn layer() -> Vec<u8> {
+    let mut tar = Vec::new();
+    entry(
+        &mut tar,
+        "PaxHeaders/global",
+        b'g',
+        &encode_pax_records(&[("MSWINDOWS.rawsd", "AQAEgA==")]),
+    );
+    entry(
+        &mut tar,
+        "PaxHeaders/Files",
+        b'x',
+        &encode_pax_records(&[(FILEATTR_RECORD, "16")]),
+    );
+    entry(&mut tar, "Files", b'5', b"");
+    let long_path = format!("Files/{}.txt", "a".repeat(120));
+    entry(
+        &mut tar,
+        "PaxHeaders/long",
+        b'x',
+        &encode_pax_records(&[("path", &long_path), (FILEATTR_RECORD, "8227")]),
+    );
+    entry(&mut tar, "Files/aaaa", b'0', &[7; 700]);
+    entry(&mut tar, "Files/plain.txt", b'0', b"no attributes");
+    tar.extend([0; 1024]);
+    tar
+}
+
+#[test]
+fn fileattr_values() {
+    assert_eq!(parse_fileattr("8227"), Ok(0x2023));
+    assert!(parse_fileattr("").is_err());
+    assert!(parse_fileattr("+32").is_err());
+    assert!(parse_fileattr("0x20").is_err());
+    assert!(parse_fileattr("4294967296").is_err());
+}
+#[test]
+fn restore_validation() {
+    let props = Props::from(ARCHIVE | READ_ONLY | HIDDEN | TEMPORARY | NOT_CONTENT_INDEXED);
+    assert_eq!(
+        fileattr_record(props),
+        Ok((FILEATTR_RECORD, "8483".to_string()))
+    );
+    assert_eq!(
+        fileattr_record(Props::from(ARCHIVE | ENCRYPTED | SPARSE)),
+        Err(Error {
+            kind: ErrorKind::NotSupported(
+                "Windows doesn't restore the attributes 0x4200 from a layer".to_string()
+            )
+        })
+    );
+}
+#[test]
+fn pax_records() {
+    // The length goes from 2 to 3 digits when counting itself
+    let value = "x".repeat(92);
+    let encoded = encode_pax_records(&[("key", &value)]);
+    assert_eq!(encoded.len(), 101);
+    assert!(encoded.starts_with(b"101 key="));
+    let shorter = "x".repeat(85);
+    let encoded = encode_pax_records(&[("key", &shorter)]);
+    assert_eq!(encoded.len(), 93);
+    let both = encode_pax_records(&[("key", &value), ("path", "a/b")]);
+    assert_eq!(
+        parse_pax_records(&both),
+        Ok(vec![
+            ("key".to_string(), value),
+            ("path".to_string(), "a/b".to_string())
+        ])
+    );
+    assert!(parse_pax_records(b"30 key=value\n").is_err());
+    assert!(parse_pax_records(b"12 keyvalue\n").is_err());
+}
+#[test]
+fn stream_a_layer() {
+    let entries: Vec<TarEntry> = TarEntries::new(layer().as_slice())
+        .collect::<Result<_>>()
+        .unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].path, "Files");
+    assert_eq!(entries[0].props, Some(Props::from(DIRECTORY)));
+    assert_eq!(entries[0].records["MSWINDOWS.rawsd"], "AQAEgA==");
+    assert_eq!(entries[1].path, format!("Files/{}.txt", "a".repeat(120)));
+    assert_eq!(entries[1].size, 700);
+    assert_eq!(
+        entries[1].props,
+        Some(Props::from(
+            ARCHIVE | READ_ONLY | HIDDEN | NOT_CONTENT_INDEXED
+        ))
+    );
+    // The global record applies to every entry, the extended ones only to the next
+    assert_eq!(entries[2].props, None);
+    assert_eq!(entries[2].records.len(), 1);
+}
+#[test]
+fn invalid_fileattr_keeps_reading() {
+    let mut tar = Vec::new();
+    entry(
+        &mut tar,
+        "PaxHeaders/a",
+        b'x',
+        &encode_pax_records(&[(FILEATTR_RECORD, "hidden")]),
+    );
+    entry(&mut tar, "a", b'0', b"");
+    entry(&mut tar, "b", b'0', b"");
+    let entries: Vec<Result<TarEntry>> = TarEntries::new(tar.as_slice()).collect();
+    assert_eq!(entries.len(), 2);
+    assert!(entries[0].is_err());
+    assert_eq!(entries[1].as_ref().unwrap().path, "b");
+}
+#[test]
+fn broken_streams() {
+    let mut tar = layer();
+    tar[10] ^= 1;
+    let entries: Vec<Result<TarEntry>> = TarEntries::new(tar.as_slice()).collect();
+    assert_eq!(
+        entries,
+        [Err(Error {
+            kind: ErrorKind::InvalidData("The checksum of a tar header is wrong".to_string())
+        })]
+    );
+    let tar = layer();
+    let entries: Vec<Result<TarEntry>> = TarEntries::new(&tar[..3000]).collect();
+    assert!(entries.last().unwrap().is_err());
+}
+#[test]
+fn size_record_replaces_the_header_size() {
+    let mut tar = Vec::new();
+    entry(
+        &mut tar,
+        "PaxHeaders/big",
+        b'x',
+        &encode_pax_records(&[("size", "700")]),
+    );
+    // Archivers leave the header size at 0 when it can't hold the real one
+    tar.extend(header("big", b'0', 0));
+    tar.extend([7; 1024]);
+    entry(&mut tar, "next", b'0', b"");
+    let entries: Vec<TarEntry> = TarEntries::new(tar.as_slice())
+        .collect::<Result<_>>()
+        .unwrap();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].size, 700);
+    assert_eq!(entries[1].path, "next");
+}
+#[test]
+fn huge_extended_headers() {
+    let mut tar = header("PaxHeaders/huge", b'x', 0);
+    tar[124..136].copy_from_slice(b"77777777777\0");
+    seal(&mut tar);
+    let entries: Vec<Result<TarEntry>> = TarEntries::new(tar.as_slice()).collect();
+    assert_eq!(
+        entries,
+        [Err(Error {
+            kind: ErrorKind::InvalidData(
+                "A tar extended header of 8589934591 bytes is too big".to_string()
+            )
+        })]
+    );
+    // A base 256 size that can't be padded
+    let mut tar = header("huge", b'0', 0);
+    tar[124..136].copy_from_slice(&[0xFF; 12]);
+    seal(&mut tar);
+    let entries: Vec<Result<TarEntry>> = TarEntries::new(tar.as_slice()).collect();
+    assert!(entries[0].is_err());
+}
+#[test]
+fn base_256_numbers() {
+    let mut tar = header("empty", b'0', 0);
+    tar[124..136].copy_from_slice(&[0x80, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    seal(&mut tar);
+    let entries: Vec<TarEntry> = TarEntries::new(tar.as_slice())
+        .collect::<Result<_>>()
+        .unwrap();
+    assert_eq!(entries[0].size, 0);
+    // 9 significant bytes don't fit in a u64
+    let mut tar = header("wrapped", b'0', 0);
+    tar[124..136].copy_from_slice(&[0x80, 0, 1, 0, 0, 0, 0, 0, 0, 0, 0, 0]);
+    seal(&mut tar);
+    let entries: Vec<Result<TarEntry>> = TarEntries::new(tar.as_slice()).collect();
+    assert!(matches!(
+        &entries[..],
+        [Err(Error {
+            kind: ErrorKind::InvalidData(_)
+        })]
+    ));
+}
+#[test]
+fn gnu_headers_have_no_prefix() {
+    let mut tar = header("gnu.txt", b'0', 0);
+    tar[257..265].copy_from_slice(b"ustar  \0");
+    // The access time of GNU headers
+    tar[345..357].copy_from_slice(b"14501234567\0");
+    seal(&mut tar);
+    let entries: Vec<TarEntry> = TarEntries::new(tar.as_slice())
+        .collect::<Result<_>>()
+        .unwrap();
+    assert_eq!(entries[0].path, "gnu.txt");
+}
@@ -0,0 +1,193 @@
+use std::io::Cursor;
+
+use crate::archive::zip::*;
+use crate::error::*;
+use crate::props::constants::*;
+use crate::Props;
+
+fn central_header(version_made_by: u16, external: u32, name: &str) -> Vec<u8> {
+    let mut header = Vec::new();
+    header.extend(0x0201_4b50u32.to_le_bytes());
+    header.extend(version_made_by.to_le_bytes());
+    header.extend(20u16.to_le_bytes());
+    // Flags with UTF-8 names
+    header.extend(0x0800u16.to_le_bytes());
+    header.extend([0; 18]);
+    header.extend((name.len() as u16).to_le_bytes());
+    // An extra field and a comment, which must be skipped
+    header.extend(4u16.to_le_bytes());
+    header.extend(2u16.to_le_bytes());
+    header.extend([0; 4]);
+    header.extend(external.to_le_bytes());
+    header.extend([0; 4]);
+    header.extend(name.as_bytes());
+    header.extend([0x99, 0x99, 0, 0]);
+    header.extend(b"hi");
+    header
+}
+/// An archive without local headers, which the reader never looks at
+fn archive(entries: &[(u16, u32, &str)], comment: &str) -> Vec<u8> {
+    let mut archive = b"local headers and data".to_vec();
+    let offset = archive.len() as u32;
+    for (version_made_by, external, name) in entries {
+        archive.extend(central_header(*version_made_by, *external, name));
+    }
+    let size = archive.len() as u32 - offset;
+    archive.extend(0x0605_4b50u32.to_le_bytes());
+    archive.extend([0; 4]);
+    archive.extend((entries.len() as u16).to_le_bytes());
+    archive.extend((entries.len() as u16).to_le_bytes());
+    archive.extend(size.to_le_bytes());
+    archive.extend(offset.to_le_bytes());
+    archive.extend((comment.len() as u16).to_le_bytes());
+    archive.extend(comment.as_bytes());
+    archive
+}
+
+#[test]
+fn hosts() {
+    assert_eq!(ZipHost::from_version_made_by(0x0314), ZipHost::Unix);
+    assert_eq!(ZipHost::from_version_made_by(0x0A3F), ZipHost::Ntfs);
+    assert_eq!(ZipHost::from_version_made_by(0x1E00), ZipHost::Other(30));
+    assert!(ZipHost::Vfat.uses_dos_attributes());
+    assert!(!ZipHost::Darwin.uses_dos_attributes());
+    assert_eq!(ZipHost::Hpfs.byte(), 6);
+}
+#[test]
+fn dos_attributes() {
+    assert_eq!(
+        from_external_attributes(0x0014, 0x10),
+        Props::from(DIRECTORY)
+    );
+    assert_eq!(
+        from_external_attributes(0x0A3F, 0x2007),
+        Props::from(ARCHIVE | READ_ONLY | HIDDEN | SYSTEM | NOT_CONTENT_INDEXED)
+    );
+    // p7zip adds a Unix mode to DOS hosts, which is ignored
+    assert_eq!(
+        from_external_attributes(0x0014, 0o100755 << 16 | 0x8020),
+        Props::from(ARCHIVE)
+    );
+    // Without a mode the bit is FILE_ATTRIBUTE_INTEGRITY_STREAM
+    assert_eq!(
+        from_external_attributes(0x0A3F, 0x8020),
+        Props::from(ARCHIVE | INTEGRITY_STREAM)
+    );
+}
+#[test]
+fn unix_modes() {
+    assert_eq!(
+        from_external_attributes(0x031E, 0o040755 << 16 | 0x10),
+        Props::from(DIRECTORY)
+    );
+    assert_eq!(
+        from_external_attributes(0x031E, 0o120777 << 16),
+        Props::from(ARCHIVE | REPARSE)
+    );
+    assert_eq!(
+        from_external_attributes(0x1314, 0o100644 << 16),
+        Props::from(ARCHIVE)
+    );
+    // Without a mode the low byte is used
+    assert_eq!(
+        from_external_attributes(0x031E, 0x01),
+        Props::from(ARCHIVE | READ_ONLY)
+    );
+}
+#[test]
+fn to_external() {
+    let props = Props::from(ARCHIVE | READ_ONLY | HIDDEN | COMPRESSED);
+    assert_eq!(to_external_attributes(props, ZipHost::MsDos), 0x23);
+    assert_eq!(to_external_attributes(props, ZipHost::Ntfs), 0x823);
+    assert_eq!(
+        to_external_attributes(props, ZipHost::Unix),
+        0o100444 << 16 | 0x01
+    );
+    let integrity = Props::from(ARCHIVE | INTEGRITY_STREAM);
+    assert_eq!(to_external_attributes(integrity, ZipHost::Ntfs), 0x8020);
+    assert_eq!(from_external_attributes(0x0A3F, 0x8020), integrity);
+    for host in [ZipHost::MsDos, ZipHost::Ntfs, ZipHost::Unix] {
+        let external = to_external_attributes(Props::from(DIRECTORY), host);
+        let version_made_by = (host.byte() as u16) << 8;
+        assert_eq!(
+            from_external_attributes(version_made_by, external),
+            Props::from(DIRECTORY)
+        );
+    }
+}
+#[test]
+fn read_whole_archive() {
+    let bytes = archive(
+        &[
+            (0x0A3F, 0x22, "docs/hidden.txt"),
+            (0x031E, 0o100644 << 16, "docs/"),
+            (0x0014, 0x10, "empty/"),
+        ],
+        "a comment with PK\x05\x06 inside",
+    );
+    let entries: Vec<ZipEntry> = CentralDirectory::open(Cursor::new(bytes))
+        .unwrap()
+        .collect::<Result<_>>()
+        .unwrap();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].name, "docs/hidden.txt");
+    assert_eq!(entries[0].props, Props::from(ARCHIVE | HIDDEN));
+    assert_eq!(entries[0].external_attributes, 0x22);
+    // The trailing slash makes it a directory even if the mode says otherwise
+    assert!(entries[1].props.is_directory());
+    assert_eq!(entries[2].props, Props::from(DIRECTORY));
+}
+#[test]
+fn streaming_from_the_central_directory() {
+    let bytes = archive(&[(0x0A3F, 0x21, "a.txt"), (0x0A3F, 0x20, "b.txt")], "");
+    let start = b"local headers and data".len();
+    let names: Vec<String> = CentralDirectory::new(&bytes[start..])
+        .map(|entry| entry.unwrap().name)
+        .collect();
+    assert_eq!(names, ["a.txt", "b.txt"]);
+}
+#[test]
+fn broken_archives() {
+    assert!(CentralDirectory::open(Cursor::new(b"not a zip".to_vec())).is_err());
+    let mut bytes = archive(&[(0x0A3F, 0x21, "a.txt")], "");
+    let start = b"local headers and data".len();
+    bytes.truncate(start + 30);
+    let entries: Vec<Result<ZipEntry>> = CentralDirectory::new(&bytes[start..]).collect();
+    assert_eq!(
+        entries,
+        [Err(Error {
+            kind: ErrorKind::InvalidData("The central directory is truncated".to_string())
+        })]
+    );
+}
+#[test]
+fn zipinfo_strings() {
+    assert_eq!(
+        parse_zipinfo_attributes("-rw-a--"),
+        Ok(Props::from(ARCHIVE))
+    );
+    assert_eq!(
+        parse_zipinfo_attributes("-r--ahs"),
+        Ok(Props::from(ARCHIVE | READ_ONLY | HIDDEN | SYSTEM))
+    );
+    assert_eq!(
+        parse_zipinfo_attributes("drwx---"),
+        Ok(Props::from(DIRECTORY))
+    );
+    assert_eq!(
+        parse_zipinfo_attributes("-r--r--r--"),
+        Ok(Props::from(ARCHIVE | READ_ONLY))
+    );
+    assert_eq!(
+        parse_zipinfo_attributes("lrwxrwxrwx"),
+        Ok(Props::from(ARCHIVE | REPARSE))
+    );
+    assert!(parse_zipinfo_attributes("-rw-a-x").is_err());
+    assert!(parse_zipinfo_attributes("rw").is_err());
+    assert!(parse_zipinfo_attributes("-xw-a--").is_err());
+    assert!(parse_zipinfo_attributes("-rwra--").is_err());
+    for attributes in ["-rw-a--", "-r--ahs", "drwx-h-"] {
+        let props = parse_zipinfo_attributes(attributes).unwrap();
+        assert_eq!(format_zipinfo_attributes(props), attributes);
+    }
+}
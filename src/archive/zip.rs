@@ -0,0 +1,377 @@
+//! Every ZIP central directory entry has a "version made by" field, whose high byte is the host
+//! that made it, and 32 bits of "external file attributes". When the host is MS-DOS, OS/2 HPFS,
+//! NTFS or VFAT they hold the attribute word in the low bits. Otherwise they hold the Unix mode
+//! in the high 16 bits, and zip tools also put the directory and read only bits in the low byte.
+//! ```
+//! use keypropdecode::archive::zip::*;
+//! use keypropdecode::props;
+//!
+//! assert_eq!(from_external_attributes(0x0A3F, 0x22), props!(file: hidden));
+//! assert_eq!(from_external_attributes(0x031E, 0o100444 << 16), props!(file: read_only));
+//! assert_eq!(to_external_attributes(props!(dir), ZipHost::Unix), 0o40755 << 16 | 0x10);
+//! assert_eq!(parse_zipinfo_attributes("-rw-ahs"), Ok(props!(file: hidden, system)));
+//! ```
+//! [`CentralDirectory`](crate::archive::zip::CentralDirectory) reads the entries of a whole
+//! archive without reading the compressed data.
+//! ```no_run
+//! use keypropdecode::archive::zip::CentralDirectory;
+//!
+//! let file = std::fs::File::open("layer.zip").unwrap();
+//! for entry in CentralDirectory::open(file).unwrap() {
+//!     let entry = entry.unwrap();
+//!     println!("{} {}", entry.props, entry.name);
+//! }
+//! ```
+use std::io::{Read, Seek, SeekFrom};
+
+use super::{io_error, read_exact, read_or_end};
+use crate::backends::fat::FAT_ATTRIBUTES;
+use crate::error::*;
+use crate::props::constants::*;
+use crate::reader::Reader;
+use crate::Props;
+
+/// The systems a ZIP entry can be made in, from the high byte of "version made by"
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ZipHost {
+    /// MS-DOS and OS/2 with FAT, 0
+    MsDos,
+    /// Unix, 3
+    Unix,
+    /// OS/2 HPFS, 6
+    Hpfs,
+    /// Windows NTFS, 10
+    Ntfs,
+    /// VFAT, 14
+    Vfat,
+    /// OS X, 19
+    Darwin,
+    /// Any other system
+    Other(u8),
+}
+impl ZipHost {
+    /// Gets the host of a "version made by" field
+    pub fn from_version_made_by(version_made_by: u16) -> Self {
+        match (version_made_by >> 8) as u8 {
+            0 => ZipHost::MsDos,
+            3 => ZipHost::Unix,
+            6 => ZipHost::Hpfs,
+            10 => ZipHost::Ntfs,
+            14 => ZipHost::Vfat,
+            19 => ZipHost::Darwin,
+            other => ZipHost::Other(other),
+        }
+    }
+    /// The value of the host in the high byte of "version made by"
+    pub fn byte(self) -> u8 {
+        match self {
+            ZipHost::MsDos => 0,
+            ZipHost::Unix => 3,
+            ZipHost::Hpfs => 6,
+            ZipHost::Ntfs => 10,
+            ZipHost::Vfat => 14,
+            ZipHost::Darwin => 19,
+            ZipHost::Other(other) => other,
+        }
+    }
+    /// Returns true if the external attributes of this host are the attribute word
+    pub fn uses_dos_attributes(self) -> bool {
+        matches!(
+            self,
+            ZipHost::MsDos | ZipHost::Hpfs | ZipHost::Ntfs | ZipHost::Vfat
+        )
+    }
+}
+
+/// The bit p7zip sets when it stores a Unix mode in the high 16 bits of a DOS host entry.
+/// Without a mode it's [`INTEGRITY_STREAM`].
+const UNIX_EXTENSION: u32 = 0x8000;
+
+/// Gets the Props of the external attributes of an entry made by the host in `version_made_by`.
+/// When a Unix mode is expected but it's 0, as some tools leave it, the low byte is used.
+pub fn from_external_attributes(version_made_by: u16, external: u32) -> Props {
+    let host = ZipHost::from_version_made_by(version_made_by);
+    let mode = external >> 16;
+    let attributes = if host.uses_dos_attributes() {
+        if mode != 0 && external & UNIX_EXTENSION != 0 {
+            external & 0xFFFF & !UNIX_EXTENSION
+        } else {
+            external & 0xFFFF
+        }
+    } else if mode == 0 {
+        external & 0xFFFF & !UNIX_EXTENSION
+    } else {
+        super::attributes_from_mode(mode)
+    };
+    Props::from(crate::backends::with_element_type(
+        attributes,
+        attributes & DIRECTORY != 0,
+    ))
+}
+/// Gets the external attributes to store `props` in an entry made by `host`.
+/// FAT hosts only get the FAT attributes, NTFS gets the whole low 16 bits and every other host
+/// gets a Unix mode with the directory and read only bits in the low byte, like zip does.
+pub fn to_external_attributes(props: Props, host: ZipHost) -> u32 {
+    let attributes: u32 = props.into();
+    match host {
+        ZipHost::MsDos | ZipHost::Hpfs | ZipHost::Vfat => attributes & FAT_ATTRIBUTES,
+        ZipHost::Ntfs => attributes & 0xFFFF,
+        _ => super::mode_from_attributes(attributes) << 16 | attributes & (DIRECTORY | READ_ONLY),
+    }
+}
+
+/// An entry of the central directory
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ZipEntry {
+    /// The path of the entry inside the archive, with `/` separators.
+    /// Names that aren't marked as UTF-8 are decoded as if they were, replacing invalid bytes.
+    pub name: String,
+    /// The "version made by" field
+    pub version_made_by: u16,
+    /// The "external file attributes" field
+    pub external_attributes: u32,
+    /// The Props of the external attributes. Names ending in `/` are always directories.
+    pub props: Props,
+}
+
+const CENTRAL_HEADER: u32 = 0x0201_4b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x0605_4b50;
+const ZIP64_END_LOCATOR: u32 = 0x0706_4b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY: u32 = 0x0606_4b50;
+const WHAT: &str = "central directory";
+/// The fixed size of the end of central directory record, without the comment
+const END_SIZE: u64 = 22;
+
+/// Reads the central directory entries one by one from a reader.
+/// After the first error the iterator ends.
+pub struct CentralDirectory<R> {
+    reader: R,
+    /// The entries left, if the end of central directory record was read
+    remaining: Option<u64>,
+    done: bool,
+}
+impl<R: Read> CentralDirectory<R> {
+    /// Reads the entries from the current position of `reader`, which must be the start of the
+    /// central directory, until something that isn't a central directory header is found
+    pub fn new(reader: R) -> Self {
+        CentralDirectory {
+            reader,
+            remaining: None,
+            done: false,
+        }
+    }
+    fn read_entry(&mut self) -> Result<Option<ZipEntry>> {
+        let mut signature = [0; 4];
+        if !read_or_end(&mut self.reader, &mut signature, WHAT)? {
+            return Ok(None);
+        }
+        if u32::from_le_bytes(signature) != CENTRAL_HEADER {
+            if self.remaining.is_some() {
+                return Err(invalid(
+                    "The central directory has fewer entries than it says",
+                ));
+            }
+            return Ok(None);
+        }
+        let mut header = [0; 42];
+        read_exact(&mut self.reader, &mut header, WHAT)?;
+        let mut fields = Reader::new(&header, "central directory header");
+        let version_made_by = fields.u16()?;
+        fields.skip(2)?;
+        let flags = fields.u16()?;
+        fields.skip(18)?;
+        let name_length = fields.u16()? as usize;
+        let extra_length = fields.u16()? as usize;
+        let comment_length = fields.u16()? as usize;
+        fields.skip(4)?;
+        let external_attributes = fields.u32()?;
+        let mut variable = vec![0; name_length + extra_length + comment_length];
+        read_exact(&mut self.reader, &mut variable, WHAT)?;
+        let name_bytes = &variable[..name_length];
+        // Bit 11 marks UTF-8 names, the rest are usually CP437 but often UTF-8 anyway
+        let name = match flags & 0x0800 {
+            0 => String::from_utf8_lossy(name_bytes).into_owned(),
+            _ => String::from_utf8(name_bytes.to_vec())
+                .map_err(|_| invalid("The name of a ZIP entry isn't valid UTF-8"))?,
+        };
+        let mut props = from_external_attributes(version_made_by, external_attributes);
+        if name.ends_with('/') && !props.is_directory() {
+            let attributes: u32 = props.into();
+            props = Props::from(crate::backends::with_element_type(attributes, true));
+        }
+        Ok(Some(ZipEntry {
+            name,
+            version_made_by,
+            external_attributes,
+            props,
+        }))
+    }
+}
+impl<R: Read + Seek> CentralDirectory<R> {
+    /// Finds the central directory of a whole archive through its end of central directory
+    /// record, ZIP64 included, and reads the entries from there
+    pub fn open(mut reader: R) -> Result<Self> {
+        let length = reader.seek(SeekFrom::End(0)).map_err(io_error)?;
+        let tail_length = length.min(END_SIZE + u16::MAX as u64);
+        let tail_start = length - tail_length;
+        let mut tail = vec![0; tail_length as usize];
+        seek(&mut reader, tail_start)?;
+        read_exact(&mut reader, &mut tail, WHAT)?;
+        let end = (0..tail.len().saturating_sub(END_SIZE as usize - 1))
+            .rev()
+            .find(|&position| {
+                tail[position..].starts_with(&END_OF_CENTRAL_DIRECTORY.to_le_bytes())
+                    && position
+                        + END_SIZE as usize
+                        + u16::from_le_bytes([tail[position + 20], tail[position + 21]]) as usize
+                        == tail.len()
+            })
+            .ok_or_else(|| invalid("There's no end of central directory record"))?;
+        let mut record = Reader::new(&tail[end..], "end of central directory record");
+        record.skip(10)?;
+        let mut entries = record.u16()? as u64;
+        record.skip(4)?;
+        let mut offset = record.u32()? as u64;
+        if entries == u16::MAX as u64 || offset == u32::MAX as u64 {
+            (entries, offset) = zip64_end(&mut reader, tail_start + end as u64)?;
+        }
+        seek(&mut reader, offset)?;
+        Ok(CentralDirectory {
+            reader,
+            remaining: Some(entries),
+            done: false,
+        })
+    }
+}
+impl<R: Read> Iterator for CentralDirectory<R> {
+    type Item = Result<ZipEntry>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done || self.remaining == Some(0) {
+            return None;
+        }
+        let entry = self.read_entry();
+        match &entry {
+            Ok(None) | Err(_) => self.done = true,
+            Ok(Some(_)) => {
+                if let Some(remaining) = &mut self.remaining {
+                    *remaining -= 1;
+                }
+            }
+        }
+        entry.transpose()
+    }
+}
+/// Reads the entry count and the offset of the central directory from the ZIP64 records
+fn zip64_end<R: Read + Seek>(reader: &mut R, end_position: u64) -> Result<(u64, u64)> {
+    let mut locator = [0; 20];
+    seek(reader, end_position.checked_sub(20).ok_or_else(no_zip64)?)?;
+    read_exact(reader, &mut locator, WHAT)?;
+    let mut locator = Reader::new(&locator, "ZIP64 end of central directory locator");
+    if locator.u32()? != ZIP64_END_LOCATOR {
+        return Err(no_zip64());
+    }
+    locator.skip(4)?;
+    let record_position = locator.u64()?;
+    let mut record = [0; 56];
+    seek(reader, record_position)?;
+    read_exact(reader, &mut record, WHAT)?;
+    let mut record = Reader::new(&record, "ZIP64 end of central directory record");
+    if record.u32()? != ZIP64_END_OF_CENTRAL_DIRECTORY {
+        return Err(no_zip64());
+    }
+    record.skip(28)?;
+    let entries = record.u64()?;
+    record.skip(8)?;
+    Ok((entries, record.u64()?))
+}
+
+/// Gets the Props of the attribute column `zipinfo` prints, either the one of DOS hosts like
+/// `-rw-a--` or `drwx---`, or a Unix mode like `-r--r--r--`
+pub fn parse_zipinfo_attributes(attributes: &str) -> Result<Props> {
+    let letters: Vec<char> = attributes.chars().collect();
+    let not_zipinfo = || {
+        invalid(&format!(
+            "\"{}\" isn't a zipinfo attribute string",
+            attributes
+        ))
+    };
+    let flag = |index: usize, set: char| match letters[index] {
+        '-' => Ok(false),
+        letter if letter == set => Ok(true),
+        _ => Err(not_zipinfo()),
+    };
+    let word = match letters.len() {
+        7 => {
+            let mut word = match letters[0] {
+                'd' => DIRECTORY,
+                '-' => 0,
+                _ => return Err(not_zipinfo()),
+            };
+            if letters[1] != 'r' {
+                return Err(not_zipinfo());
+            }
+            // Directories and programs are executable, which Props can't tell
+            flag(3, 'x')?;
+            if !flag(2, 'w')? {
+                word |= READ_ONLY;
+            }
+            for (index, set, attribute) in [(4, 'a', ARCHIVE), (5, 'h', HIDDEN), (6, 's', SYSTEM)] {
+                if flag(index, set)? {
+                    word |= attribute;
+                }
+            }
+            word
+        }
+        10 => super::attributes_from_mode(
+            super::parse_mode_string(attributes).ok_or_else(not_zipinfo)?,
+        ),
+        _ => return Err(not_zipinfo()),
+    };
+    Ok(Props::from(crate::backends::with_element_type(
+        word,
+        word & DIRECTORY != 0,
+    )))
+}
+/// Gets the attribute column `zipinfo` prints for `props` in an entry made by a DOS host.
+/// Only directories are marked executable: zipinfo also marks the names ending in `.exe`,
+/// `.com`, `.bat` and `.cmd`, but Props has no name.
+pub fn format_zipinfo_attributes(props: Props) -> String {
+    let attributes: u32 = props.into();
+    let letter = |attribute: u32, set: char| {
+        if attributes & attribute != 0 {
+            set
+        } else {
+            '-'
+        }
+    };
+    [
+        letter(DIRECTORY, 'd'),
+        'r',
+        if attributes & READ_ONLY != 0 {
+            '-'
+        } else {
+            'w'
+        },
+        letter(DIRECTORY, 'x'),
+        letter(ARCHIVE, 'a'),
+        letter(HIDDEN, 'h'),
+        letter(SYSTEM, 's'),
+    ]
+    .iter()
+    .collect()
+}
+
+fn seek(reader: &mut impl Seek, position: u64) -> Result<()> {
+    reader
+        .seek(SeekFrom::Start(position))
+        .map(|_| ())
+        .map_err(io_error)
+}
+fn no_zip64() -> Error {
+    invalid("The ZIP64 end of central directory record is missing")
+}
+fn invalid(message: &str) -> Error {
+    Error {
+        kind: ErrorKind::InvalidData(message.to_string()),
+    }
+}
@@ -0,0 +1,99 @@
+//! The CIFS client of Linux exposes the attributes the SMB server returns for every element of
+//! a `mount.cifs` share as the `user.cifs.dosattrib` extended attribute, also available as
+//! `user.smb3.dosattrib` on newer kernels.
+//! Its value is the attribute word in the byte order of the machine. Writing it sends the
+//! change to the server, which may refuse it.
+//! ```
+//! use keypropdecode::backends::cifs;
+//!
+//! let value = cifs::encode(0x1022);
+//! assert_eq!(cifs::decode(&value).unwrap(), 0x1022);
+//! ```
+use crate::error::*;
+
+#[cfg(target_os = "linux")]
+use super::{AttributeSink, AttributeSource, Links};
+#[cfg(target_os = "linux")]
+use crate::Props;
+#[cfg(target_os = "linux")]
+use std::path::Path;
+
+/// The names of the extended attribute, in the order they are tried
+pub const XATTR_NAMES: [&str; 2] = ["user.cifs.dosattrib", "user.smb3.dosattrib"];
+
+/// Gets the attribute word from the raw value of the extended attribute
+pub fn decode(bytes: &[u8]) -> Result<u32> {
+    match bytes {
+        [a, b, c, d] => Ok(u32::from_ne_bytes([*a, *b, *c, *d])),
+        _ => Err(Error {
+            kind: ErrorKind::InvalidData(format!(
+                "{} can't be {} bytes long",
+                XATTR_NAMES[0],
+                bytes.len()
+            )),
+        }),
+    }
+}
+/// Gets the raw value of the extended attribute for an attribute word
+pub fn encode(attributes: u32) -> Vec<u8> {
+    attributes.to_ne_bytes().to_vec()
+}
+/// The CIFS client as a backend.
+/// Writing asks the server to change the attributes, and it may refuse.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Cifs;
+#[cfg(target_os = "linux")]
+impl AttributeSource for Cifs {
+    fn read(&self, path: &Path, links: Links) -> Result<Props> {
+        let value = first_supported(|name| {
+            super::xattr::get(path, name, links).map_err(|error| not_cifs(error, path))
+        })?;
+        Ok(Props::from(super::with_element_type(
+            decode(&value)?,
+            super::is_directory(path, links),
+        )))
+    }
+}
+#[cfg(target_os = "linux")]
+impl AttributeSink for Cifs {
+    fn write(&self, path: &Path, props: Props, links: Links) -> Result<()> {
+        let value = encode(props.into());
+        first_supported(|name| {
+            super::xattr::set(path, name, &value, links).map_err(|error| not_cifs(error, path))
+        })
+    }
+}
+/// Gets the Props the server has for `path`, following symbolic links.
+#[cfg(target_os = "linux")]
+pub fn read(path: impl AsRef<Path>) -> Result<Props> {
+    Cifs.read(path.as_ref(), Links::Follow)
+}
+/// Asks the server to change the attributes of `path` to `props`, following symbolic links.
+#[cfg(target_os = "linux")]
+pub fn write(path: impl AsRef<Path>, props: Props) -> Result<()> {
+    Cifs.write(path.as_ref(), props, Links::Follow)
+}
+/// Tries the second name when the kernel doesn't know the first one
+#[cfg(target_os = "linux")]
+fn first_supported<T>(mut attempt: impl FnMut(&str) -> Result<T>) -> Result<T> {
+    match attempt(XATTR_NAMES[0]) {
+        Err(Error {
+            kind: ErrorKind::NotSupported(_),
+        }) => attempt(XATTR_NAMES[1]),
+        result => result,
+    }
+}
+/// The client answers for every element of the share, so if the attribute is missing the path
+/// is somewhere else
+#[cfg(target_os = "linux")]
+fn not_cifs(error: Error, path: &Path) -> Error {
+    match error.kind {
+        ErrorKind::AttributesNotFound(_) | ErrorKind::NotSupported(_) => Error {
+            kind: ErrorKind::NotSupported(format!(
+                "{} isn't in a share mounted with mount.cifs",
+                path.display()
+            )),
+        },
+        _ => error,
+    }
+}
@@ -0,0 +1,116 @@
+//! When the file system doesn't store Windows attributes at all, they can still be guessed from
+//! the metadata every Unix file system has. This backend does it always the same way, so tools
+//! running on different platforms get a consistent view:
+//!
+//! | Attribute | Set when |
+//! |-----------|----------|
+//! | directory | the element is a directory, otherwise it is a file (archive) |
+//! | read_only | the file has no write permission for anyone, or it is immutable (`chattr +i`) |
+//! | hidden    | the name starts with a dot (`.` and `..` excluded) |
+//! | system    | never |
+//! | device    | the element is a block or character device |
+//! | reparse   | the element is a symbolic link (only when links aren't followed) |
+//! | sparse    | the file has a hole (`SEEK_HOLE`, Linux only) |
+//! | compressed | the file system compresses it (`STATX_ATTR_COMPRESSED`, Linux only) |
+//! | encrypted | the file system encrypts it (`STATX_ATTR_ENCRYPTED`, Linux only) |
+//! | not_content_indexed | the file has the no dump flag (`chattr +d`, Linux only) |
+//!
+//! Every other attribute is always false. [`Auto`](crate::backends::Auto) uses it for the paths no other
+//! backend has attributes stored for.
+//! ```no_run
+//! use keypropdecode::backends::{emulation::MetadataEmulation, Links};
+//! use keypropdecode::Props;
+//!
+//! let props = Props::from_path_with("/home/user/.bashrc", &MetadataEmulation, Links::Follow).unwrap();
+//! assert!(props.is_hidden());
+//! ```
+use std::os::unix::fs::FileTypeExt;
+#[cfg(target_os = "linux")]
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+
+use super::{AttributeSource, Links};
+use crate::error::*;
+use crate::props::constants::*;
+use crate::Props;
+
+/// The emulation as a backend.
+/// It can read the attributes of any path, but it can't write them.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct MetadataEmulation;
+impl AttributeSource for MetadataEmulation {
+    fn read(&self, path: &Path, links: Links) -> Result<Props> {
+        let metadata = match links {
+            Links::Follow => std::fs::metadata(path),
+            Links::NoFollow => std::fs::symlink_metadata(path),
+        }
+        .map_err(|error| Error {
+            kind: match error.kind() {
+                std::io::ErrorKind::NotFound => ErrorKind::FileNotFound,
+                _ => ErrorKind::Other(format!("{}: {}", path.display(), error)),
+            },
+        })?;
+        let file_type = metadata.file_type();
+        let mut attributes = if metadata.is_dir() {
+            DIRECTORY
+        } else {
+            ARCHIVE
+        };
+        let hidden = path
+            .file_name()
+            .map(|name| name.as_encoded_bytes().starts_with(b"."))
+            .unwrap_or(false);
+        if hidden {
+            attributes |= HIDDEN;
+        }
+        if metadata.is_file() {
+            if metadata.permissions().readonly() {
+                attributes |= READ_ONLY;
+            }
+            // Only the files with less blocks than their size needs can have holes, but so do
+            // inline, compressed and reflinked ones, so SEEK_HOLE decides.
+            // st_blocks is always counted in 512 byte units.
+            #[cfg(target_os = "linux")]
+            if metadata.blocks().saturating_mul(512) < metadata.len() && has_hole(path) {
+                attributes |= SPARSE;
+            }
+        }
+        if file_type.is_block_device() || file_type.is_char_device() {
+            attributes |= DEVICE;
+        }
+        if file_type.is_symlink() {
+            attributes |= REPARSE;
+        }
+        #[cfg(target_os = "linux")]
+        if let Some(statx_attributes) = super::inode_flags::statx_attributes(path, links) {
+            let statx_props =
+                super::inode_flags::from_statx_attributes(statx_attributes, metadata.is_dir());
+            attributes |= u32::from(statx_props) & !(ARCHIVE | DIRECTORY);
+        }
+        Ok(Props::from(attributes))
+    }
+    fn supports(&self, _path: &Path) -> bool {
+        true
+    }
+}
+/// Returns true if the file at `path` has a hole before its end
+#[cfg(target_os = "linux")]
+fn has_hole(path: &Path) -> bool {
+    use std::os::{fd::AsRawFd, unix::fs::OpenOptionsExt};
+
+    // Opening a FIFO for reading would wait for a writer
+    let Ok(file) = std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(libc::O_NONBLOCK)
+        .open(path)
+    else {
+        return false;
+    };
+    let Ok(size) = file.metadata().map(|metadata| metadata.len()) else {
+        return false;
+    };
+    // SAFETY: the descriptor is open during the call
+    let hole = unsafe { libc::lseek(file.as_raw_fd(), 0, libc::SEEK_HOLE) };
+    // File systems without holes give the end of the file, or an error on old kernels
+    hole >= 0 && (hole as u64) < size
+}
@@ -0,0 +1,147 @@
+//! The vfat and msdos drivers of Linux give access to the attribute byte stored in the FAT
+//! directory entry through the `FAT_IOCTL_GET_ATTRIBUTES` and `FAT_IOCTL_SET_ATTRIBUTES` ioctls.
+//! FAT only has room for the read only, hidden, system, directory and archive attributes, so
+//! asking it to store any other one is an error instead of silently dropping it.
+//! ```
+//! use keypropdecode::backends::fat::*;
+//! use keypropdecode::props;
+//!
+//! assert_eq!(from_fat(0x23), props!(file: read_only, hidden));
+//! assert_eq!(to_fat(props!(dir: system)), Ok(0x14));
+//! assert!(to_fat(props!(file: compressed)).is_err());
+//! ```
+use crate::error::*;
+use crate::props::constants::*;
+use crate::Props;
+
+#[cfg(target_os = "linux")]
+use super::{AttributeSink, AttributeSource, Links};
+#[cfg(target_os = "linux")]
+use std::path::Path;
+
+/// The attributes a FAT directory entry can hold
+pub const FAT_ATTRIBUTES: u32 = READ_ONLY | HIDDEN | SYSTEM | DIRECTORY | ARCHIVE;
+/// The volume label bit of the attribute byte, which Props can't represent
+pub const ATTR_VOLUME: u8 = 0x08;
+
+/// Gets the Props of a FAT attribute byte, whose directory bit decides the element type.
+pub fn from_fat(attribute_byte: u8) -> Props {
+    let attributes = attribute_byte as u32 & FAT_ATTRIBUTES;
+    Props::from(super::with_element_type(
+        attributes,
+        attributes & DIRECTORY == DIRECTORY,
+    ))
+}
+/// Gets the FAT attribute byte of `props`.
+/// Returns a `NotSupported` error naming the attributes FAT can't hold, if there's any.
+/// The normal attribute is accepted, since on FAT it's the same as having none.
+pub fn to_fat(props: Props) -> Result<u8> {
+    let attributes: u32 = props.into();
+    let unsupported = attributes & !(FAT_ATTRIBUTES | NORMAL);
+    if unsupported != 0 {
+        return Err(Error {
+            kind: ErrorKind::NotSupported(format!(
+                "FAT can't hold the attributes {:#x}",
+                unsupported
+            )),
+        });
+    }
+    Ok((attributes & FAT_ATTRIBUTES) as u8)
+}
+/// The FAT ioctls as a backend.
+/// The driver refuses to change the directory and volume label bits.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Fat;
+#[cfg(target_os = "linux")]
+impl AttributeSource for Fat {
+    fn read(&self, path: &Path, links: Links) -> Result<Props> {
+        let mut attributes: u32 = 0;
+        ioctl(
+            path,
+            links,
+            libc::_IOR::<u32>(b'r' as u32, 0x10),
+            &mut attributes,
+        )?;
+        Ok(from_fat(attributes as u8))
+    }
+}
+#[cfg(target_os = "linux")]
+impl AttributeSink for Fat {
+    fn write(&self, path: &Path, props: Props, links: Links) -> Result<()> {
+        let mut attributes = to_fat(props)? as u32;
+        ioctl(
+            path,
+            links,
+            libc::_IOW::<u32>(b'r' as u32, 0x11),
+            &mut attributes,
+        )
+    }
+}
+/// Gets the Props of `path` from the FAT directory entry, following symbolic links.
+#[cfg(target_os = "linux")]
+pub fn read(path: impl AsRef<Path>) -> Result<Props> {
+    Fat.read(path.as_ref(), Links::Follow)
+}
+/// Stores `props` in the FAT directory entry of `path`, following symbolic links.
+#[cfg(target_os = "linux")]
+pub fn write(path: impl AsRef<Path>, props: Props) -> Result<()> {
+    Fat.write(path.as_ref(), props, Links::Follow)
+}
+#[cfg(target_os = "linux")]
+fn ioctl(path: &Path, links: Links, request: libc::Ioctl, attributes: &mut u32) -> Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let file = super::open_for_ioctl(path, links).map_err(|error| Error {
+        kind: match error.raw_os_error() {
+            Some(libc::ENOENT) => ErrorKind::FileNotFound,
+            // FAT has no symbolic links nor sockets, so those are somewhere else
+            Some(libc::ELOOP | libc::ENXIO) => not_fat(path),
+            // Another backend may still reach the attributes without opening the element
+            Some(libc::EACCES | libc::EPERM) => ErrorKind::NotSupported(format!(
+                "{} can't be opened to ask for its FAT attributes: {}",
+                path.display(),
+                error
+            )),
+            _ => ErrorKind::Other(format!("{}: {}", path.display(), error)),
+        },
+    })?;
+    // Only regular files and directories live in FAT
+    let file = file.ok_or_else(|| Error {
+        kind: not_fat(path),
+    })?;
+    // Other drivers may give the same ioctl numbers another meaning
+    let mut stats = std::mem::MaybeUninit::<libc::statfs>::uninit();
+    // SAFETY: fstatfs fills the struct, which lives during the call
+    if unsafe { libc::fstatfs(file.as_raw_fd(), stats.as_mut_ptr()) } < 0 {
+        let error = std::io::Error::last_os_error();
+        return Err(Error {
+            kind: ErrorKind::Other(format!("{}: {}", path.display(), error)),
+        });
+    }
+    // SAFETY: fstatfs succeeded, so it filled the struct
+    let stats = unsafe { stats.assume_init() };
+    if stats.f_type != libc::MSDOS_SUPER_MAGIC as _ {
+        return Err(Error {
+            kind: not_fat(path),
+        });
+    }
+    // SAFETY: both ioctls take a pointer to a u32 that lives during the call
+    let result = unsafe { libc::ioctl(file.as_raw_fd(), request, attributes as *mut u32) };
+    if result < 0 {
+        let error = std::io::Error::last_os_error();
+        return Err(Error {
+            kind: match error.raw_os_error() {
+                Some(libc::ENOTTY) => not_fat(path),
+                _ => ErrorKind::Other(format!("{}: {}", path.display(), error)),
+            },
+        });
+    }
+    Ok(())
+}
+#[cfg(target_os = "linux")]
+fn not_fat(path: &Path) -> ErrorKind {
+    ErrorKind::NotSupported(format!(
+        "{} isn't in a FAT file system mounted with vfat or msdos",
+        path.display()
+    ))
+}
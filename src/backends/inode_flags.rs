@@ -0,0 +1,283 @@
+//! Linux file systems have their own per-file flags, the ones `lsattr` shows and `chattr` changes
+//! through the `FS_IOC_GETFLAGS` and `FS_IOC_SETFLAGS` ioctls, and some of them are also reported
+//! by `statx` as `STATX_ATTR_*`. A few mean the same as a Windows attribute:
+//!
+//! | Flag | `lsattr` | `statx` | Attribute |
+//! |------|----------|---------|-----------|
+//! | `FS_COMPR_FL` | `c` | `STATX_ATTR_COMPRESSED` | compressed |
+//! | `FS_ENCRYPT_FL` | `E` | `STATX_ATTR_ENCRYPTED` | encrypted |
+//! | `FS_IMMUTABLE_FL` | `i` | `STATX_ATTR_IMMUTABLE` | read_only (only files) |
+//! | `FS_NODUMP_FL` | `d` | `STATX_ATTR_NODUMP` | not_content_indexed |
+//!
+//! Every other flag, like no copy on write (`C`), has no Windows attribute and every other
+//! attribute has no flag, so they are lost when converting.
+//! ```
+//! use keypropdecode::backends::inode_flags::*;
+//! use keypropdecode::props;
+//!
+//! let (flags, path) = parse_lsattr("--------c-----e------- ./report.txt").unwrap();
+//! assert_eq!(path, "./report.txt");
+//! assert_eq!(from_inode_flags(flags, false), props!(file: compressed));
+//! assert_eq!(to_inode_flags(props!(file: read_only, not_content_indexed)), FS_IMMUTABLE_FL | FS_NODUMP_FL);
+//! assert_eq!(format_lsattr(flags), "--------c-----e-------");
+//! ```
+use crate::error::*;
+use crate::props::constants::*;
+use crate::Props;
+
+#[cfg(target_os = "linux")]
+use super::{AttributeSink, AttributeSource, Links};
+#[cfg(target_os = "linux")]
+use std::path::Path;
+
+/// Secure deletion (`s`)
+pub const FS_SECRM_FL: u32 = 0x0000_0001;
+/// Undelete (`u`)
+pub const FS_UNRM_FL: u32 = 0x0000_0002;
+/// Compressed (`c`)
+pub const FS_COMPR_FL: u32 = 0x0000_0004;
+/// Synchronous updates (`S`)
+pub const FS_SYNC_FL: u32 = 0x0000_0008;
+/// Immutable (`i`)
+pub const FS_IMMUTABLE_FL: u32 = 0x0000_0010;
+/// Append only (`a`)
+pub const FS_APPEND_FL: u32 = 0x0000_0020;
+/// No dump (`d`)
+pub const FS_NODUMP_FL: u32 = 0x0000_0040;
+/// No access time updates (`A`)
+pub const FS_NOATIME_FL: u32 = 0x0000_0080;
+/// No compression (`m`)
+pub const FS_NOCOMP_FL: u32 = 0x0000_0400;
+/// Encrypted (`E`)
+pub const FS_ENCRYPT_FL: u32 = 0x0000_0800;
+/// Hashed directory indexes (`I`)
+pub const FS_INDEX_FL: u32 = 0x0000_1000;
+/// Data journaling (`j`)
+pub const FS_JOURNAL_DATA_FL: u32 = 0x0000_4000;
+/// No tail merging (`t`)
+pub const FS_NOTAIL_FL: u32 = 0x0000_8000;
+/// Synchronous directory updates (`D`)
+pub const FS_DIRSYNC_FL: u32 = 0x0001_0000;
+/// Top of directory hierarchy (`T`)
+pub const FS_TOPDIR_FL: u32 = 0x0002_0000;
+/// Uses extents (`e`)
+pub const FS_EXTENT_FL: u32 = 0x0008_0000;
+/// Verity protected (`V`)
+pub const FS_VERITY_FL: u32 = 0x0010_0000;
+/// No copy on write (`C`)
+pub const FS_NOCOW_FL: u32 = 0x0080_0000;
+/// Direct access (`x`)
+pub const FS_DAX_FL: u32 = 0x0200_0000;
+/// Inline data (`N`)
+pub const FS_INLINE_DATA_FL: u32 = 0x1000_0000;
+/// Project hierarchy (`P`)
+pub const FS_PROJINHERIT_FL: u32 = 0x2000_0000;
+/// Case insensitive directory (`F`)
+pub const FS_CASEFOLD_FL: u32 = 0x4000_0000;
+
+/// The `lsattr` letter of every flag, in the order it prints them
+pub const LSATTR_LETTERS: [(char, u32); 22] = [
+    ('s', FS_SECRM_FL),
+    ('u', FS_UNRM_FL),
+    ('S', FS_SYNC_FL),
+    ('D', FS_DIRSYNC_FL),
+    ('i', FS_IMMUTABLE_FL),
+    ('a', FS_APPEND_FL),
+    ('d', FS_NODUMP_FL),
+    ('A', FS_NOATIME_FL),
+    ('c', FS_COMPR_FL),
+    ('E', FS_ENCRYPT_FL),
+    ('j', FS_JOURNAL_DATA_FL),
+    ('I', FS_INDEX_FL),
+    ('t', FS_NOTAIL_FL),
+    ('T', FS_TOPDIR_FL),
+    ('e', FS_EXTENT_FL),
+    ('C', FS_NOCOW_FL),
+    ('x', FS_DAX_FL),
+    ('F', FS_CASEFOLD_FL),
+    ('N', FS_INLINE_DATA_FL),
+    ('P', FS_PROJINHERIT_FL),
+    ('V', FS_VERITY_FL),
+    ('m', FS_NOCOMP_FL),
+];
+/// The flags that have a Windows attribute
+pub const MAPPED_FLAGS: u32 = FS_COMPR_FL | FS_ENCRYPT_FL | FS_IMMUTABLE_FL | FS_NODUMP_FL;
+
+/// Gets the Props of the inode flags of a file or, if `directory`, a directory.
+/// The immutable flag of a directory is lost, since only files can be read only.
+pub fn from_inode_flags(flags: u32, directory: bool) -> Props {
+    let mut attributes = 0;
+    if flags & FS_COMPR_FL != 0 {
+        attributes |= COMPRESSED;
+    }
+    if flags & FS_ENCRYPT_FL != 0 {
+        attributes |= ENCRYPTED;
+    }
+    if flags & FS_IMMUTABLE_FL != 0 {
+        attributes |= READ_ONLY;
+    }
+    if flags & FS_NODUMP_FL != 0 {
+        attributes |= NOT_CONTENT_INDEXED;
+    }
+    Props::from(super::with_element_type(attributes, directory))
+}
+/// Gets the inode flags that mean the same as the attributes of `props`
+pub fn to_inode_flags(props: Props) -> u32 {
+    let attributes: u32 = props.into();
+    let mut flags = 0;
+    if attributes & COMPRESSED != 0 {
+        flags |= FS_COMPR_FL;
+    }
+    if attributes & ENCRYPTED != 0 {
+        flags |= FS_ENCRYPT_FL;
+    }
+    if attributes & READ_ONLY != 0 {
+        flags |= FS_IMMUTABLE_FL;
+    }
+    if attributes & NOT_CONTENT_INDEXED != 0 {
+        flags |= FS_NODUMP_FL;
+    }
+    flags
+}
+/// Gets the Props of the `stx_attributes` of a file or, if `directory`, a directory.
+/// Only the bits set in `stx_attributes_mask` should be given, the rest are meaningless.
+pub fn from_statx_attributes(attributes: u64, directory: bool) -> Props {
+    // The STATX_ATTR_* bits have the same values as the inode flags
+    from_inode_flags(attributes as u32 & MAPPED_FLAGS, directory)
+}
+/// Gets the flags of the letters `lsattr` prints, like `----i---------e-------`.
+/// The order of the letters doesn't matter, and `-` is skipped.
+pub fn parse_lsattr_flags(letters: &str) -> Result<u32> {
+    let mut flags = 0;
+    for letter in letters.chars().filter(|letter| *letter != '-') {
+        match LSATTR_LETTERS.iter().find(|(known, _)| *known == letter) {
+            Some((_, flag)) => flags |= flag,
+            None => {
+                return Err(Error {
+                    kind: ErrorKind::InvalidData(format!("'{}' isn't an lsattr flag", letter)),
+                })
+            }
+        }
+    }
+    Ok(flags)
+}
+/// Splits a line of `lsattr` output into the flags and the path
+pub fn parse_lsattr(line: &str) -> Result<(u32, &str)> {
+    let line = line.trim_end_matches(['\n', '\r']);
+    match line.split_once(' ') {
+        Some((letters, path)) if !path.trim_start().is_empty() => {
+            Ok((parse_lsattr_flags(letters)?, path.trim_start()))
+        }
+        _ => Err(Error {
+            kind: ErrorKind::InvalidData(format!("\"{}\" isn't an lsattr line", line)),
+        }),
+    }
+}
+/// Gets the letters `lsattr` prints for `flags`, with `-` for the ones that aren't set
+pub fn format_lsattr(flags: u32) -> String {
+    LSATTR_LETTERS
+        .iter()
+        .map(|(letter, flag)| if flags & flag != 0 { *letter } else { '-' })
+        .collect()
+}
+
+/// The inode flags as a backend.
+/// Writing only changes the compressed, immutable and no dump flags: the encrypted one can only be
+/// set by fscrypt on empty directories. Setting the immutable flag needs `CAP_LINUX_IMMUTABLE`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct InodeFlags;
+#[cfg(target_os = "linux")]
+impl AttributeSource for InodeFlags {
+    fn read(&self, path: &Path, links: Links) -> Result<Props> {
+        let mut flags = 0;
+        ioctl(path, links, libc::FS_IOC_GETFLAGS, &mut flags)?;
+        Ok(from_inode_flags(flags, super::is_directory(path, links)))
+    }
+}
+#[cfg(target_os = "linux")]
+impl AttributeSink for InodeFlags {
+    fn write(&self, path: &Path, props: Props, links: Links) -> Result<()> {
+        let mut flags = 0;
+        ioctl(path, links, libc::FS_IOC_GETFLAGS, &mut flags)?;
+        let wanted = to_inode_flags(props);
+        if (flags ^ wanted) & FS_ENCRYPT_FL != 0 {
+            return Err(Error {
+                kind: ErrorKind::NotSupported(format!(
+                    "The encrypted attribute of {} can't be changed with inode flags",
+                    path.display()
+                )),
+            });
+        }
+        let writable = FS_COMPR_FL | FS_IMMUTABLE_FL | FS_NODUMP_FL;
+        let mut new_flags = (flags & !writable) | (wanted & writable);
+        if new_flags == flags {
+            return Ok(());
+        }
+        ioctl(path, links, libc::FS_IOC_SETFLAGS, &mut new_flags)
+    }
+}
+/// Gets the Props of the inode flags of `path`, following symbolic links.
+#[cfg(target_os = "linux")]
+pub fn read(path: impl AsRef<Path>) -> Result<Props> {
+    InodeFlags.read(path.as_ref(), Links::Follow)
+}
+/// Changes the inode flags of `path` to match `props`, following symbolic links.
+#[cfg(target_os = "linux")]
+pub fn write(path: impl AsRef<Path>, props: Props) -> Result<()> {
+    InodeFlags.write(path.as_ref(), props, Links::Follow)
+}
+/// The `stx_attributes` the file system supports, or None if statx isn't available
+#[cfg(target_os = "linux")]
+pub(crate) fn statx_attributes(path: &Path, links: Links) -> Option<u64> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let flags = match links {
+        Links::Follow => 0,
+        Links::NoFollow => libc::AT_SYMLINK_NOFOLLOW,
+    };
+    // SAFETY: an all zero statx is a valid value, and the kernel fills it
+    let mut statx: libc::statx = unsafe { std::mem::zeroed() };
+    // SAFETY: the path is nul terminated and statx lives during the call
+    let result = unsafe { libc::statx(libc::AT_FDCWD, c_path.as_ptr(), flags, 0, &mut statx) };
+    if result < 0 {
+        return None;
+    }
+    Some(statx.stx_attributes & statx.stx_attributes_mask)
+}
+#[cfg(target_os = "linux")]
+fn ioctl(path: &Path, links: Links, request: libc::Ioctl, flags: &mut u32) -> Result<()> {
+    use std::os::fd::AsRawFd;
+
+    let file = super::open_for_ioctl(path, links)
+        .map_err(|error| Error {
+            kind: match error.raw_os_error() {
+                Some(libc::ENOENT) => ErrorKind::FileNotFound,
+                Some(libc::ELOOP) => ErrorKind::NotSupported(format!(
+                    "{} is a symbolic link, which has no inode flags",
+                    path.display()
+                )),
+                _ => ErrorKind::Other(format!("{}: {}", path.display(), error)),
+            },
+        })?
+        .ok_or_else(|| Error {
+            kind: ErrorKind::NotSupported(format!(
+                "{} isn't a regular file nor a directory, the only elements with inode flags",
+                path.display()
+            )),
+        })?;
+    // SAFETY: the kernel reads and writes an int through the pointer, which lives during the call
+    let result = unsafe { libc::ioctl(file.as_raw_fd(), request, flags as *mut u32) };
+    if result < 0 {
+        let error = std::io::Error::last_os_error();
+        return Err(Error {
+            kind: match error.raw_os_error() {
+                Some(libc::ENOTTY | libc::EOPNOTSUPP | libc::EINVAL) => ErrorKind::NotSupported(
+                    format!("{} is in a file system without inode flags", path.display()),
+                ),
+                _ => ErrorKind::Other(format!("{}: {}", path.display(), error)),
+            },
+        });
+    }
+    Ok(())
+}
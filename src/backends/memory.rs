@@ -0,0 +1,211 @@
+//! A tree of paths with their Props that lives in memory, for testing code that reads and writes
+//! attributes without touching a real file system. It works on every platform, and since it
+//! implements `AttributeSource` and `AttributeSink` it can be given anywhere a backend is
+//! expected.
+//! ```
+//! use keypropdecode::backends::{memory::MemoryStore, Links};
+//! use keypropdecode::{props, Props};
+//!
+//! let store = MemoryStore::new();
+//! store.insert("/share/docs/report.txt", props!(file: hidden)).unwrap();
+//! store.rename("/share/docs", "/share/archive").unwrap();
+//!
+//! let props = Props::from_path_with("/share/archive/report.txt", &store, Links::Follow).unwrap();
+//! assert!(props.is_hidden());
+//! assert!(store.read_dir("/share").unwrap().contains(&"/share/archive".into()));
+//! ```
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+use super::{AttributeSink, AttributeSource, Links};
+use crate::error::*;
+use crate::Props;
+
+/// The in-memory tree.
+/// Parent directories are created when inserting an element, so every element always has its
+/// parents in the store. Links don't exist in it, so following them or not makes no difference.
+#[derive(Debug, Default)]
+pub struct MemoryStore {
+    elements: RwLock<BTreeMap<PathBuf, Props>>,
+}
+impl MemoryStore {
+    /// An empty store
+    pub fn new() -> Self {
+        MemoryStore::default()
+    }
+    /// Adds an element, or replaces its Props if it was already there.
+    /// The missing parent directories are created with the default Props.
+    /// Returns an `Other` error if one of the parents is a file, or if `props` is a file and
+    /// there's a directory with elements inside it at `path`.
+    pub fn insert(&self, path: impl AsRef<Path>, props: Props) -> Result<()> {
+        let path = path.as_ref();
+        let mut elements = self.elements.write().expect("poisoned store");
+        check_keeps_children(&elements, path, props)?;
+        for parent in path.ancestors().skip(1) {
+            if parent.as_os_str().is_empty() {
+                break;
+            }
+            match elements.get(parent) {
+                Some(parent_props) if !parent_props.is_directory() => {
+                    return Err(not_a_directory(parent))
+                }
+                Some(_) => break,
+                None => {
+                    elements.insert(parent.to_path_buf(), Props::default());
+                }
+            }
+        }
+        elements.insert(path.to_path_buf(), props);
+        Ok(())
+    }
+    /// Returns true if there's an element at `path`
+    pub fn contains(&self, path: impl AsRef<Path>) -> bool {
+        let elements = self.elements.read().expect("poisoned store");
+        elements.contains_key(path.as_ref())
+    }
+    /// Moves an element, and everything inside it if it is a directory, to another path.
+    /// The new parent must exist and there can't be anything at the new path.
+    pub fn rename(&self, from: impl AsRef<Path>, to: impl AsRef<Path>) -> Result<()> {
+        let (from, to) = (from.as_ref(), to.as_ref());
+        let mut elements = self.elements.write().expect("poisoned store");
+        if !elements.contains_key(from) {
+            return Err(not_found());
+        }
+        if elements.contains_key(to) {
+            return Err(Error {
+                kind: ErrorKind::Other(format!("{} already exists", to.display())),
+            });
+        }
+        if to.starts_with(from) {
+            return Err(Error {
+                kind: ErrorKind::Other(format!("{} can't be moved inside itself", from.display())),
+            });
+        }
+        if let Some(parent) = to.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            match elements.get(parent) {
+                None => return Err(not_found()),
+                Some(parent_props) if !parent_props.is_directory() => {
+                    return Err(not_a_directory(parent))
+                }
+                Some(_) => (),
+            }
+        }
+        for (path, props) in take_tree(&mut elements, from) {
+            let relative = path.strip_prefix(from).expect("inside the moved tree");
+            let new_path = if relative.as_os_str().is_empty() {
+                to.to_path_buf()
+            } else {
+                to.join(relative)
+            };
+            elements.insert(new_path, props);
+        }
+        Ok(())
+    }
+    /// Deletes an element and, if it is a directory, everything inside it
+    pub fn remove(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let mut elements = self.elements.write().expect("poisoned store");
+        if !elements.contains_key(path) {
+            return Err(not_found());
+        }
+        take_tree(&mut elements, path);
+        Ok(())
+    }
+    /// The paths of the elements directly inside the directory at `path`, sorted
+    pub fn read_dir(&self, path: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+        let path = path.as_ref();
+        let elements = self.elements.read().expect("poisoned store");
+        match elements.get(path) {
+            None => return Err(not_found()),
+            Some(props) if !props.is_directory() => return Err(not_a_directory(path)),
+            Some(_) => (),
+        }
+        Ok(elements
+            .range(path.to_path_buf()..)
+            .skip(1)
+            .take_while(|(child, _)| child.starts_with(path))
+            .filter(|(child, _)| child.parent() == Some(path))
+            .map(|(child, _)| child.clone())
+            .collect())
+    }
+    /// Every element in the store, sorted by path
+    pub fn elements(&self) -> Vec<(PathBuf, Props)> {
+        let elements = self.elements.read().expect("poisoned store");
+        elements
+            .iter()
+            .map(|(path, props)| (path.clone(), *props))
+            .collect()
+    }
+}
+impl AttributeSource for MemoryStore {
+    fn read(&self, path: &Path, _links: Links) -> Result<Props> {
+        let elements = self.elements.read().expect("poisoned store");
+        elements.get(path).copied().ok_or_else(not_found)
+    }
+    fn supports(&self, _path: &Path) -> bool {
+        true
+    }
+}
+impl AttributeSink for MemoryStore {
+    /// Only changes elements that are already in the store, like a file system would, and
+    /// doesn't turn a directory with elements inside it into a file
+    fn write(&self, path: &Path, props: Props, _links: Links) -> Result<()> {
+        let mut elements = self.elements.write().expect("poisoned store");
+        check_keeps_children(&elements, path, props)?;
+        match elements.get_mut(path) {
+            Some(stored) => {
+                *stored = props;
+                Ok(())
+            }
+            None => Err(not_found()),
+        }
+    }
+}
+/// Removes `root` and everything under it, returning them
+fn take_tree(elements: &mut BTreeMap<PathBuf, Props>, root: &Path) -> Vec<(PathBuf, Props)> {
+    let paths: Vec<PathBuf> = elements
+        .range(root.to_path_buf()..)
+        .take_while(|(path, _)| path.starts_with(root))
+        .map(|(path, _)| path.clone())
+        .collect();
+    paths
+        .into_iter()
+        .map(|path| {
+            let props = elements.remove(&path).expect("listed above");
+            (path, props)
+        })
+        .collect()
+}
+/// Fails if giving `props` to `path` would leave the elements inside it without a directory
+fn check_keeps_children(
+    elements: &BTreeMap<PathBuf, Props>,
+    path: &Path,
+    props: Props,
+) -> Result<()> {
+    let has_children = elements
+        .range(path.to_path_buf()..)
+        .nth(1)
+        .is_some_and(|(child, _)| child.starts_with(path));
+    if !props.is_directory() && elements.contains_key(path) && has_children {
+        return Err(Error {
+            kind: ErrorKind::Other(format!(
+                "{} is a directory that isn't empty",
+                path.display()
+            )),
+        });
+    }
+    Ok(())
+}
+fn not_found() -> Error {
+    Error {
+        kind: ErrorKind::FileNotFound,
+    }
+}
+fn not_a_directory(path: &Path) -> Error {
+    Error {
+        kind: ErrorKind::Other(format!("{} isn't a directory", path.display())),
+    }
+}
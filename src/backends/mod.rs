@@ -0,0 +1,188 @@
+//! Every backend reads the attributes from a different place, since on Linux there is no
+//! single way to get them: each file system driver or server stores them on its own.
+//! They all implement `AttributeSource`, and the ones that can change the attributes
+//! `AttributeSink` too, so code that works with paths doesn't need to know where the
+//! attributes come from. `Auto` is the one `Props::from_path` uses.
+//! ```no_run
+//! use keypropdecode::backends::{AttributeSource, Auto, Links};
+//! use keypropdecode::Props;
+//!
+//! let link = Props::from_path_with("shortcut", &Auto, Links::NoFollow).unwrap();
+//! assert!(link.is_reparse());
+//! assert_eq!(Auto.read("shortcut".as_ref(), Links::Follow), Props::from_path("shortcut"));
+//! ```
+#[cfg(target_os = "linux")]
+mod xattr;
+
+/// The extended attribute of the CIFS client
+pub mod cifs;
+/// Attributes guessed from the metadata of Unix file systems
+#[cfg(unix)]
+pub mod emulation;
+/// The attribute ioctls of the vfat and msdos drivers
+pub mod fat;
+/// The inode flags of Linux file systems, the ones `lsattr` shows
+pub mod inode_flags;
+/// A tree of paths kept in memory, for tests
+pub mod memory;
+/// The extended attributes of the ntfs-3g and ntfs3 drivers
+pub mod ntfs;
+/// Samba's `user.DOSATTRIB` extended attribute
+pub mod samba;
+#[cfg(test)]
+mod tests;
+/// Samba's mapping of the attributes to the mode of the file
+pub mod unix_mode;
+/// The attributes Windows itself stores, only on Windows
+#[cfg(windows)]
+pub mod windows;
+
+use std::path::Path;
+
+use crate::error::*;
+use crate::Props;
+
+/// What to do when the path is a symbolic link or a reparse point.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Links {
+    /// Use the attributes of the element the link points to
+    #[default]
+    Follow,
+    /// Use the attributes of the link itself
+    NoFollow,
+}
+/// A place the attributes of a path can be read from.
+pub trait AttributeSource {
+    /// Gets the Props of `path`.
+    fn read(&self, path: &Path, links: Links) -> Result<Props>;
+    /// Returns false if `path` is in a file system this source can't read the attributes from.
+    /// By default it tries to read them and checks for a `NotSupported` error.
+    fn supports(&self, path: &Path) -> bool {
+        !matches!(
+            self.read(path, Links::Follow),
+            Err(Error {
+                kind: ErrorKind::NotSupported(_)
+            })
+        )
+    }
+}
+/// A place the attributes of a path can be written to.
+pub trait AttributeSink {
+    /// Changes the attributes of `path` to `props`.
+    fn write(&self, path: &Path, props: Props, links: Links) -> Result<()>;
+}
+
+/// Picks the backend that supports the path.
+/// On Windows it reads what Windows stores. On Linux it tries, in order, the ntfs-3g and ntfs3
+/// extended attributes, the CIFS client, the FAT ioctls and Samba's extended attribute, and uses
+/// the first one the file system of the path supports. When none of them has attributes stored
+/// for the path, they're guessed from its metadata with
+/// [`MetadataEmulation`](emulation::MetadataEmulation), which can't be written to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Auto;
+impl Auto {
+    #[cfg(target_os = "linux")]
+    fn sources() -> [&'static dyn AttributeSourceSink; 4] {
+        [&ntfs::Ntfs::ANY, &cifs::Cifs, &fat::Fat, &samba::Samba]
+    }
+}
+#[cfg(target_os = "linux")]
+trait AttributeSourceSink: AttributeSource + AttributeSink {}
+#[cfg(target_os = "linux")]
+impl<T: AttributeSource + AttributeSink> AttributeSourceSink for T {}
+
+impl AttributeSource for Auto {
+    #[cfg(windows)]
+    fn read(&self, path: &Path, links: Links) -> Result<Props> {
+        windows::WindowsMetadata.read(path, links)
+    }
+    #[cfg(target_os = "linux")]
+    fn read(&self, path: &Path, links: Links) -> Result<Props> {
+        for source in Auto::sources() {
+            match source.read(path, links) {
+                Err(Error {
+                    kind: ErrorKind::NotSupported(_) | ErrorKind::AttributesNotFound(_),
+                }) => continue,
+                result => return result,
+            }
+        }
+        emulation::MetadataEmulation.read(path, links)
+    }
+    #[cfg(not(any(windows, target_os = "linux")))]
+    fn read(&self, path: &Path, _links: Links) -> Result<Props> {
+        Err(no_backend(path))
+    }
+}
+#[cfg(target_os = "linux")]
+impl AttributeSink for Auto {
+    fn write(&self, path: &Path, props: Props, links: Links) -> Result<()> {
+        // The backend is picked by reading the same element that will be written
+        for sink in Auto::sources() {
+            match sink.read(path, links) {
+                Err(Error {
+                    kind: ErrorKind::NotSupported(_),
+                }) => continue,
+                _ => return sink.write(path, props, links),
+            }
+        }
+        Err(no_backend(path))
+    }
+}
+#[cfg(not(windows))]
+fn no_backend(path: &Path) -> Error {
+    Error {
+        kind: ErrorKind::NotSupported(format!(
+            "No backend can reach the attributes of {}",
+            path.display()
+        )),
+    }
+}
+
+/// Sets the element type bits of an attribute word to what the element is.
+/// Most Linux backends store the word without them, or with a stale archive bit, and Props
+/// needs them to tell apart files from directories. Because Props only knows files by their
+/// archive attribute, the archive bit is set for every file and cleared for directories.
+pub(crate) fn with_element_type(attributes: u32, directory: bool) -> u32 {
+    use crate::props::constants::{ARCHIVE, DIRECTORY};
+    if directory {
+        (attributes | DIRECTORY) & !ARCHIVE
+    } else {
+        (attributes | ARCHIVE) & !DIRECTORY
+    }
+}
+/// Opens `path` to send it attribute ioctls, like `fgetflags` of e2fsprogs does.
+/// Only regular files and directories are opened, so device nodes never get the request,
+/// and returns None for any other element.
+#[cfg(target_os = "linux")]
+pub(crate) fn open_for_ioctl(path: &Path, links: Links) -> std::io::Result<Option<std::fs::File>> {
+    use std::os::unix::fs::OpenOptionsExt;
+
+    let metadata = match links {
+        Links::Follow => std::fs::metadata(path),
+        Links::NoFollow => std::fs::symlink_metadata(path),
+    }?;
+    if !metadata.is_file() && !metadata.is_dir() {
+        return Ok(None);
+    }
+    // The element can be replaced after the check: a FIFO would wait for a writer
+    // and a terminal could become the controlling one
+    let mut custom_flags = libc::O_NONBLOCK | libc::O_NOCTTY;
+    if links == Links::NoFollow {
+        custom_flags |= libc::O_NOFOLLOW;
+    }
+    let file = std::fs::OpenOptions::new()
+        .read(true)
+        .custom_flags(custom_flags)
+        .open(path)?;
+    let metadata = file.metadata()?;
+    Ok((metadata.is_file() || metadata.is_dir()).then_some(file))
+}
+/// Returns true if `path` is a directory, looking at the link itself when not following them
+#[cfg(target_os = "linux")]
+pub(crate) fn is_directory(path: &Path, links: Links) -> bool {
+    let metadata = match links {
+        Links::Follow => std::fs::metadata(path),
+        Links::NoFollow => std::fs::symlink_metadata(path),
+    };
+    metadata.map(|metadata| metadata.is_dir()).unwrap_or(false)
+}
@@ -0,0 +1,142 @@
+//! NTFS volumes mounted on Linux expose the attribute word of every element as an extended
+//! attribute in the `system` namespace.
+//! ntfs-3g has `system.ntfs_attrib` (in the byte order of the machine) and
+//! `system.ntfs_attrib_be` (big endian). The in-kernel ntfs3 driver has both of them plus
+//! `system.dos_attrib`, which only holds the lowest byte of the word.
+//! ```
+//! use keypropdecode::backends::ntfs::NtfsXattr;
+//!
+//! assert_eq!(NtfsXattr::AttribBe.decode(&[0, 0, 0, 0x22]).unwrap(), 0x22);
+//! assert_eq!(NtfsXattr::DosAttrib.encode(0x2022), vec![0x22]);
+//! ```
+use crate::error::*;
+
+#[cfg(target_os = "linux")]
+use super::{AttributeSink, AttributeSource, Links};
+#[cfg(target_os = "linux")]
+use crate::Props;
+#[cfg(target_os = "linux")]
+use std::path::Path;
+
+/// The extended attributes the NTFS drivers expose.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum NtfsXattr {
+    /// `system.ntfs_attrib`, the whole word in the byte order of the machine
+    Attrib,
+    /// `system.ntfs_attrib_be`, the whole word in big endian
+    AttribBe,
+    /// `system.dos_attrib`, only the lowest byte of the word. ntfs3 only
+    DosAttrib,
+}
+impl NtfsXattr {
+    /// Every extended attribute, in the order they are tried when reading
+    pub const ALL: [NtfsXattr; 3] = [NtfsXattr::Attrib, NtfsXattr::AttribBe, NtfsXattr::DosAttrib];
+    /// The name of the extended attribute
+    pub fn name(&self) -> &'static str {
+        match self {
+            NtfsXattr::Attrib => "system.ntfs_attrib",
+            NtfsXattr::AttribBe => "system.ntfs_attrib_be",
+            NtfsXattr::DosAttrib => "system.dos_attrib",
+        }
+    }
+    /// Gets the attribute word from the raw value of the extended attribute
+    pub fn decode(&self, bytes: &[u8]) -> Result<u32> {
+        match (self, bytes) {
+            (NtfsXattr::Attrib, [a, b, c, d]) => Ok(u32::from_ne_bytes([*a, *b, *c, *d])),
+            (NtfsXattr::AttribBe, [a, b, c, d]) => Ok(u32::from_be_bytes([*a, *b, *c, *d])),
+            (NtfsXattr::DosAttrib, [a]) => Ok(*a as u32),
+            _ => Err(Error {
+                kind: ErrorKind::InvalidData(format!(
+                    "{} can't be {} bytes long",
+                    self.name(),
+                    bytes.len()
+                )),
+            }),
+        }
+    }
+    /// Gets the raw value of the extended attribute for an attribute word.
+    /// `system.dos_attrib` drops everything above the lowest byte.
+    pub fn encode(&self, attributes: u32) -> Vec<u8> {
+        match self {
+            NtfsXattr::Attrib => attributes.to_ne_bytes().to_vec(),
+            NtfsXattr::AttribBe => attributes.to_be_bytes().to_vec(),
+            NtfsXattr::DosAttrib => vec![attributes as u8],
+        }
+    }
+}
+/// The NTFS extended attributes as a backend.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Ntfs {
+    /// The extended attribute to use. With `None` it reads the first one the driver exposes
+    /// and writes `system.ntfs_attrib`
+    pub xattr: Option<NtfsXattr>,
+}
+impl Ntfs {
+    /// Uses whichever extended attribute the driver exposes
+    pub const ANY: Ntfs = Ntfs { xattr: None };
+}
+#[cfg(target_os = "linux")]
+impl AttributeSource for Ntfs {
+    fn read(&self, path: &Path, links: Links) -> Result<Props> {
+        let Some(xattr) = self.xattr else {
+            for xattr in NtfsXattr::ALL {
+                match read_xattr(path, xattr, links) {
+                    Err(Error {
+                        kind: ErrorKind::NotSupported(_),
+                    }) => continue,
+                    result => return result,
+                }
+            }
+            return Err(not_ntfs(path, NtfsXattr::Attrib));
+        };
+        read_xattr(path, xattr, links)
+    }
+}
+#[cfg(target_os = "linux")]
+impl AttributeSink for Ntfs {
+    /// The driver decides which attributes can be changed and ignores the rest.
+    fn write(&self, path: &Path, props: Props, links: Links) -> Result<()> {
+        let xattr = self.xattr.unwrap_or(NtfsXattr::Attrib);
+        super::xattr::set(path, xattr.name(), &xattr.encode(props.into()), links)
+            .map_err(|error| when_missing(error, path, xattr))
+    }
+}
+/// Gets the Props of `path` from the first extended attribute the driver exposes, following
+/// symbolic links.
+#[cfg(target_os = "linux")]
+pub fn read(path: impl AsRef<Path>) -> Result<Props> {
+    Ntfs::ANY.read(path.as_ref(), Links::Follow)
+}
+/// Changes the attributes of `path` through `system.ntfs_attrib`, following symbolic links.
+#[cfg(target_os = "linux")]
+pub fn write(path: impl AsRef<Path>, props: Props) -> Result<()> {
+    Ntfs::ANY.write(path.as_ref(), props, Links::Follow)
+}
+#[cfg(target_os = "linux")]
+fn read_xattr(path: &Path, xattr: NtfsXattr, links: Links) -> Result<Props> {
+    let value = super::xattr::get(path, xattr.name(), links)
+        .map_err(|error| when_missing(error, path, xattr))?;
+    Ok(Props::from(super::with_element_type(
+        xattr.decode(&value)?,
+        super::is_directory(path, links),
+    )))
+}
+/// Every NTFS element has attributes, so if the attribute is missing the file system is
+/// another one
+#[cfg(target_os = "linux")]
+fn when_missing(error: Error, path: &Path, xattr: NtfsXattr) -> Error {
+    match error.kind {
+        ErrorKind::AttributesNotFound(_) | ErrorKind::NotSupported(_) => not_ntfs(path, xattr),
+        _ => error,
+    }
+}
+#[cfg(target_os = "linux")]
+fn not_ntfs(path: &Path, xattr: NtfsXattr) -> Error {
+    Error {
+        kind: ErrorKind::NotSupported(format!(
+            "{} isn't in an NTFS volume mounted with ntfs-3g or ntfs3, it has no {} attribute",
+            path.display(),
+            xattr.name()
+        )),
+    }
+}
@@ -0,0 +1,359 @@
+//! When Samba runs with `store dos attributes = yes` it keeps the Windows attributes of every
+//! element in the `user.DOSATTRIB` extended attribute.
+//! Old versions wrote only the attribute word as a hexadecimal string (e.g. `0x20`), newer ones
+//! write that string followed by an NDR encoded `xattr_DosInfo` blob, from version 1 to 5.
+//! Both forms can be decoded on any platform with [`DosAttrib`](crate::backends::samba::DosAttrib), and on Linux the attribute can
+//! be read and written directly from a path.
+//! ```
+//! use keypropdecode::backends::samba::*;
+//! use keypropdecode::props::*;
+//!
+//! let blob = [
+//!     0x30, 0x78, 0x32, 0x32, 0x00, 0x00, 0x05, 0x00, 0x05, 0x00, 0x00, 0x00, // 0x22, version 5
+//!     0x01, 0x00, 0x00, 0x00, 0x22, 0x00, 0x00, 0x00, // valid flags and attributes
+//!     0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // create time
+//! ];
+//! let dos_attrib = DosAttrib::decode(&blob).unwrap();
+//! assert_eq!(dos_attrib.attributes, 0x22);
+//! assert!(Props::from(dos_attrib.attributes).is_hidden());
+//! assert_eq!(dos_attrib.encode(), blob);
+//! ```
+use crate::error::*;
+use crate::reader::Reader;
+
+#[cfg(target_os = "linux")]
+use super::{AttributeSink, AttributeSource, Links};
+#[cfg(target_os = "linux")]
+use crate::Props;
+#[cfg(target_os = "linux")]
+use std::path::Path;
+
+/// The name of the extended attribute Samba uses
+pub const XATTR_NAME: &str = "user.DOSATTRIB";
+
+/// `valid_flags` bit saying the attributes are stored
+pub const XATTR_DOSINFO_ATTRIB: u32 = 0x1;
+/// `valid_flags` bit saying the extended attributes size is stored
+pub const XATTR_DOSINFO_EA_SIZE: u32 = 0x2;
+/// `valid_flags` bit saying the size is stored
+pub const XATTR_DOSINFO_SIZE: u32 = 0x4;
+/// `valid_flags` bit saying the allocation size is stored
+pub const XATTR_DOSINFO_ALLOC_SIZE: u32 = 0x8;
+/// `valid_flags` bit saying the creation time is stored
+pub const XATTR_DOSINFO_CREATE_TIME: u32 = 0x10;
+/// `valid_flags` bit saying the change time is stored
+pub const XATTR_DOSINFO_CHANGE_TIME: u32 = 0x20;
+/// `valid_flags` bit saying the inode creation time is stored
+pub const XATTR_DOSINFO_ITIME: u32 = 0x40;
+
+/// The decoded content of a `user.DOSATTRIB` extended attribute.
+/// The times are `NTTIME`s: 100 nanosecond intervals since January 1, 1601.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DosAttrib {
+    /// The attribute word. When there's a blob it is the one in the blob, like Samba does, and
+    /// the hexadecimal string is rewritten from it when encoding
+    pub attributes: u32,
+    /// The rest of the blob
+    pub info: DosInfo,
+}
+/// The version specific part of the blob.
+/// Because every version stores different fields an enum here makes the most sense.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum DosInfo {
+    /// Only the hexadecimal string, as Samba 3.0 wrote it
+    Legacy,
+    /// Version 0xFFFF, holding only the attributes
+    Compat,
+    /// Version 1
+    V1 {
+        ea_size: u32,
+        size: u64,
+        alloc_size: u64,
+        create_time: u64,
+        change_time: u64,
+    },
+    /// Version 2, an old format that Samba still reads
+    V2 {
+        flags: u32,
+        ea_size: u32,
+        size: u64,
+        alloc_size: u64,
+        create_time: u64,
+        change_time: u64,
+        write_time: u64,
+        name: String,
+    },
+    /// Version 3
+    V3 {
+        valid_flags: u32,
+        ea_size: u32,
+        size: u64,
+        alloc_size: u64,
+        create_time: u64,
+    },
+    /// Version 4, which adds the inode creation time
+    V4 {
+        valid_flags: u32,
+        itime: u64,
+        create_time: u64,
+    },
+    /// Version 5, the current one
+    V5 { valid_flags: u32, create_time: u64 },
+}
+impl DosAttrib {
+    /// A version 5 blob with only the attributes set, the one Samba writes nowadays
+    pub fn new(attributes: u32) -> Self {
+        DosAttrib {
+            attributes,
+            info: DosInfo::V5 {
+                valid_flags: XATTR_DOSINFO_ATTRIB,
+                create_time: 0,
+            },
+        }
+    }
+    /// Decodes the raw value of the extended attribute, in either of the two forms
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes, "user.DOSATTRIB blob");
+        let hex = match bytes.iter().position(|byte| *byte == 0) {
+            Some(_) => reader.nul_terminated()?,
+            None => bytes,
+        };
+        let hex_attributes = parse_hex(hex)?;
+        if hex.len() == bytes.len() || reader.remaining() < 4 {
+            return Ok(DosAttrib {
+                attributes: hex_attributes,
+                info: DosInfo::Legacy,
+            });
+        }
+        reader.align(2)?;
+        let version = reader.u16()?;
+        let level = reader.u16()?;
+        if version != level {
+            return Err(invalid(format!(
+                "The version {} doesn't match the union level {}",
+                version, level
+            )));
+        }
+        reader.align(4)?;
+        let (attributes, info) = match version {
+            0xFFFF => (reader.u32()?, DosInfo::Compat),
+            1 => {
+                let attributes = reader.u32()?;
+                let info = DosInfo::V1 {
+                    ea_size: reader.u32()?,
+                    size: reader.u64()?,
+                    alloc_size: reader.u64()?,
+                    create_time: reader.u64()?,
+                    change_time: reader.u64()?,
+                };
+                (attributes, info)
+            }
+            2 => {
+                let flags = reader.u32()?;
+                let attributes = reader.u32()?;
+                let info = DosInfo::V2 {
+                    flags,
+                    ea_size: reader.u32()?,
+                    size: reader.u64()?,
+                    alloc_size: reader.u64()?,
+                    create_time: reader.u64()?,
+                    change_time: reader.u64()?,
+                    write_time: reader.u64()?,
+                    name: String::from_utf8_lossy(reader.nul_terminated()?).into_owned(),
+                };
+                (attributes, info)
+            }
+            3 => {
+                let valid_flags = reader.u32()?;
+                let attributes = reader.u32()?;
+                let info = DosInfo::V3 {
+                    valid_flags,
+                    ea_size: reader.u32()?,
+                    size: reader.u64()?,
+                    alloc_size: reader.u64()?,
+                    create_time: reader.u64()?,
+                };
+                (attributes, info)
+            }
+            4 => {
+                let valid_flags = reader.u32()?;
+                let attributes = reader.u32()?;
+                let info = DosInfo::V4 {
+                    valid_flags,
+                    itime: reader.u64()?,
+                    create_time: reader.u64()?,
+                };
+                (attributes, info)
+            }
+            5 => {
+                let valid_flags = reader.u32()?;
+                let attributes = reader.u32()?;
+                let info = DosInfo::V5 {
+                    valid_flags,
+                    create_time: reader.u64()?,
+                };
+                (attributes, info)
+            }
+            other => return Err(invalid(format!("Unknown xattr_DosInfo version {}", other))),
+        };
+        Ok(DosAttrib { attributes, info })
+    }
+    /// Encodes the extended attribute value, keeping the version and every other field
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = format!("0x{:x}", self.attributes).into_bytes();
+        let version: u16 = match self.info {
+            DosInfo::Legacy => return bytes,
+            DosInfo::Compat => 0xFFFF,
+            DosInfo::V1 { .. } => 1,
+            DosInfo::V2 { .. } => 2,
+            DosInfo::V3 { .. } => 3,
+            DosInfo::V4 { .. } => 4,
+            DosInfo::V5 { .. } => 5,
+        };
+        bytes.push(0);
+        align(&mut bytes, 2);
+        bytes.extend_from_slice(&version.to_le_bytes());
+        bytes.extend_from_slice(&version.to_le_bytes());
+        align(&mut bytes, 4);
+        match &self.info {
+            DosInfo::Legacy => unreachable!(),
+            DosInfo::Compat => bytes.extend_from_slice(&self.attributes.to_le_bytes()),
+            DosInfo::V1 {
+                ea_size,
+                size,
+                alloc_size,
+                create_time,
+                change_time,
+            } => {
+                bytes.extend_from_slice(&self.attributes.to_le_bytes());
+                bytes.extend_from_slice(&ea_size.to_le_bytes());
+                bytes.extend_from_slice(&size.to_le_bytes());
+                bytes.extend_from_slice(&alloc_size.to_le_bytes());
+                bytes.extend_from_slice(&create_time.to_le_bytes());
+                bytes.extend_from_slice(&change_time.to_le_bytes());
+            }
+            DosInfo::V2 {
+                flags,
+                ea_size,
+                size,
+                alloc_size,
+                create_time,
+                change_time,
+                write_time,
+                name,
+            } => {
+                bytes.extend_from_slice(&flags.to_le_bytes());
+                bytes.extend_from_slice(&self.attributes.to_le_bytes());
+                bytes.extend_from_slice(&ea_size.to_le_bytes());
+                bytes.extend_from_slice(&size.to_le_bytes());
+                bytes.extend_from_slice(&alloc_size.to_le_bytes());
+                bytes.extend_from_slice(&create_time.to_le_bytes());
+                bytes.extend_from_slice(&change_time.to_le_bytes());
+                bytes.extend_from_slice(&write_time.to_le_bytes());
+                bytes.extend_from_slice(name.as_bytes());
+                bytes.push(0);
+            }
+            DosInfo::V3 {
+                valid_flags,
+                ea_size,
+                size,
+                alloc_size,
+                create_time,
+            } => {
+                bytes.extend_from_slice(&(valid_flags | XATTR_DOSINFO_ATTRIB).to_le_bytes());
+                bytes.extend_from_slice(&self.attributes.to_le_bytes());
+                bytes.extend_from_slice(&ea_size.to_le_bytes());
+                bytes.extend_from_slice(&size.to_le_bytes());
+                bytes.extend_from_slice(&alloc_size.to_le_bytes());
+                bytes.extend_from_slice(&create_time.to_le_bytes());
+            }
+            DosInfo::V4 {
+                valid_flags,
+                itime,
+                create_time,
+            } => {
+                bytes.extend_from_slice(&(valid_flags | XATTR_DOSINFO_ATTRIB).to_le_bytes());
+                bytes.extend_from_slice(&self.attributes.to_le_bytes());
+                bytes.extend_from_slice(&itime.to_le_bytes());
+                bytes.extend_from_slice(&create_time.to_le_bytes());
+            }
+            DosInfo::V5 {
+                valid_flags,
+                create_time,
+            } => {
+                bytes.extend_from_slice(&(valid_flags | XATTR_DOSINFO_ATTRIB).to_le_bytes());
+                bytes.extend_from_slice(&self.attributes.to_le_bytes());
+                bytes.extend_from_slice(&create_time.to_le_bytes());
+            }
+        }
+        bytes
+    }
+}
+/// Samba's extended attribute as a backend.
+/// Whether an element is a directory or a file is taken from the file system, not from the
+/// attribute. When writing, if the attribute already exists its version and the rest of its
+/// fields (e.g. the creation time) are kept and only the attributes change. Otherwise a version
+/// 5 blob is created.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Samba;
+#[cfg(target_os = "linux")]
+impl Samba {
+    /// Reads and decodes the `user.DOSATTRIB` attribute of `path`
+    pub fn read_dos_attrib(&self, path: &Path, links: Links) -> Result<DosAttrib> {
+        DosAttrib::decode(&super::xattr::get(path, XATTR_NAME, links)?)
+    }
+}
+#[cfg(target_os = "linux")]
+impl AttributeSource for Samba {
+    fn read(&self, path: &Path, links: Links) -> Result<Props> {
+        let dos_attrib = self.read_dos_attrib(path, links)?;
+        Ok(Props::from(super::with_element_type(
+            dos_attrib.attributes,
+            super::is_directory(path, links),
+        )))
+    }
+}
+#[cfg(target_os = "linux")]
+impl AttributeSink for Samba {
+    fn write(&self, path: &Path, props: Props, links: Links) -> Result<()> {
+        let mut dos_attrib = match self.read_dos_attrib(path, links) {
+            Ok(dos_attrib) => dos_attrib,
+            Err(Error {
+                kind: ErrorKind::AttributesNotFound(_),
+            }) => DosAttrib::new(0),
+            Err(error) => return Err(error),
+        };
+        dos_attrib.attributes = props.into();
+        super::xattr::set(path, XATTR_NAME, &dos_attrib.encode(), links)
+    }
+}
+/// Gets the Props Samba stores for `path`, following symbolic links.
+#[cfg(target_os = "linux")]
+pub fn read(path: impl AsRef<Path>) -> Result<Props> {
+    Samba.read(path.as_ref(), Links::Follow)
+}
+/// Stores `props` in the `user.DOSATTRIB` attribute of `path`, following symbolic links.
+#[cfg(target_os = "linux")]
+pub fn write(path: impl AsRef<Path>, props: Props) -> Result<()> {
+    Samba.write(path.as_ref(), props, Links::Follow)
+}
+fn parse_hex(hex: &[u8]) -> Result<u32> {
+    let hex = std::str::from_utf8(hex)
+        .map_err(|_| invalid("The attribute string isn't ASCII".to_string()))?;
+    let digits = hex
+        .strip_prefix("0x")
+        .or_else(|| hex.strip_prefix("0X"))
+        .unwrap_or(hex);
+    u32::from_str_radix(digits, 16)
+        .map_err(|_| invalid(format!("`{}` isn't an hexadecimal attribute string", hex)))
+}
+fn align(bytes: &mut Vec<u8>, alignment: usize) {
+    while !bytes.len().is_multiple_of(alignment) {
+        bytes.push(0);
+    }
+}
+fn invalid(msg: String) -> Error {
+    Error {
+        kind: ErrorKind::InvalidData(msg),
+    }
+}
@@ -0,0 +1,27 @@
+use crate::backends::cifs::*;
+use crate::error::*;
+
+#[test]
+fn round_trip() {
+    assert_eq!(decode(&encode(0x1026)), Ok(0x1026));
+    assert_eq!(encode(0x22), 0x22u32.to_ne_bytes().to_vec());
+}
+#[test]
+fn wrong_size() {
+    assert_eq!(
+        decode(&[0x20]),
+        Err(Error {
+            kind: ErrorKind::InvalidData("user.cifs.dosattrib can't be 1 bytes long".to_string())
+        })
+    );
+}
+#[cfg(target_os = "linux")]
+#[test]
+fn not_a_cifs_mount() {
+    let expected = Err(Error {
+        kind: ErrorKind::NotSupported(
+            "Cargo.toml isn't in a share mounted with mount.cifs".to_string(),
+        ),
+    });
+    assert_eq!(read("Cargo.toml"), expected);
+}
@@ -0,0 +1,121 @@
+use std::path::PathBuf;
+
+use crate::backends::{emulation::*, AttributeSource, Links};
+use crate::error::*;
+use crate::props::*;
+
+/// A fresh directory for every test, since they run in parallel
+fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("keypropdecode_emulation_{}", name));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn plain_file_and_directory() {
+    let dir = test_dir("plain");
+    let file = dir.join("file.txt");
+    std::fs::write(&file, b"content").unwrap();
+    let mut props = Props::default();
+    props.change_element_type(ArcDir::Archive(ArchiveProps::default()));
+    assert_eq!(MetadataEmulation.read(&file, Links::Follow), Ok(props));
+    assert_eq!(
+        MetadataEmulation.read(&dir, Links::Follow),
+        Ok(Props::default())
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+#[test]
+fn dot_files_are_hidden() {
+    let dir = test_dir("hidden");
+    let file = dir.join(".bashrc");
+    std::fs::write(&file, b"").unwrap();
+    std::fs::create_dir(dir.join(".config")).unwrap();
+    assert!(MetadataEmulation
+        .read(&file, Links::Follow)
+        .unwrap()
+        .is_hidden());
+    let hidden_dir = MetadataEmulation
+        .read(&dir.join(".config"), Links::Follow)
+        .unwrap();
+    assert!(hidden_dir.is_hidden());
+    assert!(hidden_dir.is_directory());
+    assert!(!MetadataEmulation
+        .read(&dir, Links::Follow)
+        .unwrap()
+        .is_hidden());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+#[test]
+fn read_only_without_write_permissions() {
+    let dir = test_dir("read_only");
+    let file = dir.join("file.txt");
+    std::fs::write(&file, b"").unwrap();
+    let mut permissions = std::fs::metadata(&file).unwrap().permissions();
+    permissions.set_readonly(true);
+    std::fs::set_permissions(&file, permissions).unwrap();
+    let props = MetadataEmulation.read(&file, Links::Follow).unwrap();
+    assert_eq!(props.is_read_only(), Ok(true));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+#[test]
+fn symbolic_links() {
+    let dir = test_dir("links");
+    let file = dir.join("file.txt");
+    let link = dir.join("link");
+    std::fs::write(&file, b"").unwrap();
+    std::os::unix::fs::symlink(&file, &link).unwrap();
+    assert!(MetadataEmulation
+        .read(&link, Links::NoFollow)
+        .unwrap()
+        .is_reparse());
+    assert!(!MetadataEmulation
+        .read(&link, Links::Follow)
+        .unwrap()
+        .is_reparse());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+#[cfg(target_os = "linux")]
+#[test]
+fn sparse_files() {
+    let dir = test_dir("sparse");
+    let file = dir.join("sparse.bin");
+    std::fs::File::create(&file)
+        .unwrap()
+        .set_len(16 * 1024 * 1024)
+        .unwrap();
+    let dense = dir.join("dense.bin");
+    std::fs::write(&dense, vec![1u8; 8192]).unwrap();
+    assert_eq!(
+        MetadataEmulation
+            .read(&file, Links::Follow)
+            .unwrap()
+            .is_sparse(),
+        Ok(true)
+    );
+    assert_eq!(
+        MetadataEmulation
+            .read(&dense, Links::Follow)
+            .unwrap()
+            .is_sparse(),
+        Ok(false)
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+#[test]
+fn devices() {
+    let props = MetadataEmulation
+        .read("/dev/null".as_ref(), Links::Follow)
+        .unwrap();
+    assert!(props.is_device());
+}
+#[test]
+fn missing_file() {
+    assert_eq!(
+        MetadataEmulation.read("doesn't_exists.txt".as_ref(), Links::Follow),
+        Err(Error {
+            kind: ErrorKind::FileNotFound
+        })
+    );
+}
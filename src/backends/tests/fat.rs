@@ -0,0 +1,87 @@
+use crate::backends::fat::*;
+use crate::error::*;
+use crate::props::*;
+
+#[test]
+fn file_without_archive_bit() {
+    let mut props = Props::default();
+    props.change_element_type(ArcDir::Archive(ArchiveProps::default()));
+    props.hidden(true);
+    assert_eq!(from_fat(0x02), props);
+}
+#[test]
+fn directory() {
+    let mut props = Props::default();
+    props.hidden(true);
+    assert_eq!(from_fat(0x12), props);
+    // A directory with the archive bit is still a directory
+    assert_eq!(from_fat(0x32), props);
+}
+#[test]
+fn volume_label_bit_is_ignored() {
+    assert_eq!(from_fat(0x28), from_fat(0x20));
+}
+#[test]
+fn to_fat_byte() {
+    let mut props = Props::default();
+    props.change_element_type(ArcDir::Archive(ArchiveProps::default()));
+    props.read_only(true).unwrap();
+    props.hidden(true);
+    assert_eq!(to_fat(props), Ok(0x23));
+    assert_eq!(to_fat(Props::default()), Ok(0x10));
+}
+#[test]
+fn normal_is_accepted() {
+    let mut props = Props::default();
+    props.change_element_type(ArcDir::Archive(ArchiveProps::default()));
+    props.normal(true).unwrap();
+    assert_eq!(to_fat(props), Ok(0x20));
+}
+#[test]
+fn unsupported_attributes() {
+    let mut props = Props::default();
+    props.encrypted(true);
+    props.pinned(true);
+    assert_eq!(
+        to_fat(props),
+        Err(Error {
+            kind: ErrorKind::NotSupported("FAT can't hold the attributes 0x84000".to_string())
+        })
+    );
+}
+#[cfg(target_os = "linux")]
+#[test]
+fn not_a_fat_file_system() {
+    assert!(matches!(
+        read("Cargo.toml"),
+        Err(Error {
+            kind: ErrorKind::NotSupported(_)
+        })
+    ));
+}
+#[cfg(target_os = "linux")]
+#[test]
+fn fifo_does_not_block() {
+    let path = std::env::temp_dir().join(format!("keypropdecode_fat_fifo_{}", std::process::id()));
+    let c_path = std::ffi::CString::new(path.to_str().unwrap()).unwrap();
+    // SAFETY: the path is nul terminated
+    assert_eq!(unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) }, 0);
+    let result = read(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(
+        result,
+        Err(Error {
+            kind: ErrorKind::NotSupported(_)
+        })
+    ));
+}
+#[cfg(target_os = "linux")]
+#[test]
+fn device_nodes_are_not_opened() {
+    assert!(matches!(
+        read("/dev/null"),
+        Err(Error {
+            kind: ErrorKind::NotSupported(_)
+        })
+    ));
+}
@@ -0,0 +1,94 @@
+use crate::backends::inode_flags::*;
+use crate::error::*;
+use crate::props::*;
+
+#[test]
+fn flags_to_props() {
+    let mut props = Props::default();
+    props.change_element_type(ArcDir::Archive(ArchiveProps::default()));
+    props.read_only(true).unwrap();
+    props.compressed(true);
+    props.encrypted(true);
+    props.not_content_indexed(true);
+    let flags = FS_IMMUTABLE_FL | FS_COMPR_FL | FS_ENCRYPT_FL | FS_NODUMP_FL | FS_NOCOW_FL;
+    assert_eq!(from_inode_flags(flags, false), props);
+    assert_eq!(to_inode_flags(props), flags & MAPPED_FLAGS);
+}
+#[test]
+fn immutable_directory_is_not_read_only() {
+    let mut props = Props::default();
+    props.compressed(true);
+    assert_eq!(from_inode_flags(FS_IMMUTABLE_FL | FS_COMPR_FL, true), props);
+    assert_eq!(to_inode_flags(props), FS_COMPR_FL);
+}
+#[test]
+fn statx_attributes() {
+    // STATX_ATTR_IMMUTABLE | STATX_ATTR_APPEND | STATX_ATTR_ENCRYPTED | STATX_ATTR_VERITY
+    let props = from_statx_attributes(0x0010_0830, false);
+    assert_eq!(props.is_read_only(), Ok(true));
+    assert!(props.is_encrypted());
+    assert!(!props.is_compressed());
+}
+#[test]
+fn lsattr_lines() {
+    assert_eq!(
+        parse_lsattr("----i---------e------- /etc/resolv.conf\n"),
+        Ok((FS_IMMUTABLE_FL | FS_EXTENT_FL, "/etc/resolv.conf"))
+    );
+    // Older versions print fewer columns
+    assert_eq!(
+        parse_lsattr("-------------e-- ./file with spaces"),
+        Ok((FS_EXTENT_FL, "./file with spaces"))
+    );
+    assert_eq!(parse_lsattr_flags("dC"), Ok(FS_NODUMP_FL | FS_NOCOW_FL));
+}
+#[test]
+fn invalid_lsattr_lines() {
+    assert_eq!(
+        parse_lsattr("----i---------e---?--- file"),
+        Err(Error {
+            kind: ErrorKind::InvalidData("'?' isn't an lsattr flag".to_string())
+        })
+    );
+    assert!(parse_lsattr("----i---------e-------").is_err());
+}
+#[test]
+fn lsattr_round_trip() {
+    for (_, flag) in LSATTR_LETTERS {
+        assert_eq!(parse_lsattr_flags(&format_lsattr(flag)), Ok(flag));
+    }
+    assert_eq!(format_lsattr(0), "-".repeat(22));
+}
+#[cfg(target_os = "linux")]
+#[test]
+fn no_dump_flag_in_temp_dir() {
+    use crate::backends::{AttributeSink, AttributeSource, Links};
+
+    let path = std::env::temp_dir().join("keypropdecode_inode_flags.txt");
+    std::fs::write(&path, b"").unwrap();
+    let Ok(props) = InodeFlags.read(&path, Links::Follow) else {
+        // The temporary directory may be in a file system without inode flags
+        return std::fs::remove_file(&path).unwrap();
+    };
+    let mut changed = props;
+    changed.not_content_indexed(true);
+    InodeFlags.write(&path, changed, Links::Follow).unwrap();
+    assert!(InodeFlags
+        .read(&path, Links::Follow)
+        .unwrap()
+        .is_not_content_indexed());
+    InodeFlags.write(&path, props, Links::Follow).unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+#[cfg(target_os = "linux")]
+#[test]
+fn device_nodes_are_not_opened() {
+    use crate::backends::{AttributeSource, Links};
+
+    assert!(matches!(
+        InodeFlags.read(std::path::Path::new("/dev/null"), Links::Follow),
+        Err(Error {
+            kind: ErrorKind::NotSupported(_)
+        })
+    ));
+}
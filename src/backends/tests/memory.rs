@@ -0,0 +1,144 @@
+use std::path::{Path, PathBuf};
+
+use crate::backends::{memory::*, AttributeSink, AttributeSource, Links};
+use crate::error::*;
+use crate::props::*;
+
+fn file() -> Props {
+    let mut props = Props::default();
+    props.change_element_type(ArcDir::Archive(ArchiveProps::default()));
+    props
+}
+
+#[test]
+fn insert_creates_the_parents() {
+    let store = MemoryStore::new();
+    store.insert("/a/b/c.txt", file()).unwrap();
+    assert_eq!(
+        store.elements(),
+        vec![
+            (PathBuf::from("/"), Props::default()),
+            (PathBuf::from("/a"), Props::default()),
+            (PathBuf::from("/a/b"), Props::default()),
+            (PathBuf::from("/a/b/c.txt"), file()),
+        ]
+    );
+}
+#[test]
+fn relative_paths() {
+    let store = MemoryStore::new();
+    store.insert("a/b.txt", file()).unwrap();
+    assert!(store.contains("a"));
+    assert!(!store.contains(""));
+}
+#[test]
+fn parent_is_a_file() {
+    let store = MemoryStore::new();
+    store.insert("/a.txt", file()).unwrap();
+    assert_eq!(
+        store.insert("/a.txt/b.txt", file()),
+        Err(Error {
+            kind: ErrorKind::Other("/a.txt isn't a directory".to_string())
+        })
+    );
+}
+#[test]
+fn read_and_write() {
+    let store = MemoryStore::new();
+    store.insert("/a.txt", file()).unwrap();
+    let mut hidden = file();
+    hidden.hidden(true);
+    store
+        .write(Path::new("/a.txt"), hidden, Links::Follow)
+        .unwrap();
+    assert_eq!(store.read(Path::new("/a.txt"), Links::NoFollow), Ok(hidden));
+    assert_eq!(
+        Props::from_path_with("/a.txt", &store, Links::Follow),
+        Ok(hidden)
+    );
+}
+#[test]
+fn write_needs_the_element() {
+    let store = MemoryStore::new();
+    assert_eq!(
+        store.write(Path::new("/a.txt"), file(), Links::Follow),
+        Err(Error {
+            kind: ErrorKind::FileNotFound
+        })
+    );
+    assert_eq!(
+        store.read(Path::new("/a.txt"), Links::Follow),
+        Err(Error {
+            kind: ErrorKind::FileNotFound
+        })
+    );
+}
+#[test]
+fn rename_moves_the_whole_tree() {
+    let store = MemoryStore::new();
+    store.insert("/a/b/c.txt", file()).unwrap();
+    store.insert("/a/d.txt", file()).unwrap();
+    store.insert("/ab.txt", file()).unwrap();
+    store.insert("/x", Props::default()).unwrap();
+    store.rename("/a", "/x/a2").unwrap();
+    assert!(!store.contains("/a"));
+    assert!(!store.contains("/a/b/c.txt"));
+    assert!(store.contains("/x/a2/b/c.txt"));
+    assert!(store.contains("/x/a2/d.txt"));
+    assert!(store.contains("/ab.txt"));
+}
+#[test]
+fn rename_errors() {
+    let store = MemoryStore::new();
+    store.insert("/a/b.txt", file()).unwrap();
+    store.insert("/c.txt", file()).unwrap();
+    assert!(store.rename("/missing", "/d").is_err());
+    assert!(store.rename("/a", "/c.txt").is_err());
+    assert!(store.rename("/a", "/a/b").is_err());
+    assert!(store.rename("/a", "/missing/a").is_err());
+    assert!(store.rename("/a", "/c.txt/a").is_err());
+}
+#[test]
+fn remove_is_recursive() {
+    let store = MemoryStore::new();
+    store.insert("/a/b/c.txt", file()).unwrap();
+    store.insert("/a.txt", file()).unwrap();
+    store.remove("/a").unwrap();
+    assert_eq!(store.read_dir("/").unwrap(), vec![PathBuf::from("/a.txt")]);
+    assert_eq!(
+        store.remove("/a"),
+        Err(Error {
+            kind: ErrorKind::FileNotFound
+        })
+    );
+}
+#[test]
+fn read_dir_lists_only_the_children() {
+    let store = MemoryStore::new();
+    store.insert("/a/b/c.txt", file()).unwrap();
+    store.insert("/a/d.txt", file()).unwrap();
+    store.insert("/ab.txt", file()).unwrap();
+    assert_eq!(
+        store.read_dir("/a").unwrap(),
+        vec![PathBuf::from("/a/b"), PathBuf::from("/a/d.txt")]
+    );
+    assert!(store.read_dir("/a/d.txt").is_err());
+    assert!(store.read_dir("/missing").is_err());
+}
+#[test]
+fn directory_with_children_stays_a_directory() {
+    let store = MemoryStore::new();
+    store.insert("/a/b.txt", file()).unwrap();
+    let error = Err(Error {
+        kind: ErrorKind::Other("/a is a directory that isn't empty".to_string()),
+    });
+    assert_eq!(store.insert("/a", file()), error);
+    assert_eq!(store.write(Path::new("/a"), file(), Links::Follow), error);
+    assert!(store
+        .read(Path::new("/a"), Links::Follow)
+        .unwrap()
+        .is_directory());
+    // Empty directories can still become files
+    store.remove("/a/b.txt").unwrap();
+    store.write(Path::new("/a"), file(), Links::Follow).unwrap();
+}
@@ -0,0 +1,10 @@
+mod cifs;
+#[cfg(unix)]
+mod emulation;
+mod fat;
+mod inode_flags;
+mod memory;
+mod ntfs;
+mod samba;
+mod source;
+mod unix_mode;
@@ -0,0 +1,46 @@
+use crate::backends::ntfs::*;
+use crate::error::*;
+
+#[test]
+fn names() {
+    assert_eq!(NtfsXattr::Attrib.name(), "system.ntfs_attrib");
+    assert_eq!(NtfsXattr::AttribBe.name(), "system.ntfs_attrib_be");
+    assert_eq!(NtfsXattr::DosAttrib.name(), "system.dos_attrib");
+}
+#[test]
+fn native_order() {
+    let bytes = 0x2026u32.to_ne_bytes();
+    assert_eq!(NtfsXattr::Attrib.decode(&bytes), Ok(0x2026));
+    assert_eq!(NtfsXattr::Attrib.encode(0x2026), bytes.to_vec());
+}
+#[test]
+fn big_endian() {
+    assert_eq!(NtfsXattr::AttribBe.decode(&[0, 0, 0x20, 0x26]), Ok(0x2026));
+    assert_eq!(NtfsXattr::AttribBe.encode(0x2026), vec![0, 0, 0x20, 0x26]);
+}
+#[test]
+fn dos_attrib_keeps_the_lowest_byte() {
+    assert_eq!(NtfsXattr::DosAttrib.decode(&[0x27]), Ok(0x27));
+    assert_eq!(NtfsXattr::DosAttrib.encode(0x2027), vec![0x27]);
+}
+#[test]
+fn wrong_size() {
+    assert_eq!(
+        NtfsXattr::DosAttrib.decode(&[0, 0]),
+        Err(Error {
+            kind: ErrorKind::InvalidData("system.dos_attrib can't be 2 bytes long".to_string())
+        })
+    );
+    assert!(NtfsXattr::Attrib.decode(&[0x20]).is_err());
+}
+#[cfg(target_os = "linux")]
+#[test]
+fn not_an_ntfs_volume() {
+    let result = read("Cargo.toml");
+    assert!(matches!(
+        result,
+        Err(Error {
+            kind: ErrorKind::NotSupported(_)
+        })
+    ));
+}
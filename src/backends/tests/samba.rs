@@ -0,0 +1,151 @@
+use crate::backends::samba::*;
+use crate::error::*;
+
+const V3_BLOB: [u8; 48] = [
+    0x30, 0x78, 0x32, 0x30, 0x00, 0x00, 0x03, 0x00, 0x03, 0x00, 0x00, 0x00, // 0x20, version 3
+    0x11, 0x00, 0x00, 0x00, 0x20, 0x00, 0x00, 0x00, // valid flags and attributes
+    0x00, 0x00, 0x00, 0x00, // extended attributes size
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // size
+    0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, // allocation size
+    0x80, 0x3e, 0xd5, 0xde, 0xb1, 0x9d, 0x01, 0x00, // create time
+];
+
+#[test]
+fn legacy_string() {
+    let dos_attrib = DosAttrib::decode(b"0x23").unwrap();
+    assert_eq!(dos_attrib.attributes, 0x23);
+    assert_eq!(dos_attrib.info, DosInfo::Legacy);
+    assert_eq!(DosAttrib::decode(b"0x2\0").unwrap().attributes, 0x2);
+    assert_eq!(dos_attrib.encode(), b"0x23".to_vec());
+}
+#[test]
+fn version_3() {
+    let dos_attrib = DosAttrib::decode(&V3_BLOB).unwrap();
+    assert_eq!(dos_attrib.attributes, 0x20);
+    assert_eq!(
+        dos_attrib.info,
+        DosInfo::V3 {
+            valid_flags: XATTR_DOSINFO_ATTRIB | XATTR_DOSINFO_CREATE_TIME,
+            ea_size: 0,
+            size: 0,
+            alloc_size: 0,
+            create_time: 0x0001_9db1_ded5_3e80,
+        }
+    );
+    assert_eq!(dos_attrib.encode(), V3_BLOB.to_vec());
+}
+#[test]
+fn changing_attributes_keeps_the_create_time() {
+    let mut dos_attrib = DosAttrib::decode(&V3_BLOB).unwrap();
+    dos_attrib.attributes = 0x22;
+    let decoded = DosAttrib::decode(&dos_attrib.encode()).unwrap();
+    assert_eq!(decoded.attributes, 0x22);
+    assert_eq!(decoded.info, DosAttrib::decode(&V3_BLOB).unwrap().info);
+    assert!(decoded.encode().starts_with(b"0x22\0"));
+}
+#[test]
+fn every_version_round_trips() {
+    let infos = [
+        DosInfo::Compat,
+        DosInfo::V1 {
+            ea_size: 1,
+            size: 2,
+            alloc_size: 3,
+            create_time: 4,
+            change_time: 5,
+        },
+        DosInfo::V2 {
+            flags: 1,
+            ea_size: 2,
+            size: 3,
+            alloc_size: 4,
+            create_time: 5,
+            change_time: 6,
+            write_time: 7,
+            name: "report.txt".to_string(),
+        },
+        DosInfo::V4 {
+            valid_flags: XATTR_DOSINFO_ATTRIB | XATTR_DOSINFO_ITIME,
+            itime: 1,
+            create_time: 2,
+        },
+        DosInfo::V5 {
+            valid_flags: XATTR_DOSINFO_ATTRIB,
+            create_time: 3,
+        },
+    ];
+    for info in infos {
+        // A long string moves the version to an already aligned offset
+        for attributes in [0x2, 0x100020] {
+            let dos_attrib = DosAttrib {
+                attributes,
+                info: info.clone(),
+            };
+            assert_eq!(DosAttrib::decode(&dos_attrib.encode()).unwrap(), dos_attrib);
+        }
+    }
+}
+#[test]
+fn unknown_version() {
+    let mut blob = V3_BLOB;
+    blob[6] = 9;
+    blob[8] = 9;
+    assert_eq!(
+        DosAttrib::decode(&blob),
+        Err(Error {
+            kind: ErrorKind::InvalidData("Unknown xattr_DosInfo version 9".to_string())
+        })
+    );
+}
+#[test]
+fn truncated_blob() {
+    assert_eq!(
+        DosAttrib::decode(&V3_BLOB[..30]),
+        Err(Error {
+            kind: ErrorKind::InvalidData("The user.DOSATTRIB blob is truncated".to_string())
+        })
+    );
+}
+#[test]
+fn not_hexadecimal() {
+    assert!(DosAttrib::decode(b"hidden").is_err());
+}
+/// A temporary file that is removed when the test ends, even if it returns early or fails
+#[cfg(target_os = "linux")]
+struct TempFile(std::path::PathBuf);
+#[cfg(target_os = "linux")]
+impl TempFile {
+    /// Creates an empty file whose name is unique to the test and the process
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!(
+            "keypropdecode_samba_{}_{}",
+            name,
+            std::process::id()
+        ));
+        std::fs::write(&path, b"").unwrap();
+        TempFile(path)
+    }
+}
+#[cfg(target_os = "linux")]
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.0);
+    }
+}
+#[cfg(target_os = "linux")]
+#[test]
+fn write_and_read_back() {
+    use crate::props::*;
+    let file = TempFile::new("write_and_read_back");
+    let mut props = Props::default();
+    props.change_element_type(ArcDir::Archive(ArchiveProps::default()));
+    props.hidden(true);
+    match write(&file.0, props) {
+        // The temporary directory may be in a file system without user extended attributes
+        Err(Error {
+            kind: ErrorKind::NotSupported(_),
+        }) => return,
+        result => result.unwrap(),
+    }
+    assert_eq!(read(&file.0).unwrap(), props);
+}
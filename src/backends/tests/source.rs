@@ -0,0 +1,97 @@
+use std::path::Path;
+
+use crate::backends::*;
+use crate::props::*;
+
+struct Fixed(u32);
+impl AttributeSource for Fixed {
+    fn read(&self, path: &Path, links: Links) -> Result<Props> {
+        match (path.to_str(), links) {
+            (Some("link"), Links::NoFollow) => Ok(Props::from(self.0 | 0x400)),
+            (Some("link"), Links::Follow) => Ok(Props::from(self.0)),
+            _ => Err(Error {
+                kind: ErrorKind::NotSupported("Only the link".to_string()),
+            }),
+        }
+    }
+}
+
+#[test]
+fn follows_by_default() {
+    assert_eq!(Links::default(), Links::Follow);
+}
+#[test]
+fn custom_source() {
+    let props = Props::from_path_with("link", &Fixed(0x22), Links::Follow).unwrap();
+    assert!(props.is_hidden());
+    assert!(!props.is_reparse());
+    let link = Props::from_path_with("link", &Fixed(0x22), Links::NoFollow).unwrap();
+    assert!(link.is_reparse());
+}
+#[test]
+fn trait_objects() {
+    let sources: Vec<Box<dyn AttributeSource>> = vec![Box::new(Fixed(0x20)), Box::new(Fixed(0x10))];
+    let props: Vec<bool> = sources
+        .iter()
+        .map(|source| {
+            Props::from_path_with("link", source.as_ref(), Links::Follow)
+                .unwrap()
+                .is_archive()
+        })
+        .collect();
+    assert_eq!(props, vec![true, false]);
+}
+#[test]
+fn default_supports() {
+    assert!(Fixed(0x20).supports(Path::new("link")));
+    assert!(!Fixed(0x20).supports(Path::new("other")));
+}
+#[test]
+fn file_not_found() {
+    assert_eq!(
+        Props::from_path("doesn't_exists.txt"),
+        Err(Error {
+            kind: ErrorKind::FileNotFound
+        })
+    );
+    assert_eq!(
+        Props::try_from("doesn't_exists.txt"),
+        Props::from_path("doesn't_exists.txt")
+    );
+}
+#[cfg(target_os = "linux")]
+#[test]
+fn auto_picks_a_supported_backend() {
+    let path = std::env::temp_dir().join("keypropdecode_auto_picks_a_supported_backend");
+    std::fs::write(&path, b"").unwrap();
+    let mut props = Props::default();
+    props.change_element_type(ArcDir::Archive(ArchiveProps::default()));
+    props.hidden(true);
+    match Auto.write(&path, props, Links::Follow) {
+        // The temporary directory may be in a file system without user extended attributes
+        Err(Error {
+            kind: ErrorKind::NotSupported(_),
+        }) => return,
+        result => result.unwrap(),
+    }
+    assert_eq!(Props::from_path(&path).unwrap(), props);
+    assert_eq!(samba::read(&path).unwrap(), props);
+    std::fs::remove_file(&path).unwrap();
+}
+#[cfg(target_os = "linux")]
+#[test]
+fn auto_guesses_without_stored_attributes() {
+    let dir =
+        std::env::temp_dir().join(format!("keypropdecode_auto_guesses_{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(".hidden_file");
+    std::fs::write(&path, b"").unwrap();
+    let props = Props::from_path(&path);
+    let directory = Props::from_path(&dir);
+    std::fs::remove_dir_all(&dir).unwrap();
+    // Without user.DOSATTRIB the attributes come from the metadata
+    let props = props.unwrap();
+    assert!(props.is_archive());
+    assert!(props.is_hidden());
+    assert!(directory.unwrap().is_directory());
+}
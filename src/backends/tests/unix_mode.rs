@@ -0,0 +1,140 @@
+use crate::backends::unix_mode::*;
+use crate::props::constants::*;
+use crate::props::*;
+
+fn file(hidden: bool, system: bool, read_only: bool) -> Props {
+    let mut attributes = ARCHIVE;
+    if hidden {
+        attributes |= HIDDEN;
+    }
+    if system {
+        attributes |= SYSTEM;
+    }
+    if read_only {
+        attributes |= READ_ONLY;
+    }
+    Props::from(attributes)
+}
+fn all_mapped() -> UnixModeMapping {
+    UnixModeMapping {
+        map_system: true,
+        map_hidden: true,
+        map_readonly: MapReadonly::Yes,
+        create_mask: 0o777,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn samba_defaults() {
+    let mapping = UnixModeMapping::default();
+    // The create mask removes the group and others execute bits
+    assert_eq!(mapping.to_mode(file(true, true, false)), 0o744);
+    assert_eq!(mapping.to_mode(Props::default()), 0o755);
+    // map readonly = no
+    assert_eq!(
+        mapping.from_mode(0o444, false, "file.txt"),
+        file(false, false, false)
+    );
+    assert_eq!(
+        mapping.from_mode(0o644, false, "dir/.hidden"),
+        file(true, false, false)
+    );
+}
+#[test]
+fn every_option() {
+    let mapping = all_mapped();
+    assert_eq!(mapping.to_mode(file(true, true, true)), 0o555);
+    assert_eq!(mapping.to_mode(file(false, false, false)), 0o766);
+    assert_eq!(
+        mapping.from_mode(0o555, false, "file.txt"),
+        file(true, true, true)
+    );
+    assert_eq!(
+        mapping.from_mode(0o764, false, "file.txt"),
+        file(false, false, false)
+    );
+}
+#[test]
+fn directories_ignore_execute_bits() {
+    let mapping = all_mapped();
+    let mut hidden_dir = Props::default();
+    hidden_dir.hidden(true);
+    assert_eq!(mapping.to_mode(hidden_dir), 0o755);
+    assert_eq!(mapping.from_mode(0o755, true, "dir"), Props::default());
+    assert_eq!(mapping.from_mode(0o755, true, ".dir"), hidden_dir);
+}
+#[test]
+fn map_readonly_permissions() {
+    let mapping = UnixModeMapping {
+        map_readonly: MapReadonly::Permissions,
+        ..Default::default()
+    };
+    assert_eq!(
+        mapping.from_mode(0o464, false, "a").is_read_only(),
+        Ok(false)
+    );
+    assert_eq!(
+        mapping.from_mode(0o444, false, "a").is_read_only(),
+        Ok(true)
+    );
+    let yes = all_mapped();
+    assert_eq!(yes.from_mode(0o464, false, "a").is_read_only(), Ok(true));
+}
+#[test]
+fn no_hide_dot_files() {
+    let mapping = UnixModeMapping {
+        hide_dot_files: false,
+        ..Default::default()
+    };
+    assert!(!mapping.from_mode(0o644, false, ".profile").is_hidden());
+}
+#[test]
+fn apply_keeps_unmapped_bits() {
+    let mapping = UnixModeMapping::default();
+    // Regular file with setgid, only the owner can read and write
+    let mode = 0o102_600 | 0o2000;
+    assert_eq!(
+        mapping.apply_to_mode(file(false, false, false), mode),
+        0o102_700 | 0o2000
+    );
+    // Becoming read only removes the write bits but keeps the read ones
+    assert_eq!(
+        mapping.apply_to_mode(file(false, false, true), 0o100_640),
+        0o100_540
+    );
+    // The group execute bit isn't used by this mapping, so it stays
+    assert_eq!(
+        mapping.apply_to_mode(file(false, true, false), 0o100_654),
+        0o100_754
+    );
+}
+#[cfg(unix)]
+#[test]
+fn read_and_write_modes() {
+    use crate::backends::{AttributeSink, AttributeSource, Links};
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = std::env::temp_dir().join("keypropdecode_unix_mode.txt");
+    std::fs::write(&path, b"").unwrap();
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o644)).unwrap();
+    let mapping = all_mapped();
+    mapping
+        .write(&path, file(true, false, true), Links::Follow)
+        .unwrap();
+    let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o545);
+    assert_eq!(
+        mapping.read(&path, Links::Follow),
+        Ok(file(true, false, true))
+    );
+    assert_eq!(
+        mapping.from_permissions(
+            &mapping.to_permissions(file(true, true, false)),
+            false,
+            &path
+        ),
+        file(true, true, false)
+    );
+    std::fs::remove_file(&path).unwrap();
+}
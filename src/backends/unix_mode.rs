@@ -0,0 +1,224 @@
+//! When Samba doesn't store the DOS attributes (`store dos attributes = no`) it encodes them in
+//! the mode of the file, and this is the same mapping with the same options:
+//!
+//! | Option | Default | Attribute | Mode |
+//! |--------|---------|-----------|------|
+//! | `map archive` | yes | archive | owner execute (`0o100`) |
+//! | `map system` | no | system | group execute (`0o010`) |
+//! | `map hidden` | no | hidden | others execute (`0o001`) |
+//! | `map readonly` | no | read_only | no owner write (`0o200`) |
+//! | `hide dot files` | yes | hidden | the name starts with a dot |
+//!
+//! The execute bits only hold attributes for files, a directory always has them.
+//! Like Samba, a read only file never has write bits, whatever `map readonly` says, and the new
+//! mode goes through `create mask` or `directory mask`. Since Props marks every file as archive,
+//! with `map archive` every file gets the owner execute bit.
+//! ```
+//! use keypropdecode::backends::unix_mode::*;
+//! use keypropdecode::props;
+//!
+//! let mapping = UnixModeMapping {
+//!     map_hidden: true,
+//!     create_mask: 0o777,
+//!     ..Default::default()
+//! };
+//! assert_eq!(mapping.to_mode(props!(file: hidden)), 0o767);
+//! assert_eq!(mapping.from_mode(0o745, false, "notes.txt"), props!(file: hidden));
+//! assert_eq!(mapping.from_mode(0o644, false, ".profile"), props!(file: hidden));
+//! ```
+use std::path::Path;
+
+use crate::props::constants::*;
+use crate::Props;
+
+#[cfg(unix)]
+use super::{AttributeSink, AttributeSource, Links};
+#[cfg(unix)]
+use crate::error::*;
+
+/// The values of Samba's `map readonly` option
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum MapReadonly {
+    /// Files are never read only
+    #[default]
+    No,
+    /// Files without the owner write bit are read only
+    Yes,
+    /// Files the user can't write are read only.
+    /// The user isn't known here, so they are the ones without any write bit.
+    Permissions,
+}
+/// The Samba options that decide how attributes are stored in a mode.
+/// The default is the default of Samba.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct UnixModeMapping {
+    /// The archive attribute is the owner execute bit
+    pub map_archive: bool,
+    /// The system attribute is the group execute bit
+    pub map_system: bool,
+    /// The hidden attribute is the others execute bit
+    pub map_hidden: bool,
+    /// How the read only attribute is read from the mode
+    pub map_readonly: MapReadonly,
+    /// Elements whose name starts with a dot are hidden
+    pub hide_dot_files: bool,
+    /// The bits a new file mode can have
+    pub create_mask: u32,
+    /// The bits a new directory mode can have
+    pub directory_mask: u32,
+}
+impl Default for UnixModeMapping {
+    fn default() -> Self {
+        UnixModeMapping {
+            map_archive: true,
+            map_system: false,
+            map_hidden: false,
+            map_readonly: MapReadonly::No,
+            hide_dot_files: true,
+            create_mask: 0o744,
+            directory_mask: 0o755,
+        }
+    }
+}
+const WRITE_BITS: u32 = 0o222;
+const READ_BITS: u32 = 0o444;
+/// The file type, setuid, setgid and sticky bits
+const KEPT_BITS: u32 = 0o177_000;
+impl UnixModeMapping {
+    /// The execute bits this mapping uses to hold attributes
+    fn mapped_execute_bits(&self) -> u32 {
+        let mut bits = 0;
+        if self.map_archive {
+            bits |= 0o100;
+        }
+        if self.map_system {
+            bits |= 0o010;
+        }
+        if self.map_hidden {
+            bits |= 0o001;
+        }
+        bits
+    }
+    /// Gets the permission bits of a new element with the attributes of `props`
+    pub fn to_mode(&self, props: Props) -> u32 {
+        let attributes: u32 = props.into();
+        let mut mode = READ_BITS | WRITE_BITS;
+        if attributes & READ_ONLY != 0 {
+            mode &= !WRITE_BITS;
+        }
+        if props.is_directory() {
+            return (mode | 0o111) & self.directory_mask;
+        }
+        if self.map_archive && attributes & ARCHIVE != 0 {
+            mode |= 0o100;
+        }
+        if self.map_system && attributes & SYSTEM != 0 {
+            mode |= 0o010;
+        }
+        if self.map_hidden && attributes & HIDDEN != 0 {
+            mode |= 0o001;
+        }
+        mode & self.create_mask
+    }
+    /// Gets the mode an existing element with `mode` gets when its attributes change to `props`.
+    /// Like Samba, it keeps the file type, the special bits, the execute bits the mapping
+    /// doesn't use, the read bits and, unless the file becomes read only, the write bits.
+    pub fn apply_to_mode(&self, props: Props, mode: u32) -> u32 {
+        let mut new_mode = self.to_mode(props);
+        let kept = KEPT_BITS | (0o111 & !self.mapped_execute_bits());
+        new_mode |= mode & kept;
+        if mode & READ_BITS != 0 {
+            new_mode = (new_mode & !READ_BITS) | (mode & READ_BITS);
+        }
+        if !props.is_read_only().unwrap_or(false) {
+            new_mode |= mode & WRITE_BITS;
+        }
+        // Directories always keep their execute bits, whatever the mapping
+        if props.is_directory() {
+            new_mode |= mode & 0o111;
+        }
+        new_mode
+    }
+    /// Gets the Props of a file or, if `directory`, a directory with `mode` at `path`.
+    /// Only the name of the path is used, for `hide dot files`.
+    pub fn from_mode(&self, mode: u32, directory: bool, path: impl AsRef<Path>) -> Props {
+        let mut attributes = 0;
+        let read_only = match self.map_readonly {
+            MapReadonly::No => false,
+            MapReadonly::Yes => mode & 0o200 == 0,
+            MapReadonly::Permissions => mode & WRITE_BITS == 0,
+        };
+        if read_only {
+            attributes |= READ_ONLY;
+        }
+        if !directory {
+            if self.map_system && mode & 0o010 != 0 {
+                attributes |= SYSTEM;
+            }
+            if self.map_hidden && mode & 0o001 != 0 {
+                attributes |= HIDDEN;
+            }
+        }
+        let dot_file = path
+            .as_ref()
+            .file_name()
+            .map(|name| name.as_encoded_bytes().starts_with(b"."))
+            .unwrap_or(false);
+        if self.hide_dot_files && dot_file {
+            attributes |= HIDDEN;
+        }
+        Props::from(super::with_element_type(attributes, directory))
+    }
+    /// Gets the permissions of a new element with the attributes of `props`
+    #[cfg(unix)]
+    pub fn to_permissions(&self, props: Props) -> std::fs::Permissions {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::Permissions::from_mode(self.to_mode(props))
+    }
+    /// Gets the Props of a file or, if `directory`, a directory with `permissions` at `path`
+    #[cfg(unix)]
+    pub fn from_permissions(
+        &self,
+        permissions: &std::fs::Permissions,
+        directory: bool,
+        path: impl AsRef<Path>,
+    ) -> Props {
+        use std::os::unix::fs::PermissionsExt;
+        self.from_mode(permissions.mode(), directory, path)
+    }
+}
+/// Reads the mode of the element like Samba would, and writes the attributes by changing it.
+/// Symbolic links have no mode of their own, so they are always followed.
+#[cfg(unix)]
+impl AttributeSource for UnixModeMapping {
+    fn read(&self, path: &Path, _links: Links) -> Result<Props> {
+        let metadata = metadata(path)?;
+        Ok(self.from_permissions(&metadata.permissions(), metadata.is_dir(), path))
+    }
+    fn supports(&self, _path: &Path) -> bool {
+        true
+    }
+}
+#[cfg(unix)]
+impl AttributeSink for UnixModeMapping {
+    fn write(&self, path: &Path, props: Props, _links: Links) -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let metadata = metadata(path)?;
+        let mode = self.apply_to_mode(props, metadata.permissions().mode());
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(mode & 0o7777)).map_err(
+            |error| Error {
+                kind: ErrorKind::Other(format!("{}: {}", path.display(), error)),
+            },
+        )
+    }
+}
+#[cfg(unix)]
+fn metadata(path: &Path) -> Result<std::fs::Metadata> {
+    std::fs::metadata(path).map_err(|error| Error {
+        kind: match error.kind() {
+            std::io::ErrorKind::NotFound => ErrorKind::FileNotFound,
+            _ => ErrorKind::Other(format!("{}: {}", path.display(), error)),
+        },
+    })
+}
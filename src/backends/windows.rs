@@ -0,0 +1,29 @@
+//! What Windows itself stores for every element, through
+//! `std::os::windows::fs::MetadataExt::file_attributes`.
+use std::os::windows::fs::MetadataExt;
+use std::path::Path;
+
+use super::{AttributeSource, Links};
+use crate::error::*;
+use crate::Props;
+
+/// The metadata Windows returns as a backend.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct WindowsMetadata;
+impl AttributeSource for WindowsMetadata {
+    fn read(&self, path: &Path, links: Links) -> Result<Props> {
+        let metadata = match links {
+            Links::Follow => std::fs::metadata(path),
+            Links::NoFollow => std::fs::symlink_metadata(path),
+        };
+        match metadata {
+            Ok(metadata) => Ok(Props::from(metadata.file_attributes())),
+            Err(_) => Err(Error {
+                kind: ErrorKind::FileNotFound,
+            }),
+        }
+    }
+    fn supports(&self, _path: &Path) -> bool {
+        true
+    }
+}
@@ -0,0 +1,101 @@
+use std::{ffi::CString, io, os::unix::ffi::OsStrExt, path::Path};
+
+use super::Links;
+use crate::error::*;
+
+type GetXattr = unsafe extern "C" fn(
+    *const libc::c_char,
+    *const libc::c_char,
+    *mut libc::c_void,
+    libc::size_t,
+) -> libc::ssize_t;
+type SetXattr = unsafe extern "C" fn(
+    *const libc::c_char,
+    *const libc::c_char,
+    *const libc::c_void,
+    libc::size_t,
+    libc::c_int,
+) -> libc::c_int;
+
+/// Reads the extended attribute `name` of `path`
+pub(crate) fn get(path: &Path, name: &str, links: Links) -> Result<Vec<u8>> {
+    let c_path = c_path(path)?;
+    let c_name = c_name(name);
+    let getxattr: GetXattr = match links {
+        Links::Follow => libc::getxattr,
+        Links::NoFollow => libc::lgetxattr,
+    };
+    loop {
+        // SAFETY: both strings are nul terminated and a null buffer with size 0 only asks for the size
+        let size = unsafe { getxattr(c_path.as_ptr(), c_name.as_ptr(), std::ptr::null_mut(), 0) };
+        if size < 0 {
+            return Err(map_error(io::Error::last_os_error(), path, name));
+        }
+        let mut value = vec![0u8; size as usize];
+        // SAFETY: the buffer is valid for `value.len()` bytes
+        let read = unsafe {
+            getxattr(
+                c_path.as_ptr(),
+                c_name.as_ptr(),
+                value.as_mut_ptr().cast(),
+                value.len(),
+            )
+        };
+        if read < 0 {
+            let error = io::Error::last_os_error();
+            // The attribute grew between both calls
+            if error.raw_os_error() == Some(libc::ERANGE) {
+                continue;
+            }
+            return Err(map_error(error, path, name));
+        }
+        value.truncate(read as usize);
+        return Ok(value);
+    }
+}
+/// Creates or replaces the extended attribute `name` of `path`
+pub(crate) fn set(path: &Path, name: &str, value: &[u8], links: Links) -> Result<()> {
+    let c_path = c_path(path)?;
+    let c_name = c_name(name);
+    let setxattr: SetXattr = match links {
+        Links::Follow => libc::setxattr,
+        Links::NoFollow => libc::lsetxattr,
+    };
+    // SAFETY: both strings are nul terminated and the value is valid for `value.len()` bytes
+    let result = unsafe {
+        setxattr(
+            c_path.as_ptr(),
+            c_name.as_ptr(),
+            value.as_ptr().cast(),
+            value.len(),
+            0,
+        )
+    };
+    if result < 0 {
+        return Err(map_error(io::Error::last_os_error(), path, name));
+    }
+    Ok(())
+}
+fn c_path(path: &Path) -> Result<CString> {
+    CString::new(path.as_os_str().as_bytes()).map_err(|_| Error {
+        kind: ErrorKind::Other(format!("{} contains a nul byte", path.display())),
+    })
+}
+fn c_name(name: &str) -> CString {
+    CString::new(name).expect("attribute names don't have nul bytes")
+}
+fn map_error(error: io::Error, path: &Path, name: &str) -> Error {
+    let kind = match error.raw_os_error() {
+        Some(libc::ENOENT) | Some(libc::ENOTDIR) => ErrorKind::FileNotFound,
+        Some(libc::ENODATA) => {
+            ErrorKind::AttributesNotFound(format!("{} has no {} attribute", path.display(), name))
+        }
+        Some(libc::ENOTSUP) => ErrorKind::NotSupported(format!(
+            "The file system of {} doesn't support the {} attribute",
+            path.display(),
+            name
+        )),
+        _ => ErrorKind::Other(format!("{} of {}: {}", name, path.display(), error)),
+    };
+    Error { kind }
+}
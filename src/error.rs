@@ -10,9 +10,13 @@ pub struct Error {
     pub kind: ErrorKind,
 }
 #[derive(PartialEq)]
+#[non_exhaustive]
 pub enum ErrorKind {
     FileNotFound,
     NotAFile(String),
+    AttributesNotFound(String),
+    NotSupported(String),
+    InvalidData(String),
     Other(String),
 }
 
@@ -21,6 +25,9 @@ impl Display for Error {
         match &self.kind {
             ErrorKind::FileNotFound => write!(f, "The file path you provided was not found"),
             ErrorKind::NotAFile(msg) => write!(f, "Not marked as a file: {}", msg),
+            ErrorKind::AttributesNotFound(msg) => write!(f, "No attributes stored: {}", msg),
+            ErrorKind::NotSupported(msg) => write!(f, "Not supported: {}", msg),
+            ErrorKind::InvalidData(msg) => write!(f, "Invalid attribute data: {}", msg),
             ErrorKind::Other(msg) => write!(f, "Undefined error: {}", msg),
         }
     }
@@ -30,6 +37,9 @@ impl Debug for Error {
         match &self.kind {
             ErrorKind::FileNotFound => write!(f, "The file path you provided was not found"),
             ErrorKind::NotAFile(msg) => write!(f, "Not marked as a file: {}", msg),
+            ErrorKind::AttributesNotFound(msg) => write!(f, "No attributes stored: {}", msg),
+            ErrorKind::NotSupported(msg) => write!(f, "Not supported: {}", msg),
+            ErrorKind::InvalidData(msg) => write!(f, "Invalid attribute data: {}", msg),
             ErrorKind::Other(msg) => write!(f, "Undefined error: {}", msg),
         }
     }
@@ -0,0 +1,383 @@
+//! Reads the directory entry sets of exFAT images. Every file or directory is a set of
+//! directory entries: a File entry with the 16 bit FileAttributes field, a Stream Extension
+//! entry with where the data is, and File Name entries with 15 characters of the name each.
+//! exFAT only stores the read only, hidden, system, directory and archive attributes, so
+//! [`to_exfat`](crate::image::exfat::to_exfat) also tells which attributes of the Props would
+//! be lost when copying to it.
+//! ```
+//! use keypropdecode::image::exfat::*;
+//! use keypropdecode::props::constants::*;
+//! use keypropdecode::props;
+//!
+//! assert_eq!(from_exfat(0x0021), props!(file: read_only));
+//! assert_eq!(to_exfat(props!(file: hidden, compressed)), (0x0022, COMPRESSED));
+//! ```
+//! ```no_run
+//! use keypropdecode::image::exfat::ExfatImage;
+//!
+//! let card = std::fs::File::open("/dev/sdb1").unwrap();
+//! let mut image = ExfatImage::open(card).unwrap();
+//! for entry in image.walk().unwrap() {
+//!     println!("{} {}", entry.props, entry.path);
+//! }
+//! ```
+use std::collections::HashSet;
+use std::io::{Read, Seek};
+
+use super::{components, invalid, read_at};
+use crate::error::*;
+use crate::props::constants::*;
+use crate::Props;
+
+/// The attributes the FileAttributes field can hold
+pub const EXFAT_ATTRIBUTES: u32 = READ_ONLY | HIDDEN | SYSTEM | DIRECTORY | ARCHIVE;
+
+const WHAT: &str = "exFAT image";
+const ENTRY_SIZE: usize = 32;
+const VOLUME_LABEL: u8 = 0x83;
+const FILE: u8 = 0x85;
+const STREAM_EXTENSION: u8 = 0xC0;
+const FILE_NAME: u8 = 0xC1;
+/// The characters of the name every File Name entry holds
+const NAME_CHARACTERS: usize = 15;
+
+/// Gets the Props of the FileAttributes field of a File entry, which has the bits of the FAT
+/// attribute byte
+pub fn from_exfat(attributes: u16) -> Props {
+    crate::backends::fat::from_fat((attributes as u32 & EXFAT_ATTRIBUTES) as u8)
+}
+/// Gets the FileAttributes field of `props`, with the attributes exFAT can't hold.
+/// The normal attribute isn't one of them, since on exFAT it's the same as having none.
+pub fn to_exfat(props: Props) -> (u16, u32) {
+    let attributes: u32 = props.into();
+    (
+        (attributes & EXFAT_ATTRIBUTES) as u16,
+        attributes & !(EXFAT_ATTRIBUTES | NORMAL),
+    )
+}
+
+/// A file or a directory of an exFAT directory
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ExfatEntry {
+    /// The path from the root, with `/` between the names
+    pub path: String,
+    /// The name, from the File Name entries
+    pub name: String,
+    /// The FileAttributes field as it's stored
+    pub attributes: u16,
+    /// The Props of the FileAttributes field
+    pub props: Props,
+    /// The first cluster of the data, 0 when there's none
+    pub first_cluster: u32,
+    /// The size of the data
+    pub data_length: u64,
+    /// True when the data is in consecutive clusters that aren't in the FAT
+    pub contiguous: bool,
+    /// Where the File entry of the set is in the image
+    pub offset: u64,
+}
+
+/// Where the entries of a directory are
+#[derive(Debug, Clone, Copy)]
+struct Directory {
+    first_cluster: u32,
+    /// The length, for contiguous directories, which aren't in the FAT
+    contiguous: Option<u64>,
+}
+
+/// An exFAT image, read from `file`
+pub struct ExfatImage<F> {
+    file: F,
+    fat_offset: u64,
+    heap_offset: u64,
+    cluster_size: u64,
+    clusters: u32,
+    root: Directory,
+}
+impl<F: Read + Seek> ExfatImage<F> {
+    /// Reads the boot sector of the image at the start of `file`
+    pub fn open(mut file: F) -> Result<Self> {
+        let mut boot = [0; 512];
+        read_at(&mut file, 0, &mut boot, WHAT)?;
+        if &boot[3..11] != b"EXFAT   " || boot[510..512] != [0x55, 0xAA] {
+            return Err(invalid("The image doesn't have an exFAT boot sector"));
+        }
+        let field = |offset: usize| {
+            u32::from_le_bytes([
+                boot[offset],
+                boot[offset + 1],
+                boot[offset + 2],
+                boot[offset + 3],
+            ])
+        };
+        let bytes_per_sector_shift = boot[108];
+        let sectors_per_cluster_shift = boot[109];
+        if !(9..=12).contains(&bytes_per_sector_shift)
+            || bytes_per_sector_shift + sectors_per_cluster_shift > 25
+        {
+            return Err(invalid("The boot sector of the image is invalid"));
+        }
+        let bytes_per_sector = 1u64 << bytes_per_sector_shift;
+        Ok(ExfatImage {
+            file,
+            fat_offset: field(80) as u64 * bytes_per_sector,
+            heap_offset: field(88) as u64 * bytes_per_sector,
+            cluster_size: bytes_per_sector << sectors_per_cluster_shift,
+            clusters: field(92),
+            root: Directory {
+                first_cluster: field(96),
+                contiguous: None,
+            },
+        })
+    }
+    /// Gives back the file the image is read from
+    pub fn into_inner(self) -> F {
+        self.file
+    }
+    /// Gets the entries of the directory at `path`, the root being `""` or `"/"`.
+    /// The deleted entry sets are skipped, and so are the corrupt ones, like the ones with a
+    /// wrong checksum or without a Stream Extension.
+    pub fn read_dir(&mut self, path: &str) -> Result<Vec<ExfatEntry>> {
+        if components(path).next().is_none() {
+            return Ok(self.read_directory(self.root, "")?.0);
+        }
+        let entry = self.entry(path)?;
+        let directory = directory_of(&entry)?;
+        Ok(self.read_directory(directory, &entry.path)?.0)
+    }
+    /// Gets every entry of the image, each directory followed by what's inside it
+    pub fn walk(&mut self) -> Result<Vec<ExfatEntry>> {
+        let mut walked = Vec::new();
+        self.walk_directory(self.root, "", &mut HashSet::new(), &mut walked)?;
+        Ok(walked)
+    }
+    /// Gets the entry at `path`, comparing the names without caring about the case
+    pub fn entry(&mut self, path: &str) -> Result<ExfatEntry> {
+        let mut directory = self.root;
+        let mut parent = String::new();
+        let mut found: Option<ExfatEntry> = None;
+        for component in components(path) {
+            if let Some(entry) = &found {
+                directory = directory_of(entry)?;
+            }
+            let component = component.to_lowercase();
+            let entry = self
+                .read_directory(directory, &parent)?
+                .0
+                .into_iter()
+                .find(|entry| entry.name.to_lowercase() == component)
+                .ok_or(Error {
+                    kind: ErrorKind::FileNotFound,
+                })?;
+            parent = entry.path.clone();
+            found = Some(entry);
+        }
+        found.ok_or_else(|| Error {
+            kind: ErrorKind::Other("The root directory has no entry".to_string()),
+        })
+    }
+    /// Gets the label of the volume from the root directory, if it has one
+    pub fn volume_label(&mut self) -> Result<Option<String>> {
+        Ok(self.read_directory(self.root, "")?.1)
+    }
+    fn walk_directory(
+        &mut self,
+        directory: Directory,
+        path: &str,
+        visited: &mut HashSet<u32>,
+        walked: &mut Vec<ExfatEntry>,
+    ) -> Result<()> {
+        // A corrupt image could have a directory inside itself
+        if !visited.insert(directory.first_cluster) {
+            return Err(invalid("A directory of the image contains itself"));
+        }
+        for entry in self.read_directory(directory, path)?.0 {
+            let children = match entry.props.is_directory() {
+                true => Some((directory_of(&entry)?, entry.path.clone())),
+                false => None,
+            };
+            walked.push(entry);
+            if let Some((child, path)) = children {
+                self.walk_directory(child, &path, visited, walked)?;
+            }
+        }
+        Ok(())
+    }
+    /// Gets the entry sets of a directory and, in the root, the volume label
+    fn read_directory(
+        &mut self,
+        directory: Directory,
+        path: &str,
+    ) -> Result<(Vec<ExfatEntry>, Option<String>)> {
+        let clusters = match directory.contiguous {
+            Some(length) => {
+                // The length comes from the entry, so it's checked before allocating
+                let first = directory.first_cluster;
+                let count = u32::try_from(length.div_ceil(self.cluster_size).max(1))
+                    .ok()
+                    .filter(|count| {
+                        first >= 2
+                            && (first - 2)
+                                .checked_add(*count)
+                                .is_some_and(|end| end <= self.clusters)
+                    })
+                    .ok_or_else(|| {
+                        invalid(&format!(
+                            "The directory {} of {} bytes is outside of the cluster heap",
+                            path, length
+                        ))
+                    })?;
+                (first..first + count).collect()
+            }
+            None => self.chain(directory.first_cluster)?,
+        };
+        let mut bytes = vec![0; clusters.len() * self.cluster_size as usize];
+        let mut offsets = Vec::new();
+        for (index, cluster) in clusters.into_iter().enumerate() {
+            let offset = self.cluster_offset(cluster)?;
+            let start = index * self.cluster_size as usize;
+            let end = start + self.cluster_size as usize;
+            read_at(&mut self.file, offset, &mut bytes[start..end], WHAT)?;
+            offsets.push(offset);
+        }
+        let cluster_size = self.cluster_size as usize;
+        let offset_of = |at: usize| offsets[at / cluster_size] + (at % cluster_size) as u64;
+
+        let mut entries = Vec::new();
+        let mut label = None;
+        let mut at = 0;
+        while at < bytes.len() {
+            let entry_type = bytes[at];
+            match entry_type {
+                0 => break,
+                VOLUME_LABEL => {
+                    let count = (bytes[at + 1] as usize).min(11);
+                    label = Some(utf16(&bytes[at + 2..at + 2 + count * 2]));
+                }
+                FILE => {
+                    let count = bytes[at + 1] as usize;
+                    let end = at + (count + 1) * ENTRY_SIZE;
+                    // A corrupt set is passed over one entry at a time, since its count may be
+                    // wrong too, and the entries of the next sets are still found
+                    if let Some(Ok(mut entry)) = bytes.get(at..end).map(|set| entry_set(set, path))
+                    {
+                        entry.offset = offset_of(at);
+                        entries.push(entry);
+                        at = end;
+                        continue;
+                    }
+                }
+                // Deleted sets, the allocation bitmap, the up-case table and the rest
+                _ => (),
+            }
+            at += ENTRY_SIZE;
+        }
+        Ok((entries, label))
+    }
+    /// Gets the clusters of the chain in the FAT that starts at `first`
+    fn chain(&mut self, first: u32) -> Result<Vec<u32>> {
+        let mut chain = vec![first];
+        let mut cluster = first;
+        loop {
+            self.cluster_offset(cluster)?;
+            let mut next = [0; 4];
+            read_at(
+                &mut self.file,
+                self.fat_offset + cluster as u64 * 4,
+                &mut next,
+                WHAT,
+            )?;
+            let next = u32::from_le_bytes(next);
+            if next == 0xFFFF_FFFF {
+                return Ok(chain);
+            }
+            if chain.len() > self.clusters as usize {
+                return Err(invalid("A cluster chain of the image loops"));
+            }
+            chain.push(next);
+            cluster = next;
+        }
+    }
+    fn cluster_offset(&self, cluster: u32) -> Result<u64> {
+        if cluster < 2 || cluster - 2 >= self.clusters {
+            return Err(invalid(&format!(
+                "The cluster {} is outside of the image",
+                cluster
+            )));
+        }
+        Ok(self.heap_offset + (cluster as u64 - 2) * self.cluster_size)
+    }
+}
+
+/// Parses the entries of a set, the File entry first, checking its checksum
+fn entry_set(set: &[u8], parent: &str) -> Result<ExfatEntry> {
+    let stored = u16::from_le_bytes([set[2], set[3]]);
+    if checksum(set) != stored {
+        return Err(invalid(
+            "The checksum of an entry set of the image is wrong",
+        ));
+    }
+    let stream = set
+        .get(ENTRY_SIZE..2 * ENTRY_SIZE)
+        .filter(|stream| stream[0] == STREAM_EXTENSION)
+        .ok_or_else(|| invalid("An entry set of the image has no Stream Extension"))?;
+    let name_length = stream[3] as usize;
+    let mut name = Vec::new();
+    for entry in set[2 * ENTRY_SIZE..].chunks_exact(ENTRY_SIZE) {
+        if entry[0] != FILE_NAME || name.len() >= name_length * 2 {
+            break;
+        }
+        name.extend_from_slice(&entry[2..2 + NAME_CHARACTERS * 2]);
+    }
+    name.truncate(name_length * 2);
+    if name.len() != name_length * 2 || name_length == 0 {
+        return Err(invalid("An entry set of the image has a truncated name"));
+    }
+    let name = utf16(&name);
+    let attributes = u16::from_le_bytes([set[4], set[5]]);
+    let u64_at = |offset: usize| {
+        let mut value = [0; 8];
+        value.copy_from_slice(&stream[offset..offset + 8]);
+        u64::from_le_bytes(value)
+    };
+    Ok(ExfatEntry {
+        path: match parent.is_empty() {
+            true => name.clone(),
+            false => format!("{}/{}", parent, name),
+        },
+        name,
+        attributes,
+        props: from_exfat(attributes),
+        first_cluster: u32::from_le_bytes([stream[20], stream[21], stream[22], stream[23]]),
+        data_length: u64_at(24),
+        contiguous: stream[1] & 0x02 != 0,
+        offset: 0,
+    })
+}
+/// The SetChecksum of an entry set, which skips its own field
+fn checksum(set: &[u8]) -> u16 {
+    set.iter()
+        .enumerate()
+        .filter(|(index, _)| !matches!(index, 2 | 3))
+        .fold(0u16, |sum, (_, byte)| {
+            sum.rotate_right(1).wrapping_add(*byte as u16)
+        })
+}
+fn directory_of(entry: &ExfatEntry) -> Result<Directory> {
+    if !entry.props.is_directory() {
+        return Err(Error {
+            kind: ErrorKind::Other(format!("{} isn't a directory", entry.path)),
+        });
+    }
+    Ok(Directory {
+        first_cluster: entry.first_cluster,
+        contiguous: entry.contiguous.then_some(entry.data_length),
+    })
+}
+fn utf16(bytes: &[u8]) -> String {
+    let characters: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&characters)
+}
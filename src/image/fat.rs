@@ -0,0 +1,560 @@
+//! Reads the directories of FAT12, FAT16 and FAT32 images, giving the long name and the Props
+//! of every entry, and rewrites the attribute byte of an entry in place, like `mattrib` from
+//! mtools does. The long file name slots are joined to the entry they belong to, and volume
+//! labels are given as entries of their own, since the attribute byte of a label has the volume
+//! bit that Props can't represent.
+//! ```no_run
+//! use keypropdecode::image::fat::FatImage;
+//!
+//! let file = std::fs::OpenOptions::new().read(true).write(true).open("floppy.img").unwrap();
+//! let mut image = FatImage::open(file).unwrap();
+//! for entry in image.walk().unwrap() {
+//!     println!("{} {}", entry.props, entry.path);
+//! }
+//! let mut entry = image.entry("autoexec.bat").unwrap();
+//! let attribute_byte = keypropdecode::image::fat::apply_mattrib(entry.attribute_byte, "+h -a").unwrap();
+//! image.set_attribute_byte(&mut entry, attribute_byte).unwrap();
+//! ```
+use std::collections::HashSet;
+use std::io::{Read, Seek, Write};
+
+use super::{components, invalid, read_at, write_at};
+use crate::backends::fat::{from_fat, to_fat, ATTR_VOLUME};
+use crate::error::*;
+use crate::Props;
+
+const WHAT: &str = "FAT image";
+const ENTRY_SIZE: usize = 32;
+/// The attribute byte of the long file name slots
+const ATTR_LONG_NAME: u8 = 0x0F;
+const ATTR_DIRECTORY: u8 = 0x10;
+/// The first byte of a deleted entry
+const DELETED: u8 = 0xE5;
+
+/// The FAT variants, decided by the number of clusters like Microsoft's specification says
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FatKind {
+    /// Fewer than 4085 clusters
+    Fat12,
+    /// Fewer than 65525 clusters
+    Fat16,
+    /// 65525 clusters or more
+    Fat32,
+}
+
+/// An entry of a FAT directory
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FatEntry {
+    /// The path from the root, with `/` between the names.
+    /// For volume labels it's the label.
+    pub path: String,
+    /// The long name, or the short one when the entry has no valid long name slots
+    pub name: String,
+    /// The 8.3 name, in upper case and with a dot before the extension if there's one.
+    /// The bytes above 0x7F are read as Latin-1, since the code page isn't stored.
+    pub short_name: String,
+    /// The attribute byte as it's stored, with the bits Props can't represent
+    pub attribute_byte: u8,
+    /// The Props of the attribute byte
+    pub props: Props,
+    /// The first cluster of the data
+    pub cluster: u32,
+    /// The size of the file, 0 for directories
+    pub size: u32,
+    /// Where the 32 bytes of the entry are in the image
+    pub offset: u64,
+}
+impl FatEntry {
+    /// Returns true if the entry is the label of the volume instead of a file or a directory
+    pub fn is_volume_label(&self) -> bool {
+        self.attribute_byte & ATTR_VOLUME != 0
+    }
+}
+
+/// Where a directory is
+#[derive(Debug, Clone, Copy)]
+enum Directory {
+    /// The fixed root directory of FAT12 and FAT16
+    FixedRoot {
+        offset: u64,
+        entries: u64,
+    },
+    Cluster(u32),
+}
+
+/// A FAT image, read from `file`
+pub struct FatImage<F> {
+    file: F,
+    kind: FatKind,
+    cluster_size: u64,
+    fat_offset: u64,
+    data_offset: u64,
+    clusters: u32,
+    root: Directory,
+}
+impl<F: Read + Seek> FatImage<F> {
+    /// Reads the boot sector of the image at the start of `file`
+    pub fn open(mut file: F) -> Result<Self> {
+        let mut boot = [0; 512];
+        read_at(&mut file, 0, &mut boot, WHAT)?;
+        if boot[510..512] != [0x55, 0xAA] {
+            return Err(invalid("The image doesn't have a FAT boot sector"));
+        }
+        let field = |offset: usize| u16::from_le_bytes([boot[offset], boot[offset + 1]]) as u64;
+        let field32 = |offset: usize| {
+            u32::from_le_bytes([
+                boot[offset],
+                boot[offset + 1],
+                boot[offset + 2],
+                boot[offset + 3],
+            ]) as u64
+        };
+        let bytes_per_sector = field(11);
+        let sectors_per_cluster = boot[13] as u64;
+        let reserved = field(14);
+        let fats = boot[16] as u64;
+        let root_entries = field(17);
+        let total_sectors = match field(19) {
+            0 => field32(32),
+            total => total,
+        };
+        let fat_size = match field(22) {
+            0 => field32(36),
+            fat_size => fat_size,
+        };
+        if !matches!(bytes_per_sector, 512 | 1024 | 2048 | 4096)
+            || !sectors_per_cluster.is_power_of_two()
+            || reserved == 0
+            || fats == 0
+            || fat_size == 0
+        {
+            return Err(invalid("The BIOS parameter block of the image is invalid"));
+        }
+        let root_sectors = (root_entries * ENTRY_SIZE as u64).div_ceil(bytes_per_sector);
+        let root_sector = reserved + fats * fat_size;
+        let data_sector = root_sector + root_sectors;
+        let clusters = total_sectors
+            .checked_sub(data_sector)
+            .ok_or_else(|| invalid("The image is smaller than its own metadata"))?
+            / sectors_per_cluster;
+        let kind = match clusters {
+            0..4085 => FatKind::Fat12,
+            4085..65525 => FatKind::Fat16,
+            _ => FatKind::Fat32,
+        };
+        let root = match kind {
+            FatKind::Fat32 => Directory::Cluster(field32(44) as u32),
+            _ => Directory::FixedRoot {
+                offset: root_sector * bytes_per_sector,
+                entries: root_entries,
+            },
+        };
+        Ok(FatImage {
+            file,
+            kind,
+            cluster_size: sectors_per_cluster * bytes_per_sector,
+            fat_offset: reserved * bytes_per_sector,
+            data_offset: data_sector * bytes_per_sector,
+            clusters: clusters.min(0x0FFF_FFF5) as u32,
+            root,
+        })
+    }
+    /// The FAT variant of the image
+    pub fn kind(&self) -> FatKind {
+        self.kind
+    }
+    /// Gives back the file the image is read from
+    pub fn into_inner(self) -> F {
+        self.file
+    }
+    /// Gets the entries of the directory at `path`, the root being `""` or `"/"`.
+    /// The `.` and `..` entries and the deleted ones are skipped.
+    pub fn read_dir(&mut self, path: &str) -> Result<Vec<FatEntry>> {
+        if components(path).next().is_none() {
+            return self.read_directory(self.root, "");
+        }
+        let entry = self.entry(path)?;
+        let directory = self.directory_of(&entry)?;
+        self.read_directory(directory, &entry.path)
+    }
+    /// Gets every entry of the image, each directory followed by what's inside it
+    pub fn walk(&mut self) -> Result<Vec<FatEntry>> {
+        let mut walked = Vec::new();
+        self.walk_directory(self.root, "", &mut HashSet::new(), &mut walked)?;
+        Ok(walked)
+    }
+    /// Gets the entry at `path`, comparing the names without caring about the case
+    pub fn entry(&mut self, path: &str) -> Result<FatEntry> {
+        let mut directory = self.root;
+        let mut parent = String::new();
+        let mut found = None;
+        for component in components(path) {
+            if let Some(entry) = &found {
+                directory = self.directory_of(entry)?;
+            }
+            let entries = self.read_directory(directory, &parent)?;
+            let entry = entries
+                .into_iter()
+                .find(|entry| !entry.is_volume_label() && matches(entry, component))
+                .ok_or(Error {
+                    kind: ErrorKind::FileNotFound,
+                })?;
+            parent = entry.path.clone();
+            found = Some(entry);
+        }
+        found.ok_or_else(|| Error {
+            kind: ErrorKind::Other("The root directory has no entry".to_string()),
+        })
+    }
+    /// Gets the label of the volume from the root directory, if it has one
+    pub fn volume_label(&mut self) -> Result<Option<String>> {
+        Ok(self
+            .read_directory(self.root, "")?
+            .into_iter()
+            .find(|entry| entry.is_volume_label())
+            .map(|entry| entry.name))
+    }
+    fn walk_directory(
+        &mut self,
+        directory: Directory,
+        path: &str,
+        visited: &mut HashSet<u32>,
+        walked: &mut Vec<FatEntry>,
+    ) -> Result<()> {
+        if let Directory::Cluster(cluster) = directory {
+            // A corrupt image could have a directory inside itself
+            if !visited.insert(cluster) {
+                return Err(invalid("A directory of the image contains itself"));
+            }
+        }
+        for entry in self.read_directory(directory, path)? {
+            let children = match entry.props.is_directory() && !entry.is_volume_label() {
+                true => Some((self.directory_of(&entry)?, entry.path.clone())),
+                false => None,
+            };
+            walked.push(entry);
+            if let Some((child, path)) = children {
+                self.walk_directory(child, &path, visited, walked)?;
+            }
+        }
+        Ok(())
+    }
+    fn directory_of(&self, entry: &FatEntry) -> Result<Directory> {
+        if !entry.props.is_directory() {
+            return Err(Error {
+                kind: ErrorKind::Other(format!("{} isn't a directory", entry.path)),
+            });
+        }
+        // Only the `..` entries, which are skipped, point to the root with cluster 0
+        match entry.cluster {
+            0 => Err(invalid(&format!(
+                "The directory {} has no cluster",
+                entry.path
+            ))),
+            cluster => Ok(Directory::Cluster(cluster)),
+        }
+    }
+    fn read_directory(&mut self, directory: Directory, path: &str) -> Result<Vec<FatEntry>> {
+        let mut parser = DirectoryParser::new(path, self.kind == FatKind::Fat32);
+        match directory {
+            Directory::FixedRoot { offset, entries } => {
+                let mut bytes = vec![0; entries as usize * ENTRY_SIZE];
+                read_at(&mut self.file, offset, &mut bytes, WHAT)?;
+                parser.parse(offset, &bytes);
+            }
+            Directory::Cluster(first) => {
+                let mut bytes = vec![0; self.cluster_size as usize];
+                for cluster in self.chain(first)? {
+                    let offset = self.cluster_offset(cluster)?;
+                    read_at(&mut self.file, offset, &mut bytes, WHAT)?;
+                    if !parser.parse(offset, &bytes) {
+                        break;
+                    }
+                }
+            }
+        }
+        Ok(parser.entries)
+    }
+    /// Gets the clusters of the chain that starts at `first`
+    fn chain(&mut self, first: u32) -> Result<Vec<u32>> {
+        let end = match self.kind {
+            FatKind::Fat12 => 0xFF8,
+            FatKind::Fat16 => 0xFFF8,
+            FatKind::Fat32 => 0x0FFF_FFF8,
+        };
+        let mut chain = vec![first];
+        let mut cluster = first;
+        loop {
+            self.cluster_offset(cluster)?;
+            let next = self.next_cluster(cluster)?;
+            if next >= end {
+                return Ok(chain);
+            }
+            if chain.len() > self.clusters as usize {
+                return Err(invalid("A cluster chain of the image loops"));
+            }
+            chain.push(next);
+            cluster = next;
+        }
+    }
+    fn next_cluster(&mut self, cluster: u32) -> Result<u32> {
+        let mut bytes = [0; 4];
+        let (offset, length) = match self.kind {
+            FatKind::Fat12 => (cluster as u64 + cluster as u64 / 2, 2),
+            FatKind::Fat16 => (cluster as u64 * 2, 2),
+            FatKind::Fat32 => (cluster as u64 * 4, 4),
+        };
+        read_at(
+            &mut self.file,
+            self.fat_offset + offset,
+            &mut bytes[..length],
+            WHAT,
+        )?;
+        let value = u32::from_le_bytes(bytes);
+        Ok(match self.kind {
+            FatKind::Fat12 if cluster % 2 == 1 => value >> 4,
+            FatKind::Fat12 => value & 0xFFF,
+            FatKind::Fat16 => value,
+            FatKind::Fat32 => value & 0x0FFF_FFFF,
+        })
+    }
+    fn cluster_offset(&self, cluster: u32) -> Result<u64> {
+        if cluster < 2 || cluster > self.clusters + 1 {
+            return Err(invalid(&format!(
+                "The cluster {} is outside of the image",
+                cluster
+            )));
+        }
+        Ok(self.data_offset + (cluster as u64 - 2) * self.cluster_size)
+    }
+}
+impl<F: Read + Write + Seek> FatImage<F> {
+    /// Writes `attribute_byte` to the directory entry of `entry` and updates it.
+    /// Like `mattrib`, it refuses to turn a file into a directory or the other way around, and
+    /// to add or remove the volume bit. The entry is read again first, so an entry that changed
+    /// since it was read gives an `InvalidData` error instead of overwriting another one.
+    pub fn set_attribute_byte(&mut self, entry: &mut FatEntry, attribute_byte: u8) -> Result<()> {
+        let changed = attribute_byte ^ entry.attribute_byte;
+        if changed & (ATTR_DIRECTORY | ATTR_VOLUME) != 0 || attribute_byte & 0x3F == ATTR_LONG_NAME
+        {
+            return Err(Error {
+                kind: ErrorKind::NotSupported(format!(
+                    "The attribute byte {:#04x} changes what {} is",
+                    attribute_byte, entry.path
+                )),
+            });
+        }
+        let mut stored = [0; ENTRY_SIZE];
+        read_at(&mut self.file, entry.offset, &mut stored, WHAT)?;
+        if stored[11] != entry.attribute_byte || matches!(stored[0], 0 | DELETED) {
+            return Err(invalid(&format!(
+                "The directory entry of {} changed since it was read",
+                entry.path
+            )));
+        }
+        write_at(&mut self.file, entry.offset + 11, &[attribute_byte])?;
+        entry.attribute_byte = attribute_byte;
+        entry.props = from_fat(attribute_byte);
+        Ok(())
+    }
+    /// Sets the attributes of the entry at `path` to `props`, keeping the bits Props doesn't
+    /// know about, and returns the updated entry.
+    /// Since every file has the archive attribute in Props, this can't clear it, but
+    /// [`set_attribute_byte`](FatImage::set_attribute_byte) can.
+    pub fn set_props(&mut self, path: &str, props: Props) -> Result<FatEntry> {
+        let mut entry = self.entry(path)?;
+        let attribute_byte = entry.attribute_byte & 0xC0 | to_fat(props)?;
+        self.set_attribute_byte(&mut entry, attribute_byte)?;
+        Ok(entry)
+    }
+}
+
+/// Applies changes written like the arguments of `mattrib`, such as `+h -a` or `+rs`, to an
+/// attribute byte. The letters are `a`, `h`, `r` and `s`.
+pub fn apply_mattrib(attribute_byte: u8, changes: &str) -> Result<u8> {
+    let mut attribute_byte = attribute_byte;
+    for change in changes.split_whitespace() {
+        let invalid = || Error {
+            kind: ErrorKind::InvalidData(format!("\"{}\" isn't a mattrib change", change)),
+        };
+        let (set, letters) = match change.split_at_checked(1) {
+            Some(("+", letters)) => (true, letters),
+            Some(("-", letters)) => (false, letters),
+            _ => return Err(invalid()),
+        };
+        if letters.is_empty() {
+            return Err(invalid());
+        }
+        for letter in letters.chars() {
+            let bit = match letter {
+                'r' => 0x01,
+                'h' => 0x02,
+                's' => 0x04,
+                'a' => 0x20,
+                _ => return Err(invalid()),
+            };
+            match set {
+                true => attribute_byte |= bit,
+                false => attribute_byte &= !bit,
+            }
+        }
+    }
+    Ok(attribute_byte)
+}
+
+/// Turns the 32 byte entries of a directory into FatEntry, joining the long name slots
+struct DirectoryParser<'a> {
+    parent: &'a str,
+    /// Only FAT32 uses the high word of the cluster, FAT12 and FAT16 may have other data there
+    fat32: bool,
+    entries: Vec<FatEntry>,
+    /// The characters of every slot of the long name being read, by their order
+    long_name: Vec<Option<[u16; 13]>>,
+    checksum: u8,
+    ended: bool,
+}
+impl<'a> DirectoryParser<'a> {
+    fn new(parent: &'a str, fat32: bool) -> Self {
+        DirectoryParser {
+            parent,
+            fat32,
+            entries: Vec::new(),
+            long_name: Vec::new(),
+            checksum: 0,
+            ended: false,
+        }
+    }
+    /// Parses the entries of `bytes`, which are at `offset` in the image.
+    /// Returns false once the end of the directory is found.
+    fn parse(&mut self, offset: u64, bytes: &[u8]) -> bool {
+        for (index, raw) in bytes.chunks_exact(ENTRY_SIZE).enumerate() {
+            if self.ended || raw[0] == 0 {
+                self.ended = true;
+                return false;
+            }
+            if raw[0] == DELETED {
+                self.long_name.clear();
+                continue;
+            }
+            if raw[11] & 0x3F == ATTR_LONG_NAME {
+                self.long_name_slot(raw);
+                continue;
+            }
+            let long_name = self.take_long_name(checksum(&raw[..11]));
+            let entry_offset = offset + (index * ENTRY_SIZE) as u64;
+            if let Some(entry) = self.entry(raw, long_name, entry_offset) {
+                self.entries.push(entry);
+            }
+        }
+        true
+    }
+    fn long_name_slot(&mut self, raw: &[u8]) {
+        let order = (raw[0] & 0x1F) as usize;
+        if raw[0] & 0x40 != 0 {
+            self.long_name = vec![None; order];
+            self.checksum = raw[13];
+        }
+        if order == 0 || order > self.long_name.len() || raw[13] != self.checksum {
+            self.long_name.clear();
+            return;
+        }
+        let mut characters = [0; 13];
+        let ranges = [(1, 0, 5), (14, 5, 6), (28, 11, 2)];
+        for (start, first, count) in ranges {
+            for character in 0..count {
+                let at = start + character * 2;
+                characters[first + character] = u16::from_le_bytes([raw[at], raw[at + 1]]);
+            }
+        }
+        self.long_name[order - 1] = Some(characters);
+    }
+    /// Gets the long name read so far if it belongs to the short name with `checksum`
+    fn take_long_name(&mut self, checksum: u8) -> Option<String> {
+        let slots = std::mem::take(&mut self.long_name);
+        if slots.is_empty() || checksum != self.checksum {
+            return None;
+        }
+        let characters: Vec<u16> = slots
+            .into_iter()
+            .collect::<Option<Vec<[u16; 13]>>>()?
+            .concat()
+            .into_iter()
+            .take_while(|character| *character != 0)
+            .collect();
+        Some(String::from_utf16_lossy(&characters))
+    }
+    fn entry(&self, raw: &[u8], long_name: Option<String>, offset: u64) -> Option<FatEntry> {
+        let attribute_byte = raw[11];
+        let mut base: Vec<u8> = raw[..8].to_vec();
+        // 0xE5 starts real names too, stored as 0x05 so it isn't taken as deleted
+        if base[0] == 0x05 {
+            base[0] = DELETED;
+        }
+        let text = |bytes: &[u8]| -> String {
+            bytes
+                .iter()
+                .map(|byte| *byte as char)
+                .collect::<String>()
+                .trim_end()
+                .to_string()
+        };
+        if attribute_byte & ATTR_VOLUME != 0 {
+            let label = text(&raw[..11]);
+            return Some(FatEntry {
+                path: label.clone(),
+                name: label.clone(),
+                short_name: label,
+                attribute_byte,
+                props: from_fat(attribute_byte),
+                cluster: 0,
+                size: 0,
+                offset,
+            });
+        }
+        let (base, extension) = (text(&base), text(&raw[8..11]));
+        if base == "." || base == ".." {
+            return None;
+        }
+        let join = |base: String, extension: String| match extension.is_empty() {
+            true => base,
+            false => format!("{}.{}", base, extension),
+        };
+        let short_name = join(base.clone(), extension.clone());
+        // Windows NT keeps names that are all in lower case as 8.3 names with these flags
+        let name = long_name.unwrap_or_else(|| {
+            let lower = |text: String, flag: u8| match raw[12] & flag {
+                0 => text,
+                _ => text.to_lowercase(),
+            };
+            join(lower(base, 0x08), lower(extension, 0x10))
+        });
+        let cluster_high = match self.fat32 {
+            true => u16::from_le_bytes([raw[20], raw[21]]) as u32,
+            false => 0,
+        };
+        let cluster_low = u16::from_le_bytes([raw[26], raw[27]]) as u32;
+        Some(FatEntry {
+            path: match self.parent.is_empty() {
+                true => name.clone(),
+                false => format!("{}/{}", self.parent, name),
+            },
+            name,
+            short_name,
+            attribute_byte,
+            props: from_fat(attribute_byte),
+            cluster: cluster_high << 16 | cluster_low,
+            size: u32::from_le_bytes([raw[28], raw[29], raw[30], raw[31]]),
+            offset,
+        })
+    }
+}
+/// The checksum of a short name that its long name slots store
+fn checksum(short_name: &[u8]) -> u8 {
+    short_name
+        .iter()
+        .fold(0u8, |sum, byte| sum.rotate_right(1).wrapping_add(*byte))
+}
+fn matches(entry: &FatEntry, component: &str) -> bool {
+    let component = component.to_lowercase();
+    entry.name.to_lowercase() == component || entry.short_name.to_lowercase() == component
+}
@@ -0,0 +1,43 @@
+//! File system images keep the attributes of every element in their own on-disk structures.
+//! These modules read them, and in some cases change them in place, straight from an image
+//! file or a block device, without mounting anything.
+/// The directory entry sets of exFAT images
+pub mod exfat;
+/// The directory entries of FAT12, FAT16 and FAT32 images
+pub mod fat;
+/// The structures NTFS keeps the attributes in
+pub mod ntfs;
+#[cfg(test)]
+mod tests;
+
+use std::io::{Read, Seek, SeekFrom, Write};
+
+use crate::archive::{io_error, read_exact};
+use crate::error::*;
+
+/// Fills `buffer` with the bytes of `image` at `offset`
+pub(crate) fn read_at(
+    image: &mut (impl Read + Seek),
+    offset: u64,
+    buffer: &mut [u8],
+    what: &str,
+) -> Result<()> {
+    image.seek(SeekFrom::Start(offset)).map_err(io_error)?;
+    read_exact(image, buffer, what)
+}
+/// Writes `bytes` to `image` at `offset`
+pub(crate) fn write_at(image: &mut (impl Write + Seek), offset: u64, bytes: &[u8]) -> Result<()> {
+    image.seek(SeekFrom::Start(offset)).map_err(io_error)?;
+    image.write_all(bytes).map_err(io_error)?;
+    image.flush().map_err(io_error)
+}
+pub(crate) fn invalid(message: &str) -> Error {
+    Error {
+        kind: ErrorKind::InvalidData(message.to_string()),
+    }
+}
+/// The names of a path inside an image, which can be separated by `/` or `\`
+pub(crate) fn components(path: &str) -> impl Iterator<Item = &str> {
+    path.split(['/', '\\'])
+        .filter(|component| !component.is_empty())
+}
@@ -0,0 +1,242 @@
+//! Parses the index buffers of the `$I30` indexes of directories, the `INDX` blocks of their
+//! `$INDEX_ALLOCATION` attribute. Every entry of a directory keeps a copy of the `$FILE_NAME`
+//! of the element, with its attribute word, so the buffers tell the attributes of elements
+//! even when their records are gone.
+//!
+//! When an entry is removed the ones after it are moved back over it, leaving the end of the
+//! last ones after the used part of the buffer. Those are given as slack entries, found by
+//! looking for what looks like a `$FILE_NAME`, so the ones whose header was overwritten are
+//! found too. The first slack entries are usually copies of the last live ones.
+//! ```no_run
+//! use keypropdecode::image::ntfs::index::IndexBuffer;
+//!
+//! let allocation = std::fs::read("$I30").unwrap();
+//! for buffer in allocation.chunks(4096).filter(|buffer| buffer.starts_with(b"INDX")) {
+//!     for entry in IndexBuffer::parse(buffer).unwrap().entries {
+//!         if entry.slack {
+//!             println!("{} {}", entry.props(), entry.file_name.name);
+//!         }
+//!     }
+//! }
+//! ```
+use super::mft::FileName;
+use super::{apply_fixups, FileReference};
+use crate::error::*;
+use crate::image::invalid;
+use crate::reader::Reader;
+use crate::Props;
+
+/// The flag of the entries that point to a buffer with the entries before them
+pub const INDEX_ENTRY_NODE: u32 = 0x01;
+/// The flag of the entry that ends the list, which has no key
+pub const INDEX_ENTRY_END: u32 = 0x02;
+/// The flag of the index nodes with buffers below them
+pub const INDEX_NODE_HAS_CHILDREN: u32 = 0x01;
+
+const WHAT: &str = "index buffer";
+/// Where the header of the list of entries starts, the offsets in it are relative to it
+const NODE_HEADER: usize = 24;
+/// The size of a `$FILE_NAME` without its name
+const FILE_NAME_SIZE: usize = 66;
+/// The FILETIME of 1980-01-01, before which no time of a slack entry is believed
+const OLDEST_TIME: u64 = 119_600_064_000_000_000;
+/// The FILETIME of 2100-01-01
+const NEWEST_TIME: u64 = 157_469_184_000_000_000;
+
+/// An entry of an `$I30` index
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct IndexEntry {
+    /// The record of the element. Slack entries whose header was overwritten don't have it.
+    pub reference: Option<FileReference>,
+    /// The copy of the `$FILE_NAME` of the element
+    pub file_name: FileName,
+    /// The buffer with the entries that go before this one, as a VCN of the allocation
+    pub child: Option<u64>,
+    /// True when the entry is after the used part of the buffer, which happens to the entries of
+    /// deleted elements and to the leftovers of the ones that were moved
+    pub slack: bool,
+    /// Where the entry starts in the buffer, or where its `$FILE_NAME` does for the slack entries
+    /// without a header
+    pub offset: usize,
+}
+impl IndexEntry {
+    /// Gets the Props of the attribute word of the copy of the `$FILE_NAME`
+    pub fn props(&self) -> Props {
+        self.file_name.props()
+    }
+}
+
+/// An `INDX` buffer of an `$INDEX_ALLOCATION`
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct IndexBuffer {
+    /// The log sequence number of the last change
+    pub lsn: u64,
+    /// Where the buffer is in the allocation, in clusters or in blocks of 512 bytes if the
+    /// buffers are smaller than a cluster
+    pub vcn: u64,
+    /// The flags of the node, like [`INDEX_NODE_HAS_CHILDREN`]
+    pub flags: u32,
+    /// The live entries in order, followed by the slack ones
+    pub entries: Vec<IndexEntry>,
+}
+impl IndexBuffer {
+    /// Parses a whole buffer, applying its fixups.
+    /// Malformed live entries give an error, while the slack is only read where it's intact.
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        if bytes.get(..4) != Some(b"INDX") {
+            return Err(invalid("The index buffer doesn't start with INDX"));
+        }
+        let mut bytes = bytes.to_vec();
+        apply_fixups(&mut bytes, WHAT)?;
+        let mut header = Reader::new(&bytes, WHAT);
+        header.skip(8)?;
+        let lsn = header.u64()?;
+        let vcn = header.u64()?;
+        let first_entry = NODE_HEADER + header.u32()? as usize;
+        let used = NODE_HEADER + header.u32()? as usize;
+        let allocated = NODE_HEADER + header.u32()? as usize;
+        let flags = header.u32()?;
+        if first_entry < NODE_HEADER + 16 || first_entry > used || used > bytes.len() {
+            return Err(invalid("The index buffer has an invalid node header"));
+        }
+        let (mut entries, end) = live_entries(&bytes[..used], first_entry)?;
+        let slack_start = end.max(first_entry);
+        entries.extend(slack_entries(
+            &bytes[..allocated.min(bytes.len())],
+            slack_start,
+        ));
+        Ok(IndexBuffer {
+            lsn,
+            vcn,
+            flags,
+            entries,
+        })
+    }
+    /// Gets the entries in the used part of the buffer
+    pub fn live(&self) -> impl Iterator<Item = &IndexEntry> {
+        self.entries.iter().filter(|entry| !entry.slack)
+    }
+    /// Gets the entries found after the used part of the buffer
+    pub fn slack(&self) -> impl Iterator<Item = &IndexEntry> {
+        self.entries.iter().filter(|entry| entry.slack)
+    }
+}
+
+/// Reads the entries up to the end one, giving them and where the end one finishes
+fn live_entries(bytes: &[u8], mut offset: usize) -> Result<(Vec<IndexEntry>, usize)> {
+    let mut entries = Vec::new();
+    loop {
+        let mut header = Reader::new(&bytes[offset..], WHAT);
+        let reference = FileReference::from(header.u64()?);
+        let length = header.u16()? as usize;
+        let key_length = header.u16()? as usize;
+        let flags = header.u32()?;
+        let child_size = if flags & INDEX_ENTRY_NODE != 0 { 8 } else { 0 };
+        if length < 16 + child_size
+            || !length.is_multiple_of(8)
+            || offset + length > bytes.len()
+            || 16 + key_length + child_size > length
+        {
+            return Err(invalid(
+                "The index buffer has an entry with an invalid size",
+            ));
+        }
+        let entry = &bytes[offset..offset + length];
+        let child = match child_size {
+            0 => None,
+            _ => {
+                let mut vcn = [0; 8];
+                vcn.copy_from_slice(&entry[length - 8..]);
+                Some(u64::from_le_bytes(vcn))
+            }
+        };
+        if flags & INDEX_ENTRY_END != 0 {
+            // Its child, if it has one, has the entries after the last one
+            return Ok((entries, offset + length));
+        }
+        entries.push(IndexEntry {
+            reference: Some(reference),
+            file_name: FileName::parse(&entry[16..16 + key_length])?,
+            child,
+            slack: false,
+            offset,
+        });
+        offset += length;
+    }
+}
+
+/// Looks for the `$FILE_NAME` keys left in the slack, at every offset an entry could have
+fn slack_entries(bytes: &[u8], start: usize) -> Vec<IndexEntry> {
+    let mut entries = Vec::new();
+    // The keys start 16 bytes after their entry, which is aligned to 8 bytes
+    let mut key = (start + 16).next_multiple_of(8);
+    while key + FILE_NAME_SIZE <= bytes.len() {
+        let Some(file_name) = slack_file_name(&bytes[key..]) else {
+            key += 8;
+            continue;
+        };
+        let key_length = FILE_NAME_SIZE + file_name.name.encode_utf16().count() * 2;
+        // The header is only believed if it's in the slack and agrees with the key
+        let header = &bytes[key - 16..key];
+        let u16_at = |offset: usize| u16::from_le_bytes([header[offset], header[offset + 1]]);
+        let flags = u32::from_le_bytes([header[12], header[13], header[14], header[15]]);
+        let length = u16_at(8) as usize;
+        let intact = key - 16 >= start
+            && u16_at(10) as usize == key_length
+            && length >= 16 + key_length
+            && length.is_multiple_of(8)
+            && flags & !(INDEX_ENTRY_NODE | INDEX_ENTRY_END) == 0;
+        let child = match flags & INDEX_ENTRY_NODE != 0 {
+            true => bytes.get(key - 16 + length - 8..key - 16 + length),
+            false => None,
+        }
+        .filter(|_| intact)
+        .map(|vcn| {
+            let mut value = [0; 8];
+            value.copy_from_slice(vcn);
+            u64::from_le_bytes(value)
+        });
+        let mut reference = [0; 8];
+        reference.copy_from_slice(&header[..8]);
+        entries.push(IndexEntry {
+            reference: intact.then(|| FileReference::from(u64::from_le_bytes(reference))),
+            file_name,
+            child,
+            slack: true,
+            offset: if intact { key - 16 } else { key },
+        });
+        key = (key + key_length).next_multiple_of(8) + 16;
+    }
+    entries
+}
+
+/// Parses what's at the start of `bytes` if it looks like a whole `$FILE_NAME`
+fn slack_file_name(bytes: &[u8]) -> Option<FileName> {
+    let name_length = bytes[64] as usize;
+    let name = bytes.get(FILE_NAME_SIZE..FILE_NAME_SIZE + name_length * 2)?;
+    if name_length == 0 || bytes[65] > 3 {
+        return None;
+    }
+    let characters: Vec<u16> = name
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    let name = String::from_utf16(&characters).ok()?;
+    if name
+        .chars()
+        .any(|character| character < ' ' || character == '/')
+    {
+        return None;
+    }
+    let file_name = FileName::parse(&bytes[..FILE_NAME_SIZE + name_length * 2]).ok()?;
+    let times = file_name.times;
+    [
+        times.created,
+        times.modified,
+        times.mft_modified,
+        times.accessed,
+    ]
+    .iter()
+    .all(|time| (OLDEST_TIME..NEWEST_TIME).contains(time))
+    .then_some(file_name)
+}
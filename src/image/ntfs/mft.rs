@@ -0,0 +1,719 @@
+//! Parses the records of the Master File Table. Every record starts with the `FILE` signature,
+//! has its sectors protected by the update sequence array and holds the attributes of the
+//! element, two of which keep its attribute word: `$STANDARD_INFORMATION`, that Windows updates
+//! every time the attributes change, and every `$FILE_NAME`, whose copy is only updated when
+//! the name changes. Tools that change the attributes or the times, like the ones used for
+//! timestomping, usually only touch the first one, so
+//! [`MftRecord::discrepancies`](crate::image::ntfs::mft::MftRecord::discrepancies) compares them.
+//!
+//! The times are kept as they're stored, as FILETIME values.
+//! ```no_run
+//! use keypropdecode::image::ntfs::mft::Mft;
+//!
+//! let mut mft = Mft::new(std::fs::File::open("$MFT").unwrap());
+//! for record in mft.records() {
+//!     let (number, record) = record.unwrap();
+//!     for discrepancy in record.discrepancies() {
+//!         println!("{} {:?}", number, discrepancy);
+//!     }
+//! }
+//! ```
+use std::collections::BTreeSet;
+use std::io::{Read, Seek, SeekFrom};
+
+use super::{apply_fixups, utf16_at, FileReference};
+use crate::archive::io_error;
+use crate::error::*;
+use crate::image::{invalid, read_at};
+use crate::props::constants::*;
+use crate::reader::Reader;
+use crate::Props;
+
+/// The size of an MFT record on almost every volume
+pub const RECORD_SIZE: usize = 1024;
+/// The bytes of the header of a record before the record number, which NTFS 3.0 doesn't have
+const RECORD_HEADER: usize = 42;
+/// The type code of `$STANDARD_INFORMATION`
+pub const STANDARD_INFORMATION: u32 = 0x10;
+/// The type code of `$ATTRIBUTE_LIST`
+pub const ATTRIBUTE_LIST: u32 = 0x20;
+/// The type code of `$FILE_NAME`
+pub const FILE_NAME: u32 = 0x30;
+/// The type code of `$DATA`
+pub const DATA: u32 = 0x80;
+/// The flag of the records in use
+pub const RECORD_IN_USE: u16 = 0x01;
+/// The flag of the records of directories
+pub const RECORD_IS_DIRECTORY: u16 = 0x02;
+/// The bit `$FILE_NAME` uses for directories, instead of the directory attribute
+pub const FILE_NAME_INDEX_PRESENT: u32 = 0x1000_0000;
+/// The bit `$FILE_NAME` uses for view indexes, like the ones of `$Secure`
+pub const FILE_NAME_VIEW_INDEX_PRESENT: u32 = 0x2000_0000;
+
+const WHAT: &str = "MFT record";
+
+/// The four times NTFS keeps, as FILETIME values
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct NtfsTimes {
+    /// When the element was created
+    pub created: u64,
+    /// When the data was last written
+    pub modified: u64,
+    /// When the MFT record changed
+    pub mft_modified: u64,
+    /// When the element was last read
+    pub accessed: u64,
+}
+impl NtfsTimes {
+    fn read(reader: &mut Reader) -> Result<Self> {
+        Ok(NtfsTimes {
+            created: reader.u64()?,
+            modified: reader.u64()?,
+            mft_modified: reader.u64()?,
+            accessed: reader.u64()?,
+        })
+    }
+}
+
+/// The `$STANDARD_INFORMATION` attribute
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct StandardInformation {
+    /// The times Windows shows for the element
+    pub times: NtfsTimes,
+    /// The attribute word, without the directory attribute, which NTFS keeps in the record flags
+    pub attributes: u32,
+    /// The security descriptor in `$Secure`, a field NTFS 3.0 added
+    pub security_id: Option<u32>,
+    /// The last change of the element in the USN journal, a field NTFS 3.0 added
+    pub usn: Option<u64>,
+}
+impl StandardInformation {
+    /// Parses the value of the attribute
+    pub fn parse(value: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(value, "$STANDARD_INFORMATION attribute");
+        let times = NtfsTimes::read(&mut reader)?;
+        let attributes = reader.u32()?;
+        let (mut security_id, mut usn) = (None, None);
+        if reader.remaining() >= 36 {
+            // The maximum versions, the version, the class and the owner
+            reader.skip(16)?;
+            security_id = Some(reader.u32()?);
+            // The quota charged
+            reader.skip(8)?;
+            usn = Some(reader.u64()?);
+        }
+        Ok(StandardInformation {
+            times,
+            attributes,
+            security_id,
+            usn,
+        })
+    }
+}
+
+/// The namespace of a `$FILE_NAME`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum FileNameNamespace {
+    /// Any name, case sensitive
+    Posix,
+    /// The long name, when there's also a DOS one
+    Win32,
+    /// The 8.3 name, when there's also a long one
+    Dos,
+    /// A name that is valid as both
+    Win32AndDos,
+    /// Any other value, which Windows doesn't write
+    Other(u8),
+}
+impl From<u8> for FileNameNamespace {
+    fn from(namespace: u8) -> Self {
+        match namespace {
+            0 => FileNameNamespace::Posix,
+            1 => FileNameNamespace::Win32,
+            2 => FileNameNamespace::Dos,
+            3 => FileNameNamespace::Win32AndDos,
+            other => FileNameNamespace::Other(other),
+        }
+    }
+}
+
+/// A `$FILE_NAME` attribute
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FileName {
+    /// The directory the name is in
+    pub parent: FileReference,
+    /// The times when the name was last changed
+    pub times: NtfsTimes,
+    /// The allocated size of the data when the name was last changed
+    pub allocated_size: u64,
+    /// The size of the data when the name was last changed
+    pub real_size: u64,
+    /// The attribute word, with [`FILE_NAME_INDEX_PRESENT`] for directories
+    pub attributes: u32,
+    /// Which names the name is valid as
+    pub namespace: FileNameNamespace,
+    /// The name, without the path of the parent
+    pub name: String,
+}
+impl FileName {
+    /// Parses the value of the attribute
+    pub fn parse(value: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(value, "$FILE_NAME attribute");
+        let parent = FileReference::from(reader.u64()?);
+        let times = NtfsTimes::read(&mut reader)?;
+        let allocated_size = reader.u64()?;
+        let real_size = reader.u64()?;
+        let attributes = reader.u32()?;
+        // The reparse tag or the size of the extended attributes
+        reader.skip(4)?;
+        let length = reader.take(2)?;
+        Ok(FileName {
+            parent,
+            times,
+            allocated_size,
+            real_size,
+            attributes,
+            namespace: FileNameNamespace::from(length[1]),
+            name: utf16_at(value, 66, length[0] as usize, "$FILE_NAME attribute")?,
+        })
+    }
+    /// Gets the Props of the attribute word
+    pub fn props(&self) -> Props {
+        Props::from(crate::backends::with_element_type(
+            self.attributes & !(FILE_NAME_INDEX_PRESENT | FILE_NAME_VIEW_INDEX_PRESENT),
+            self.attributes & FILE_NAME_INDEX_PRESENT != 0,
+        ))
+    }
+}
+
+/// An entry of an `$ATTRIBUTE_LIST`, which tells in which record an attribute is when they
+/// don't fit in one
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AttributeListEntry {
+    /// The type of the attribute, like [`FILE_NAME`]
+    pub type_code: u32,
+    /// The name of the attribute, empty for most of them
+    pub name: String,
+    /// The first cluster of the data the attribute has, for the ones split in several records
+    pub starting_vcn: u64,
+    /// The record the attribute is in
+    pub reference: FileReference,
+    /// The identifier of the attribute in its record
+    pub id: u16,
+}
+/// Parses the value of an `$ATTRIBUTE_LIST`
+pub fn parse_attribute_list(value: &[u8]) -> Result<Vec<AttributeListEntry>> {
+    let what = "$ATTRIBUTE_LIST attribute";
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    while offset < value.len() {
+        let mut reader = Reader::new(&value[offset..], what);
+        let type_code = reader.u32()?;
+        let length = reader.u16()? as usize;
+        let name = reader.take(2)?;
+        if length < 26 {
+            return Err(invalid("An $ATTRIBUTE_LIST entry has an invalid length"));
+        }
+        let starting_vcn = reader.u64()?;
+        let reference = FileReference::from(reader.u64()?);
+        let id = reader.u16()?;
+        entries.push(AttributeListEntry {
+            type_code,
+            name: utf16_at(&value[offset..], name[1] as usize, name[0] as usize, what)?,
+            starting_vcn,
+            reference,
+            id,
+        });
+        offset += length;
+    }
+    Ok(entries)
+}
+
+/// A run of clusters of a non-resident attribute
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct DataRun {
+    /// The number of clusters
+    pub length: u64,
+    /// The first cluster of the run in the volume, or None for the sparse runs, which read as
+    /// zeros
+    pub lcn: Option<u64>,
+}
+/// Parses a mapping pairs array, the list of data runs of a non-resident attribute.
+/// Every run starts with a byte with the size of the offset in the high nibble and the size of
+/// the length in the low one, and the offsets are relative to the previous run.
+pub fn parse_data_runs(bytes: &[u8]) -> Result<Vec<DataRun>> {
+    let malformed = || invalid("The data runs of an attribute are malformed");
+    let mut runs = Vec::new();
+    let mut offset = 0;
+    let mut lcn: i64 = 0;
+    while let Some(header) = bytes.get(offset).filter(|header| **header != 0) {
+        let length_size = (header & 0x0F) as usize;
+        let offset_size = (header >> 4) as usize;
+        if length_size == 0 || length_size > 8 || offset_size > 8 {
+            return Err(malformed());
+        }
+        let field = bytes
+            .get(offset + 1..offset + 1 + length_size + offset_size)
+            .ok_or_else(malformed)?;
+        let length = field[..length_size]
+            .iter()
+            .rev()
+            .fold(0u64, |value, byte| value << 8 | *byte as u64);
+        let run_lcn = match offset_size {
+            0 => None,
+            _ => {
+                // Sign extended from its highest byte
+                let delta = field[length_size..].iter().rev().fold(
+                    (field[length_size + offset_size - 1] as i8 as i64) >> 8,
+                    |value, byte| value << 8 | *byte as i64,
+                );
+                lcn = lcn
+                    .checked_add(delta)
+                    .filter(|lcn| *lcn >= 0)
+                    .ok_or_else(malformed)?;
+                Some(lcn as u64)
+            }
+        };
+        runs.push(DataRun {
+            length,
+            lcn: run_lcn,
+        });
+        offset += 1 + length_size + offset_size;
+    }
+    Ok(runs)
+}
+/// A piece of the non-resident unnamed `$DATA` attribute of a record
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct NonResidentData {
+    /// The first cluster of the data this piece maps
+    pub starting_vcn: u64,
+    /// The size of the whole data, only valid in the piece that starts at 0
+    pub data_size: u64,
+    /// Where the clusters of the piece are
+    pub runs: Vec<DataRun>,
+}
+
+/// A difference between `$STANDARD_INFORMATION` and a `$FILE_NAME` of the same record
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Discrepancy {
+    /// The name of the `$FILE_NAME`
+    pub name: String,
+    /// The attributes set in only one of them, leaving out the directory ones
+    pub attributes: u32,
+    /// True when `$STANDARD_INFORMATION` says the element was created before the name was
+    pub created_before_file_name: bool,
+    /// True when `$STANDARD_INFORMATION` says the element was modified before the name was
+    pub modified_before_file_name: bool,
+}
+
+/// A record of the Master File Table
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MftRecord {
+    /// The number the record says it has, only in records written by Windows XP and later
+    pub number: Option<u32>,
+    /// How many times the record was used, which references to it must match
+    pub sequence: u16,
+    /// The number of hard links to the element
+    pub link_count: u16,
+    /// The flags, like [`RECORD_IN_USE`] and [`RECORD_IS_DIRECTORY`]
+    pub flags: u16,
+    /// The base record of an extension record, with the record number 0 in base records
+    pub base_record: FileReference,
+    /// The `$STANDARD_INFORMATION`, which extension records don't have
+    pub standard_information: Option<StandardInformation>,
+    /// The `$FILE_NAME` attributes, one for every name and namespace of the element
+    pub file_names: Vec<FileName>,
+    /// The entries of the `$ATTRIBUTE_LIST`, if the record has one that's resident
+    pub attribute_list: Option<Vec<AttributeListEntry>>,
+    /// The unnamed `$DATA` when it isn't resident. With an `$ATTRIBUTE_LIST` it can be split
+    /// between several records.
+    pub data: Option<NonResidentData>,
+}
+impl MftRecord {
+    /// Parses a whole record, applying its fixups first
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        match bytes.get(..4) {
+            Some(b"FILE") => (),
+            Some(b"BAAD") => return Err(invalid("The MFT record was marked as bad by chkdsk")),
+            _ => return Err(invalid("The MFT record doesn't start with FILE")),
+        }
+        let mut bytes = bytes.to_vec();
+        apply_fixups(&mut bytes, WHAT)?;
+        let mut header = Reader::new(&bytes, WHAT);
+        header.skip(16)?;
+        let sequence = header.u16()?;
+        let link_count = header.u16()?;
+        let first_attribute = header.u16()? as usize;
+        let flags = header.u16()?;
+        let used = header.u32()? as usize;
+        header.skip(4)?;
+        let base_record = FileReference::from(header.u64()?);
+        header.skip(4)?;
+        let number = match first_attribute >= 48 {
+            true => Some(header.u32()?),
+            false => None,
+        };
+        if used > bytes.len() || first_attribute > used {
+            return Err(invalid("The MFT record has an invalid used size"));
+        }
+        let mut record = MftRecord {
+            number,
+            sequence,
+            link_count,
+            flags,
+            base_record,
+            standard_information: None,
+            file_names: Vec::new(),
+            attribute_list: None,
+            data: None,
+        };
+        for attribute in Attributes::new(&bytes[..used], first_attribute) {
+            let attribute = attribute?;
+            let Some(value) = attribute.value else {
+                if attribute.type_code == DATA && attribute.bytes[9] == 0 {
+                    record.data = Some(non_resident_data(attribute.bytes)?);
+                }
+                continue;
+            };
+            match attribute.type_code {
+                STANDARD_INFORMATION => {
+                    record.standard_information = Some(StandardInformation::parse(value)?)
+                }
+                FILE_NAME => record.file_names.push(FileName::parse(value)?),
+                ATTRIBUTE_LIST => record.attribute_list = Some(parse_attribute_list(value)?),
+                _ => (),
+            }
+        }
+        Ok(record)
+    }
+    /// Returns true if the record is in use, false if the element was deleted
+    pub fn in_use(&self) -> bool {
+        self.flags & RECORD_IN_USE != 0
+    }
+    /// Returns true if the record is of a directory
+    pub fn is_directory(&self) -> bool {
+        self.flags & RECORD_IS_DIRECTORY != 0
+    }
+    /// Gets the Props of `$STANDARD_INFORMATION`, with the directory attribute from the flags
+    pub fn props(&self) -> Option<Props> {
+        self.standard_information
+            .map(|information| self.props_of(information.attributes))
+    }
+    /// Gets the Props of every `$FILE_NAME`, with the directory attribute from the flags
+    pub fn file_name_props(&self) -> Vec<Props> {
+        self.file_names
+            .iter()
+            .map(|file_name| self.props_of(file_name.attributes))
+            .collect()
+    }
+    /// Gets the preferred name: the long one if there's one, or any other if there isn't
+    pub fn name(&self) -> Option<&FileName> {
+        self.file_names
+            .iter()
+            .find(|file_name| file_name.namespace != FileNameNamespace::Dos)
+            .or(self.file_names.first())
+    }
+    /// Compares `$STANDARD_INFORMATION` with every `$FILE_NAME`, giving the ones that differ
+    pub fn discrepancies(&self) -> Vec<Discrepancy> {
+        let Some(information) = self.standard_information else {
+            return Vec::new();
+        };
+        let compared = !(DIRECTORY | FILE_NAME_INDEX_PRESENT | FILE_NAME_VIEW_INDEX_PRESENT);
+        self.file_names
+            .iter()
+            .map(|file_name| Discrepancy {
+                name: file_name.name.clone(),
+                attributes: (information.attributes ^ file_name.attributes) & compared,
+                created_before_file_name: information.times.created < file_name.times.created,
+                modified_before_file_name: information.times.modified < file_name.times.modified,
+            })
+            .filter(|discrepancy| {
+                discrepancy.attributes != 0
+                    || discrepancy.created_before_file_name
+                    || discrepancy.modified_before_file_name
+            })
+            .collect()
+    }
+    fn props_of(&self, attributes: u32) -> Props {
+        Props::from(crate::backends::with_element_type(
+            attributes & !(FILE_NAME_INDEX_PRESENT | FILE_NAME_VIEW_INDEX_PRESENT),
+            self.is_directory(),
+        ))
+    }
+}
+
+/// An attribute of a record, with its value if it's resident
+pub(crate) struct Attribute<'a> {
+    pub(crate) type_code: u32,
+    /// The value of resident attributes
+    pub(crate) value: Option<&'a [u8]>,
+    /// The whole attribute, header included
+    pub(crate) bytes: &'a [u8],
+}
+/// The attributes of the used part of a record, from the first one to the end marker
+pub(crate) struct Attributes<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+impl<'a> Attributes<'a> {
+    pub(crate) fn new(bytes: &'a [u8], offset: usize) -> Self {
+        Attributes { bytes, offset }
+    }
+    fn parse(&mut self) -> Result<Option<Attribute<'a>>> {
+        let mut header = Reader::new(&self.bytes[self.offset..], WHAT);
+        let type_code = header.u32()?;
+        if type_code == 0xFFFF_FFFF {
+            return Ok(None);
+        }
+        let length = header.u32()? as usize;
+        if length < 16 || !length.is_multiple_of(8) || length > header.remaining() + 8 {
+            return Err(invalid(
+                "An attribute of the MFT record has an invalid length",
+            ));
+        }
+        let bytes = &self.bytes[self.offset..self.offset + length];
+        self.offset += length;
+        let value = match bytes[8] == 0 {
+            true => {
+                let mut fields = Reader::new(&bytes[16..], WHAT);
+                let length = fields.u32()? as usize;
+                let offset = fields.u16()? as usize;
+                let value = bytes
+                    .get(offset..offset + length)
+                    .ok_or_else(|| invalid("The value of an attribute is outside of it"))?;
+                Some(value)
+            }
+            false => None,
+        };
+        Ok(Some(Attribute {
+            type_code,
+            value,
+            bytes,
+        }))
+    }
+}
+impl<'a> Iterator for Attributes<'a> {
+    type Item = Result<Attribute<'a>>;
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.parse() {
+            Ok(Some(attribute)) => Some(Ok(attribute)),
+            Ok(None) => None,
+            Err(error) => {
+                // Nothing after a malformed attribute can be trusted
+                self.offset = self.bytes.len();
+                Some(Err(error))
+            }
+        }
+    }
+}
+
+/// Parses the header of a non-resident attribute and its data runs
+fn non_resident_data(bytes: &[u8]) -> Result<NonResidentData> {
+    let mut header = Reader::new(bytes, WHAT);
+    header.skip(16)?;
+    let starting_vcn = header.u64()?;
+    header.skip(8)?;
+    let runs = header.u16()? as usize;
+    header.skip(14)?;
+    let data_size = header.u64()?;
+    Ok(NonResidentData {
+        starting_vcn,
+        data_size,
+        runs: parse_data_runs(
+            bytes
+                .get(runs..)
+                .ok_or_else(|| invalid("The data runs of an attribute are outside of it"))?,
+        )?,
+    })
+}
+
+/// A piece of the `$MFT` file: where it is in the file and, if it isn't sparse, in the volume
+#[derive(Debug, Clone, Copy)]
+pub(super) struct Extent {
+    pub(super) offset: u64,
+    pub(super) length: u64,
+    pub(super) volume_offset: Option<u64>,
+}
+
+/// The `$MFT` file, read one record at a time from a copy of it or from a volume
+pub struct Mft<R> {
+    pub(super) reader: R,
+    pub(super) record_size: usize,
+    /// Where the pieces of the file are, when reading it from a volume
+    pub(super) extents: Option<Vec<Extent>>,
+    /// The size of the file, when reading it from a volume
+    pub(super) length: Option<u64>,
+}
+impl<R: Read + Seek> Mft<R> {
+    /// Reads the records from `reader`, which must start with the record 0
+    pub fn new(reader: R) -> Self {
+        Mft {
+            reader,
+            record_size: RECORD_SIZE,
+            extents: None,
+            length: None,
+        }
+    }
+    /// Sets the size of the records, for the volumes whose records aren't 1024 bytes long.
+    /// Gives an error for sizes too small to hold the header of a record.
+    pub fn record_size(mut self, record_size: usize) -> Result<Self> {
+        if record_size < RECORD_HEADER {
+            return Err(invalid(&format!(
+                "MFT records of {} bytes can't hold their header",
+                record_size
+            )));
+        }
+        self.record_size = record_size;
+        Ok(self)
+    }
+    /// Reads the record `number` as it's stored
+    pub fn record(&mut self, number: u64) -> Result<MftRecord> {
+        let mut bytes = vec![0; self.record_size];
+        let offset = number.checked_mul(self.record_size as u64).ok_or_else(|| {
+            invalid(&format!(
+                "The MFT record {} is past the end of the file",
+                number
+            ))
+        })?;
+        self.read_bytes(offset, &mut bytes)?;
+        MftRecord::parse(&bytes)
+    }
+    /// Reads the record `number` with the `$STANDARD_INFORMATION` and `$FILE_NAME` attributes
+    /// that its `$ATTRIBUTE_LIST` says are in extension records
+    pub fn resolve(&mut self, number: u64) -> Result<MftRecord> {
+        let mut record = self.record(number)?;
+        let Some(list) = record.attribute_list.clone() else {
+            return Ok(record);
+        };
+        let extensions: BTreeSet<u64> = list
+            .iter()
+            .filter(|entry| matches!(entry.type_code, STANDARD_INFORMATION | FILE_NAME))
+            .map(|entry| entry.reference.record)
+            .filter(|extension| *extension != number)
+            .collect();
+        for extension in extensions {
+            let extension = self.record(extension)?;
+            if extension.base_record.record != number {
+                return Err(invalid(
+                    "An $ATTRIBUTE_LIST points to a record of another element",
+                ));
+            }
+            if record.standard_information.is_none() {
+                record.standard_information = extension.standard_information;
+            }
+            record.file_names.extend(extension.file_names);
+        }
+        Ok(record)
+    }
+    /// Reads every record in order, skipping the ones that were never used, which are all zeros.
+    /// The extension records are given too, so check [`MftRecord::base_record`].
+    pub fn records(&mut self) -> MftRecords<'_, R> {
+        MftRecords {
+            mft: self,
+            number: 0,
+            count: None,
+            batch: Vec::new(),
+            batch_start: 0,
+        }
+    }
+    /// The number of records of the file.
+    /// The size of the `$DATA` of a volume is only believed up to what its data runs map, which
+    /// can't be more than the volume itself.
+    pub(super) fn count(&mut self) -> Result<u64> {
+        let end = self.reader.seek(SeekFrom::End(0)).map_err(io_error)?;
+        let mapped = self
+            .extents
+            .iter()
+            .flatten()
+            .map(|extent| extent.offset + extent.length)
+            .max()
+            .unwrap_or(0);
+        let length = match self.length {
+            Some(length) => length.min(mapped).min(end),
+            None => end,
+        };
+        Ok(length / self.record_size as u64)
+    }
+    /// Fills `buffer` with the bytes of the file at `offset`, following the extents if there are
+    fn read_bytes(&mut self, offset: u64, buffer: &mut [u8]) -> Result<()> {
+        let Some(extents) = &self.extents else {
+            return read_at(&mut self.reader, offset, buffer, "MFT");
+        };
+        let mut filled = 0;
+        while filled < buffer.len() {
+            let at = offset
+                .checked_add(filled as u64)
+                .ok_or_else(|| crate::archive::truncated("MFT"))?;
+            let index = extents.partition_point(|extent| extent.offset + extent.length <= at);
+            let extent = extents
+                .get(index)
+                .filter(|extent| extent.offset <= at)
+                .ok_or_else(|| crate::archive::truncated("MFT"))?;
+            let count = (extent.offset + extent.length - at).min((buffer.len() - filled) as u64);
+            let part = &mut buffer[filled..filled + count as usize];
+            match extent.volume_offset {
+                Some(volume_offset) => read_at(
+                    &mut self.reader,
+                    volume_offset
+                        .checked_add(at - extent.offset)
+                        .ok_or_else(|| crate::archive::truncated("MFT"))?,
+                    part,
+                    "MFT",
+                )?,
+                None => part.fill(0),
+            }
+            filled += count as usize;
+        }
+        Ok(())
+    }
+}
+
+/// The records read at once by [`Mft::records`]
+const BATCH: u64 = 64;
+
+/// The records of [`Mft::records`], with their numbers
+pub struct MftRecords<'m, R> {
+    mft: &'m mut Mft<R>,
+    number: u64,
+    count: Option<u64>,
+    /// The records from `batch_start` on, read at once
+    batch: Vec<u8>,
+    batch_start: u64,
+}
+impl<R: Read + Seek> Iterator for MftRecords<'_, R> {
+    type Item = Result<(u64, MftRecord)>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let count = match self.count {
+            Some(count) => count,
+            None => match self.mft.count() {
+                Ok(count) => *self.count.insert(count),
+                Err(error) => {
+                    self.count = Some(0);
+                    return Some(Err(error));
+                }
+            },
+        };
+        let size = self.mft.record_size;
+        while self.number < count {
+            let number = self.number;
+            self.number += 1;
+            let index = (number - self.batch_start) as usize;
+            if number < self.batch_start || (index + 1) * size > self.batch.len() {
+                let records = BATCH.min(count - number) as usize;
+                self.batch.resize(records * size, 0);
+                self.batch_start = number;
+                if let Err(error) = self.mft.read_bytes(number * size as u64, &mut self.batch) {
+                    self.number = count;
+                    return Some(Err(error));
+                }
+            }
+            let index = (number - self.batch_start) as usize;
+            let bytes = &self.batch[index * size..(index + 1) * size];
+            if bytes.iter().all(|byte| *byte == 0) {
+                continue;
+            }
+            return Some(MftRecord::parse(bytes).map(|record| (number, record)));
+        }
+        None
+    }
+}
@@ -0,0 +1,79 @@
+//! The on-disk structures of NTFS that keep the attributes of every element, read from a copy
+//! of the `$MFT` file or from a whole volume.
+/// The index buffers directories keep the copies of the names of their elements in
+pub mod index;
+/// The records of the Master File Table
+pub mod mft;
+#[cfg(test)]
+mod tests;
+/// The records of the change journal
+pub mod usn;
+/// Enumerates whole NTFS volumes with the paths of their elements
+pub mod volume;
+
+use crate::error::*;
+
+/// The bytes each entry of an update sequence array protects, whatever the sector size is
+const FIXUP_STRIDE: usize = 512;
+
+/// A reference to an MFT record: the record number and the sequence number the record had
+/// when the reference was made, which tells if the record was reused since then
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, PartialOrd, Ord, Default)]
+pub struct FileReference {
+    /// The number of the record, 48 bits long
+    pub record: u64,
+    /// The sequence number of the record
+    pub sequence: u16,
+}
+impl From<u64> for FileReference {
+    fn from(reference: u64) -> Self {
+        FileReference {
+            record: reference & 0x0000_FFFF_FFFF_FFFF,
+            sequence: (reference >> 48) as u16,
+        }
+    }
+}
+impl From<FileReference> for u64 {
+    fn from(reference: FileReference) -> Self {
+        reference.record & 0x0000_FFFF_FFFF_FFFF | (reference.sequence as u64) << 48
+    }
+}
+
+/// Puts back the last two bytes of every 512 byte stride of a multi-sector structure, like MFT
+/// records and index buffers, checking that each of them had the update sequence number.
+/// A stride without it was torn while being written and gives an `InvalidData` error.
+pub(crate) fn apply_fixups(bytes: &mut [u8], what: &str) -> Result<()> {
+    let invalid = |message: &str| Error {
+        kind: ErrorKind::InvalidData(format!("The {} {}", what, message)),
+    };
+    if bytes.len() < 8 {
+        return Err(invalid("is truncated"));
+    }
+    let offset = u16::from_le_bytes([bytes[4], bytes[5]]) as usize;
+    let count = u16::from_le_bytes([bytes[6], bytes[7]]) as usize;
+    if count == 0 || offset + count * 2 > bytes.len() || (count - 1) * FIXUP_STRIDE > bytes.len() {
+        return Err(invalid("has an invalid update sequence array"));
+    }
+    let array = bytes[offset..offset + count * 2].to_vec();
+    for (stride, fixup) in array[2..].chunks_exact(2).enumerate() {
+        let end = (stride + 1) * FIXUP_STRIDE;
+        if bytes[end - 2..end] != array[..2] {
+            return Err(invalid(
+                "has a torn write, a sector doesn't end with its update sequence number",
+            ));
+        }
+        bytes[end - 2..end].copy_from_slice(fixup);
+    }
+    Ok(())
+}
+/// Reads the UTF-16 text of `length` characters at `offset`
+pub(crate) fn utf16_at(bytes: &[u8], offset: usize, length: usize, what: &str) -> Result<String> {
+    let text = bytes
+        .get(offset..offset + length * 2)
+        .ok_or_else(|| crate::archive::truncated(what))?;
+    let characters: Vec<u16> = text
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    Ok(String::from_utf16_lossy(&characters))
+}
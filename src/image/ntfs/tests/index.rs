@@ -0,0 +1,120 @@
+use super::mft::{file_name, protect};
+use crate::image::ntfs::index::*;
+use crate::image::ntfs::mft::FILE_NAME_INDEX_PRESENT;
+use crate::image::ntfs::FileReference;
+use crate::props::constants::*;
+use crate::Props;
+
+/// A FILETIME of 2012
+const TIME: u64 = 130_000_000_000_000_000;
+
+fn entry(reference: u64, name: &str, attributes: u32, child: Option<u64>) -> Vec<u8> {
+    let attribute = file_name(5 | 5 << 48, name, 3, attributes, TIME);
+    let key = &attribute[24..24 + 66 + name.encode_utf16().count() * 2];
+    let child_size = if child.is_some() { 8 } else { 0 };
+    let length = (16 + key.len()).next_multiple_of(8) + child_size;
+    let mut entry = reference.to_le_bytes().to_vec();
+    entry.extend((length as u16).to_le_bytes());
+    entry.extend((key.len() as u16).to_le_bytes());
+    entry.extend((child_size as u32 / 8 * INDEX_ENTRY_NODE).to_le_bytes());
+    entry.extend(key);
+    entry.resize(length - child_size, 0);
+    if let Some(child) = child {
+        entry.extend(child.to_le_bytes());
+    }
+    entry
+}
+/// A 4096 byte buffer with `live` before the end entry and `slack` after it
+fn buffer(live: &[Vec<u8>], slack: &[u8]) -> Vec<u8> {
+    let mut buffer = b"INDX".to_vec();
+    buffer.extend(40u16.to_le_bytes());
+    buffer.extend(9u16.to_le_bytes());
+    buffer.extend(0x55u64.to_le_bytes());
+    buffer.extend(4u64.to_le_bytes());
+    buffer.resize(64, 0);
+    for entry in live {
+        buffer.extend(entry);
+    }
+    let mut end = vec![0; 8];
+    end.extend(16u16.to_le_bytes());
+    end.extend([0, 0]);
+    end.extend(INDEX_ENTRY_END.to_le_bytes());
+    buffer.extend(end);
+    let used = buffer.len() as u32 - 24;
+    buffer.extend(slack);
+    buffer.resize(4096, 0);
+    buffer[24..28].copy_from_slice(&40u32.to_le_bytes());
+    buffer[28..32].copy_from_slice(&used.to_le_bytes());
+    buffer[32..36].copy_from_slice(&(4096u32 - 24).to_le_bytes());
+    protect(&mut buffer, 40);
+    buffer
+}
+
+#[test]
+fn live_entries() {
+    let bytes = buffer(
+        &[
+            entry(40 | 2 << 48, "a.txt", ARCHIVE | HIDDEN, None),
+            entry(41 | 1 << 48, "Docs", FILE_NAME_INDEX_PRESENT, Some(3)),
+        ],
+        &[],
+    );
+    let buffer = IndexBuffer::parse(&bytes).unwrap();
+    assert_eq!(buffer.lsn, 0x55);
+    assert_eq!(buffer.vcn, 4);
+    assert_eq!(buffer.slack().count(), 0);
+    let entries: Vec<&IndexEntry> = buffer.live().collect();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(
+        entries[0].reference,
+        Some(FileReference {
+            record: 40,
+            sequence: 2
+        })
+    );
+    assert_eq!(entries[0].file_name.name, "a.txt");
+    assert_eq!(entries[0].props(), Props::from(ARCHIVE | HIDDEN));
+    assert_eq!(entries[0].offset, 64);
+    assert_eq!(entries[0].child, None);
+    assert_eq!(entries[1].props(), Props::from(DIRECTORY));
+    assert_eq!(entries[1].child, Some(3));
+}
+#[test]
+fn slack_entries() {
+    // A long name that crosses the fixup at the end of the first sector
+    let long_name = "deleted ".repeat(30) + ".doc";
+    let mut slack = entry(50 | 3 << 48, "secret.doc", HIDDEN | SYSTEM, None);
+    slack.extend(entry(51 | 1 << 48, &long_name, READ_ONLY, None));
+    // Only the name is left of this one
+    let mut overwritten = entry(52 | 1 << 48, "old.txt", ARCHIVE, None);
+    overwritten[..16].fill(0xAB);
+    slack.extend(overwritten);
+    // Not a $FILE_NAME
+    slack.extend([0x41; 96]);
+    let bytes = buffer(&[entry(40, "a.txt", ARCHIVE, None)], &slack);
+    let buffer = IndexBuffer::parse(&bytes).unwrap();
+    assert_eq!(buffer.live().count(), 1);
+    let slack: Vec<&IndexEntry> = buffer.slack().collect();
+    let names: Vec<&str> = slack
+        .iter()
+        .map(|entry| entry.file_name.name.as_str())
+        .collect();
+    assert_eq!(names, ["secret.doc", long_name.as_str(), "old.txt"]);
+    assert_eq!(slack[0].props(), Props::from(ARCHIVE | HIDDEN | SYSTEM));
+    assert_eq!(slack[0].reference.unwrap().record, 50);
+    assert_eq!(slack[1].props(), Props::from(ARCHIVE | READ_ONLY));
+    assert_eq!(slack[2].reference, None);
+    assert_eq!(slack[2].props(), Props::from(ARCHIVE));
+}
+#[test]
+fn damaged_buffers() {
+    let mut torn = buffer(&[entry(40, "a.txt", ARCHIVE, None)], &[]);
+    torn[2046] = 0;
+    assert!(IndexBuffer::parse(&torn).is_err());
+    let mut record = buffer(&[], &[]);
+    record[..4].copy_from_slice(b"FILE");
+    assert!(IndexBuffer::parse(&record).is_err());
+    let mut long = entry(40, "a.txt", ARCHIVE, None);
+    long[8..10].copy_from_slice(&4000u16.to_le_bytes());
+    assert!(IndexBuffer::parse(&buffer(&[long], &[])).is_err());
+}
@@ -0,0 +1,280 @@
+use std::io::Cursor;
+
+use crate::error::*;
+use crate::image::ntfs::mft::*;
+use crate::image::ntfs::FileReference;
+use crate::props::constants::*;
+use crate::Props;
+
+pub(super) fn resident(type_code: u32, value: &[u8]) -> Vec<u8> {
+    let length = (24 + value.len()).div_ceil(8) * 8;
+    let mut attribute = type_code.to_le_bytes().to_vec();
+    attribute.extend((length as u32).to_le_bytes());
+    // Resident, without a name
+    attribute.extend([0, 0, 0, 0, 0, 0, 0, 0]);
+    attribute.extend((value.len() as u32).to_le_bytes());
+    attribute.extend(24u16.to_le_bytes());
+    attribute.extend([0, 0]);
+    attribute.extend(value);
+    attribute.resize(length, 0);
+    attribute
+}
+/// A non-resident attribute without data runs
+fn non_resident(type_code: u32) -> Vec<u8> {
+    let mut attribute = type_code.to_le_bytes().to_vec();
+    attribute.extend(72u32.to_le_bytes());
+    attribute.push(1);
+    attribute.resize(32, 0);
+    // The data runs, just the end of the list
+    attribute.extend(64u16.to_le_bytes());
+    attribute.resize(72, 0);
+    attribute
+}
+pub(super) fn standard_information(attributes: u32, created: u64, modified: u64) -> Vec<u8> {
+    let mut value = Vec::new();
+    for time in [created, modified, modified, modified] {
+        value.extend(time.to_le_bytes());
+    }
+    value.extend(attributes.to_le_bytes());
+    value.resize(52, 0);
+    value.extend(256u32.to_le_bytes());
+    value.extend([0; 8]);
+    value.extend(0x1234u64.to_le_bytes());
+    resident(STANDARD_INFORMATION, &value)
+}
+pub(super) fn file_name(
+    parent: u64,
+    name: &str,
+    namespace: u8,
+    attributes: u32,
+    created: u64,
+) -> Vec<u8> {
+    let mut value = parent.to_le_bytes().to_vec();
+    for _ in 0..4 {
+        value.extend(created.to_le_bytes());
+    }
+    value.extend(4096u64.to_le_bytes());
+    value.extend(100u64.to_le_bytes());
+    value.extend(attributes.to_le_bytes());
+    value.extend([0; 4]);
+    let name: Vec<u16> = name.encode_utf16().collect();
+    value.push(name.len() as u8);
+    value.push(namespace);
+    value.extend(name.iter().flat_map(|character| character.to_le_bytes()));
+    resident(FILE_NAME, &value)
+}
+/// A 1024 byte record with its fixups applied, like it's stored
+pub(super) fn record(number: u32, flags: u16, base: u64, attributes: &[Vec<u8>]) -> Vec<u8> {
+    let mut record = b"FILE".to_vec();
+    // The update sequence array at 48, with the number and two entries
+    record.extend(48u16.to_le_bytes());
+    record.extend(3u16.to_le_bytes());
+    record.extend([0; 8]);
+    record.extend(7u16.to_le_bytes());
+    record.extend(1u16.to_le_bytes());
+    record.extend(56u16.to_le_bytes());
+    record.extend(flags.to_le_bytes());
+    let used_at = record.len();
+    record.extend([0; 4]);
+    record.extend(1024u32.to_le_bytes());
+    record.extend(base.to_le_bytes());
+    record.extend([0; 4]);
+    record.extend(number.to_le_bytes());
+    record.extend([0; 8]);
+    for attribute in attributes {
+        record.extend(attribute);
+    }
+    record.extend(0xFFFF_FFFFu32.to_le_bytes());
+    record.extend([0; 4]);
+    let used = record.len() as u32;
+    record[used_at..used_at + 4].copy_from_slice(&used.to_le_bytes());
+    record.resize(1024, 0);
+    protect(&mut record, 48);
+    record
+}
+/// Moves the last two bytes of every sector to the update sequence array at `offset`
+pub(super) fn protect(bytes: &mut [u8], offset: usize) {
+    let usn = [0x2A, 0x00];
+    bytes[offset..offset + 2].copy_from_slice(&usn);
+    for sector in 0..bytes.len() / 512 {
+        let end = (sector + 1) * 512;
+        let entry = offset + 2 + sector * 2;
+        let original = [bytes[end - 2], bytes[end - 1]];
+        bytes[entry..entry + 2].copy_from_slice(&original);
+        bytes[end - 2..end].copy_from_slice(&usn);
+    }
+}
+
+#[test]
+fn references() {
+    let reference = FileReference::from(0x0005_0000_0000_0024);
+    assert_eq!(
+        reference,
+        FileReference {
+            record: 0x24,
+            sequence: 5
+        }
+    );
+    assert_eq!(u64::from(reference), 0x0005_0000_0000_0024);
+}
+#[test]
+fn file_record() {
+    // A name long enough to cross the fixup at the end of the first sector
+    let long_name = "a".repeat(200) + ".txt";
+    let bytes = record(
+        40,
+        RECORD_IN_USE,
+        0,
+        &[
+            standard_information(HIDDEN | ARCHIVE, 100, 200),
+            file_name(5 | 5 << 48, "REPORT~1.TXT", 2, HIDDEN | ARCHIVE, 100),
+            file_name(5 | 5 << 48, &long_name, 1, HIDDEN | ARCHIVE, 100),
+            non_resident(0x80),
+        ],
+    );
+    let record = MftRecord::parse(&bytes).unwrap();
+    assert_eq!(record.number, Some(40));
+    assert_eq!(record.sequence, 7);
+    assert!(record.in_use());
+    assert!(!record.is_directory());
+    assert_eq!(record.props(), Some(Props::from(ARCHIVE | HIDDEN)));
+    let information = record.standard_information.unwrap();
+    assert_eq!(information.security_id, Some(256));
+    assert_eq!(information.usn, Some(0x1234));
+    assert_eq!(record.file_names.len(), 2);
+    assert_eq!(record.file_names[0].namespace, FileNameNamespace::Dos);
+    assert_eq!(record.file_names[0].parent.record, 5);
+    assert_eq!(record.name().unwrap().name, long_name);
+    assert_eq!(record.discrepancies(), []);
+}
+#[test]
+fn directory_record() {
+    let bytes = record(
+        5,
+        RECORD_IN_USE | RECORD_IS_DIRECTORY,
+        0,
+        &[
+            standard_information(HIDDEN | SYSTEM, 1, 1),
+            file_name(5, ".", 3, HIDDEN | SYSTEM | FILE_NAME_INDEX_PRESENT, 1),
+        ],
+    );
+    let record = MftRecord::parse(&bytes).unwrap();
+    assert_eq!(
+        record.props(),
+        Some(Props::from(DIRECTORY | HIDDEN | SYSTEM))
+    );
+    assert_eq!(
+        record.file_names[0].props(),
+        Props::from(DIRECTORY | HIDDEN | SYSTEM)
+    );
+    assert_eq!(
+        record.file_name_props(),
+        [Props::from(DIRECTORY | HIDDEN | SYSTEM)]
+    );
+    assert_eq!(record.discrepancies(), []);
+}
+#[test]
+fn timestomped_record() {
+    let bytes = record(
+        41,
+        RECORD_IN_USE,
+        0,
+        &[
+            // Made to look older and to be a system file after the name was set
+            standard_information(ARCHIVE | SYSTEM, 50, 500),
+            file_name(5, "tool.exe", 3, ARCHIVE, 100),
+        ],
+    );
+    let record = MftRecord::parse(&bytes).unwrap();
+    assert_eq!(
+        record.discrepancies(),
+        [Discrepancy {
+            name: "tool.exe".to_string(),
+            attributes: SYSTEM,
+            created_before_file_name: true,
+            modified_before_file_name: false,
+        }]
+    );
+}
+#[test]
+fn damaged_records() {
+    let mut torn = record(1, RECORD_IN_USE, 0, &[standard_information(ARCHIVE, 1, 1)]);
+    torn[1022] = 0;
+    assert!(matches!(
+        MftRecord::parse(&torn),
+        Err(Error {
+            kind: ErrorKind::InvalidData(_)
+        })
+    ));
+    let mut bad = record(1, RECORD_IN_USE, 0, &[]);
+    bad[..4].copy_from_slice(b"BAAD");
+    assert!(MftRecord::parse(&bad).is_err());
+    assert!(MftRecord::parse(&[0; 1024]).is_err());
+    // An attribute longer than the record
+    let mut long = standard_information(ARCHIVE, 1, 1);
+    long[4..8].copy_from_slice(&4096u32.to_le_bytes());
+    assert!(MftRecord::parse(&record(1, RECORD_IN_USE, 0, &[long])).is_err());
+}
+#[test]
+fn attribute_lists() {
+    let mut list = Vec::new();
+    for (type_code, record) in [(STANDARD_INFORMATION, 3u64), (FILE_NAME, 5), (FILE_NAME, 5)] {
+        list.extend(type_code.to_le_bytes());
+        list.extend(32u16.to_le_bytes());
+        list.extend([0, 26]);
+        list.extend(0u64.to_le_bytes());
+        list.extend((record | 1 << 48).to_le_bytes());
+        list.extend([0; 8]);
+    }
+    let base = record(
+        3,
+        RECORD_IN_USE,
+        0,
+        &[
+            standard_information(ARCHIVE | READ_ONLY, 10, 10),
+            resident(ATTRIBUTE_LIST, &list),
+        ],
+    );
+    let extension = record(
+        5,
+        RECORD_IN_USE,
+        3 | 1 << 48,
+        &[
+            file_name(5, "LONGNA~1", 2, ARCHIVE, 10),
+            file_name(5, "long name", 1, ARCHIVE, 10),
+        ],
+    );
+    let mut mft = vec![0; 6 * 1024];
+    mft[3 * 1024..4 * 1024].copy_from_slice(&base);
+    mft[5 * 1024..].copy_from_slice(&extension);
+    let mut mft = Mft::new(Cursor::new(mft));
+
+    let unresolved = mft.record(3).unwrap();
+    assert_eq!(unresolved.file_names, []);
+    assert_eq!(unresolved.attribute_list.as_ref().unwrap().len(), 3);
+    assert_eq!(unresolved.attribute_list.unwrap()[1].reference.record, 5);
+    let resolved = mft.resolve(3).unwrap();
+    assert_eq!(resolved.name().unwrap().name, "long name");
+    assert_eq!(resolved.discrepancies()[0].attributes, READ_ONLY);
+
+    let numbers: Vec<u64> = mft.records().map(|record| record.unwrap().0).collect();
+    assert_eq!(numbers, [3, 5]);
+}
+#[test]
+fn invalid_record_sizes() {
+    for size in [0, 16] {
+        assert!(matches!(
+            Mft::new(Cursor::new(Vec::new())).record_size(size),
+            Err(Error {
+                kind: ErrorKind::InvalidData(_)
+            })
+        ));
+    }
+    let mut mft = Mft::new(Cursor::new(Vec::new())).record_size(4096).unwrap();
+    assert!(matches!(
+        mft.record(u64::MAX / 1024),
+        Err(Error {
+            kind: ErrorKind::InvalidData(_)
+        })
+    ));
+}
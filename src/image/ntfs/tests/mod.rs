@@ -0,0 +1,4 @@
+mod index;
+mod mft;
+mod usn;
+mod volume;
@@ -0,0 +1,204 @@
+use std::io::Cursor;
+
+use crate::error::*;
+use crate::image::ntfs::usn::*;
+use crate::image::ntfs::FileReference;
+use crate::props::constants::*;
+use crate::Props;
+
+fn v2(file: u64, usn: i64, reason: u32, attributes: u32, name: &str) -> Vec<u8> {
+    let name: Vec<u8> = name.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let length = (60 + name.len()).next_multiple_of(8);
+    let mut record = (length as u32).to_le_bytes().to_vec();
+    record.extend(2u16.to_le_bytes());
+    record.extend(0u16.to_le_bytes());
+    record.extend(file.to_le_bytes());
+    record.extend((5u64 | 5 << 48).to_le_bytes());
+    record.extend(usn.to_le_bytes());
+    record.extend((130_000_000_000_000_000 + usn as u64).to_le_bytes());
+    record.extend(reason.to_le_bytes());
+    record.extend(0u32.to_le_bytes());
+    record.extend(0x100u32.to_le_bytes());
+    record.extend(attributes.to_le_bytes());
+    record.extend((name.len() as u16).to_le_bytes());
+    record.extend(60u16.to_le_bytes());
+    record.extend(name);
+    record.resize(length, 0);
+    record
+}
+fn v3(file: u128, reason: u32, attributes: u32, name: &str) -> Vec<u8> {
+    let name: Vec<u8> = name.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let length = (76 + name.len()).next_multiple_of(8);
+    let mut record = (length as u32).to_le_bytes().to_vec();
+    record.extend(3u16.to_le_bytes());
+    record.extend(0u16.to_le_bytes());
+    record.extend(file.to_le_bytes());
+    record.extend(7u128.to_le_bytes());
+    record.extend(4096i64.to_le_bytes());
+    record.extend(130_000_000_000_000_000u64.to_le_bytes());
+    record.extend(reason.to_le_bytes());
+    record.extend(2u32.to_le_bytes());
+    record.extend(0x200u32.to_le_bytes());
+    record.extend(attributes.to_le_bytes());
+    record.extend((name.len() as u16).to_le_bytes());
+    record.extend(76u16.to_le_bytes());
+    record.extend(name);
+    record.resize(length, 0);
+    record
+}
+fn v4(file: u128, extents: &[(i64, i64)]) -> Vec<u8> {
+    let length = 64 + extents.len() * 16;
+    let mut record = (length as u32).to_le_bytes().to_vec();
+    record.extend(4u16.to_le_bytes());
+    record.extend(0u16.to_le_bytes());
+    record.extend(file.to_le_bytes());
+    record.extend(7u128.to_le_bytes());
+    record.extend(8192i64.to_le_bytes());
+    record.extend(
+        (UsnReason::DATA_OVERWRITE | UsnReason::CLOSE)
+            .0
+            .to_le_bytes(),
+    );
+    record.extend(0u32.to_le_bytes());
+    record.extend(0u32.to_le_bytes());
+    record.extend((extents.len() as u16).to_le_bytes());
+    record.extend(16u16.to_le_bytes());
+    for (offset, length) in extents {
+        record.extend(offset.to_le_bytes());
+        record.extend(length.to_le_bytes());
+    }
+    record
+}
+
+#[test]
+fn reasons() {
+    let reason = UsnReason::BASIC_INFO_CHANGE | UsnReason::CLOSE;
+    assert_eq!(reason, UsnReason(0x8000_8000));
+    assert!(reason.contains(UsnReason::CLOSE));
+    assert!(!reason.contains(UsnReason::FILE_CREATE | UsnReason::CLOSE));
+    assert_eq!(reason.to_string(), "BASIC_INFO_CHANGE | CLOSE");
+    assert_eq!(
+        reason.names().collect::<Vec<_>>(),
+        ["BASIC_INFO_CHANGE", "CLOSE"]
+    );
+    assert_eq!(
+        UsnReason(0x4000_0100).to_string(),
+        "FILE_CREATE | 0x40000000"
+    );
+    assert_eq!(UsnReason::default().to_string(), "0");
+    assert_eq!(u32::from(UsnReason::from(0x200)), 0x200);
+}
+#[test]
+fn records() {
+    let record = UsnRecord::parse(&v2(
+        40 | 3 << 48,
+        1024,
+        0x8000_8000,
+        HIDDEN | ARCHIVE,
+        "a.txt",
+    ))
+    .unwrap();
+    assert_eq!(record.major_version, 2);
+    assert_eq!(
+        record.file_reference(),
+        Some(FileReference {
+            record: 40,
+            sequence: 3
+        })
+    );
+    assert_eq!(record.parent_reference().unwrap().record, 5);
+    assert_eq!(record.usn, 1024);
+    assert_eq!(record.timestamp, Some(130_000_000_000_001_024));
+    assert_eq!(
+        record.reason,
+        UsnReason::BASIC_INFO_CHANGE | UsnReason::CLOSE
+    );
+    assert_eq!(record.security_id, Some(0x100));
+    assert_eq!(record.name.as_deref(), Some("a.txt"));
+    assert_eq!(record.props(), Some(Props::from(ARCHIVE | HIDDEN)));
+
+    let file = 0x0123_4567_89AB_CDEF_0011_2233_4455_6677;
+    let record = UsnRecord::parse(&v3(file, 0x100, DIRECTORY | SYSTEM, "Logs")).unwrap();
+    assert_eq!(record.file, file);
+    assert_eq!(record.file_reference(), None);
+    assert_eq!(record.source_info, 2);
+    assert_eq!(record.props(), Some(Props::from(DIRECTORY | SYSTEM)));
+
+    let record = UsnRecord::parse(&v4(file, &[(0, 4096), (65536, 512)])).unwrap();
+    assert_eq!(record.props(), None);
+    assert_eq!(record.name, None);
+    assert_eq!(
+        record.extents,
+        [
+            UsnExtent {
+                offset: 0,
+                length: 4096
+            },
+            UsnExtent {
+                offset: 65536,
+                length: 512
+            }
+        ]
+    );
+
+    let mut future = v2(40, 0, 0, 0, "a");
+    future[4] = 5;
+    assert!(matches!(
+        UsnRecord::parse(&future),
+        Err(Error {
+            kind: ErrorKind::NotSupported(_)
+        })
+    ));
+    let mut short = v2(40, 0, 0, 0, "a");
+    short[..4].copy_from_slice(&200u32.to_le_bytes());
+    assert!(UsnRecord::parse(&short).is_err());
+}
+#[test]
+fn journal() {
+    // A freed part, then records padded to the end of the page
+    let mut journal = vec![0; 8192];
+    journal.extend(v2(40, 8192, 0x100, ARCHIVE, "a.txt"));
+    journal.extend(v2(40, 8264, 0x8000, ARCHIVE | HIDDEN, "a.txt"));
+    journal.resize(12288, 0);
+    journal.extend(v4(1, &[(0, 1)]));
+    // Garbage the reader steps over
+    journal.extend(0xFFFF_0001u32.to_le_bytes());
+    journal.extend([0; 4]);
+    journal.extend(v2(40, 12368, 0x8000, ARCHIVE | HIDDEN | SYSTEM, "a.txt"));
+    journal.extend(v2(40, 12440, 0x8000, SYSTEM, "a.txt"));
+    journal.extend(v2(40, 12512, 0x200, SYSTEM, "a.txt"));
+    journal.extend(v2(40, 12584, 0x100, HIDDEN, "a.txt"));
+    // Cut in the middle
+    journal.extend(&v2(41, 12656, 0x100, 0, "b.txt")[..40]);
+
+    let records: Vec<Result<UsnRecord>> = UsnJournal::new(Cursor::new(journal)).collect();
+    assert_eq!(records.len(), 9);
+    assert!(records[3].is_err());
+    assert!(matches!(
+        records[8],
+        Err(Error {
+            kind: ErrorKind::InvalidData(_)
+        })
+    ));
+    let records: Vec<UsnRecord> = records
+        .into_iter()
+        .filter_map(|record| record.ok())
+        .collect();
+    let usns: Vec<i64> = records.iter().map(|record| record.usn).collect();
+    assert_eq!(usns, [8192, 8264, 8192, 12368, 12440, 12512, 12584]);
+
+    let mut tracker = AttributeTracker::default();
+    let changes: Vec<AttributeChange> = records
+        .iter()
+        .filter_map(|record| tracker.update(record))
+        .collect();
+    assert_eq!(changes.len(), 3);
+    assert_eq!(changes[0].usn, 8264);
+    assert_eq!(changes[0].set, HIDDEN);
+    assert_eq!(changes[0].cleared, 0);
+    assert_eq!(changes[0].name, "a.txt");
+    assert_eq!(changes[1].set, SYSTEM);
+    assert_eq!(changes[2].usn, 12440);
+    assert_eq!(changes[2].cleared, ARCHIVE | HIDDEN);
+    assert_eq!(changes[2].props, Props::from(ARCHIVE | SYSTEM));
+}
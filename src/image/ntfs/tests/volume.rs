@@ -0,0 +1,337 @@
+use std::io::Cursor;
+
+use super::mft::{file_name, record, resident, standard_information};
+use crate::error::*;
+use crate::image::ntfs::mft::*;
+use crate::image::ntfs::volume::*;
+use crate::image::ntfs::FileReference;
+use crate::props::constants::*;
+use crate::Props;
+
+const CLUSTER: usize = 512;
+
+fn boot_sector() -> Vec<u8> {
+    let mut boot = vec![0; 512];
+    boot[3..11].copy_from_slice(b"NTFS    ");
+    boot[11..13].copy_from_slice(&512u16.to_le_bytes());
+    boot[13] = 1;
+    boot[48..56].copy_from_slice(&16u64.to_le_bytes());
+    // 1024 byte records and one cluster index buffers
+    boot[64] = 0xF6;
+    boot[68] = 1;
+    boot[72..80].copy_from_slice(&0xABCDu64.to_le_bytes());
+    boot[510] = 0x55;
+    boot[511] = 0xAA;
+    boot
+}
+/// The unnamed non-resident `$DATA` of the `$MFT`, in two runs
+fn mft_data(size: u64) -> Vec<u8> {
+    let mut attribute = DATA.to_le_bytes().to_vec();
+    attribute.extend(80u32.to_le_bytes());
+    attribute.push(1);
+    attribute.resize(16, 0);
+    attribute.extend(0u64.to_le_bytes());
+    attribute.extend(23u64.to_le_bytes());
+    attribute.extend(64u16.to_le_bytes());
+    attribute.resize(40, 0);
+    for _ in 0..3 {
+        attribute.extend(size.to_le_bytes());
+    }
+    // 8 clusters at 16, then 16 clusters at 40
+    attribute.extend([0x11, 0x08, 0x10, 0x11, 0x10, 0x18, 0]);
+    attribute.resize(80, 0);
+    attribute
+}
+/// The records of a small volume, with a deleted directory and an orphan
+fn records() -> Vec<Vec<u8>> {
+    let in_use = RECORD_IN_USE;
+    let directory = RECORD_IN_USE | RECORD_IS_DIRECTORY;
+    let root = 5 | 7 << 48;
+    let users = 6 | 7 << 48;
+    let mut records = vec![Vec::new(); 12];
+    records[0] = record(
+        0,
+        in_use,
+        0,
+        &[
+            standard_information(HIDDEN | SYSTEM, 1, 1),
+            file_name(root, "$MFT", 3, HIDDEN | SYSTEM, 1),
+            mft_data(12 * 1024),
+        ],
+    );
+    // The long name of record 7, in an extension record
+    records[3] = record(
+        3,
+        in_use,
+        7 | 7 << 48,
+        &[file_name(users, "notes.txt", 1, ARCHIVE, 1)],
+    );
+    records[5] = record(
+        5,
+        directory,
+        0,
+        &[
+            standard_information(HIDDEN | SYSTEM, 1, 1),
+            file_name(root, ".", 3, HIDDEN | SYSTEM, 1),
+        ],
+    );
+    records[6] = record(
+        6,
+        directory,
+        0,
+        &[
+            standard_information(READ_ONLY, 1, 1),
+            file_name(root, "Users", 3, READ_ONLY | FILE_NAME_INDEX_PRESENT, 1),
+        ],
+    );
+    let mut list = Vec::new();
+    for (type_code, reference) in [(STANDARD_INFORMATION, 7u64), (FILE_NAME, 7), (FILE_NAME, 3)] {
+        list.extend(type_code.to_le_bytes());
+        list.extend(32u16.to_le_bytes());
+        list.extend([0, 26]);
+        list.extend(0u64.to_le_bytes());
+        list.extend((reference | 7 << 48).to_le_bytes());
+        list.extend([0; 8]);
+    }
+    records[7] = record(
+        7,
+        in_use,
+        0,
+        &[
+            standard_information(ARCHIVE | HIDDEN, 1, 1),
+            resident(ATTRIBUTE_LIST, &list),
+            file_name(users, "NOTES~1.TXT", 2, ARCHIVE, 1),
+        ],
+    );
+    records[8] = record(
+        8,
+        0,
+        0,
+        &[
+            standard_information(ARCHIVE, 1, 1),
+            file_name(users, "old.doc", 3, ARCHIVE, 1),
+        ],
+    );
+    // Freeing the record of the directory incremented its sequence number
+    records[9] = record(
+        9,
+        RECORD_IS_DIRECTORY,
+        0,
+        &[
+            standard_information(0, 1, 1),
+            file_name(root, "Temp", 3, FILE_NAME_INDEX_PRESENT, 1),
+        ],
+    );
+    records[9][16..18].copy_from_slice(&3u16.to_le_bytes());
+    records[10] = record(
+        10,
+        0,
+        0,
+        &[
+            standard_information(TEMPORARY, 1, 1),
+            file_name(9 | 2 << 48, "x.tmp", 3, TEMPORARY, 1),
+        ],
+    );
+    // Its directory was record 6 before Users reused it
+    records[11] = record(
+        11,
+        in_use,
+        0,
+        &[file_name(
+            6 | 6 << 48,
+            "lost.txt",
+            3,
+            ARCHIVE | READ_ONLY,
+            1,
+        )],
+    );
+    for bytes in &mut records {
+        bytes.resize(1024, 0);
+    }
+    records
+}
+/// A volume with the records 0 to 3 at cluster 16 and 4 to 11 at cluster 40
+fn volume() -> Vec<u8> {
+    let records = records().concat();
+    let mut image = boot_sector();
+    image.resize(56 * CLUSTER, 0);
+    image[16 * CLUSTER..24 * CLUSTER].copy_from_slice(&records[..4 * 1024]);
+    image[40 * CLUSTER..56 * CLUSTER].copy_from_slice(&records[4 * 1024..]);
+    image
+}
+
+#[test]
+fn boot_sectors() {
+    let boot = BootSector::parse(&boot_sector()).unwrap();
+    assert_eq!(
+        boot,
+        BootSector {
+            bytes_per_sector: 512,
+            cluster_size: 512,
+            mft_cluster: 16,
+            record_size: 1024,
+            index_record_size: 512,
+            serial_number: 0xABCD,
+        }
+    );
+    let mut large = boot_sector();
+    // 2 MiB clusters
+    large[13] = 0xEB;
+    assert_eq!(BootSector::parse(&large).unwrap().cluster_size, 1 << 21);
+    let mut fat = boot_sector();
+    fat[3..11].copy_from_slice(b"MSDOS5.0");
+    assert!(BootSector::parse(&fat).is_err());
+    let mut small = boot_sector();
+    small[64] = 0xF8;
+    assert!(BootSector::parse(&small).is_err());
+}
+#[test]
+fn data_runs() {
+    let runs = parse_data_runs(&[
+        0x21, 0x10, 0x00, 0x01, 0x11, 0x08, 0xF0, 0x01, 0x04, 0x11, 0x02, 0x20, 0,
+    ])
+    .unwrap();
+    assert_eq!(
+        runs,
+        [
+            DataRun {
+                length: 16,
+                lcn: Some(256)
+            },
+            DataRun {
+                length: 8,
+                lcn: Some(240)
+            },
+            DataRun {
+                length: 4,
+                lcn: None
+            },
+            DataRun {
+                length: 2,
+                lcn: Some(272)
+            },
+        ]
+    );
+    // Before the start of the volume
+    assert!(parse_data_runs(&[0x11, 0x01, 0xF0, 0]).is_err());
+    assert!(parse_data_runs(&[0x21, 0x01, 0x00]).is_err());
+}
+#[test]
+fn volume_entries() {
+    let mut mft = Mft::open_volume(Cursor::new(volume())).unwrap();
+    let entries = mft.entries().unwrap();
+    let paths: Vec<(&str, bool, bool)> = entries
+        .iter()
+        .map(|entry| (entry.path.as_str(), entry.in_use, entry.orphan))
+        .collect();
+    assert_eq!(
+        paths,
+        [
+            ("$MFT", true, false),
+            ("Users", true, false),
+            ("Users/notes.txt", true, false),
+            ("Users/old.doc", false, false),
+            ("Temp", false, false),
+            ("Temp/x.tmp", false, false),
+            ("$OrphanFiles/lost.txt", true, true),
+        ]
+    );
+    assert_eq!(
+        entries[2].reference,
+        FileReference {
+            record: 7,
+            sequence: 7
+        }
+    );
+    assert_eq!(entries[2].name, "notes.txt");
+    assert_eq!(entries[2].props, Props::from(ARCHIVE | HIDDEN));
+    assert_eq!(entries[1].props, Props::from(DIRECTORY | READ_ONLY));
+    assert_eq!(entries[4].props, Props::from(DIRECTORY));
+    // Without $STANDARD_INFORMATION
+    assert_eq!(entries[6].props, Props::from(ARCHIVE | READ_ONLY));
+    assert_eq!(entries[6].parent.sequence, 6);
+}
+#[test]
+fn raw_mft() {
+    let boot = BootSector::parse(&boot_sector()).unwrap();
+    let mut mft = Mft::new(Cursor::new(records().concat()))
+        .boot_sector(&boot)
+        .unwrap();
+    let mut volume = Mft::open_volume(Cursor::new(volume())).unwrap();
+    assert_eq!(mft.entries().unwrap(), volume.entries().unwrap());
+    assert_eq!(volume.record(11).unwrap().number, Some(11));
+    assert!(Mft::open_volume(Cursor::new(vec![0; 4096])).is_err());
+}
+#[test]
+fn crafted_volumes() {
+    let find = |image: &[u8], pattern: &[u8]| {
+        image
+            .windows(pattern.len())
+            .position(|window| window == pattern)
+            .unwrap()
+    };
+    let invalid = |image: Vec<u8>| {
+        matches!(
+            Mft::open_volume(Cursor::new(image)),
+            Err(Error {
+                kind: ErrorKind::InvalidData(_)
+            })
+        )
+    };
+    let mut image = volume();
+    image[48..56].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert!(invalid(image));
+    // A run at a cluster whose offset doesn't fit in 64 bits
+    let mut image = volume();
+    let runs = find(&image, &[0x11, 0x08, 0x10, 0x11, 0x10, 0x18, 0]);
+    image[runs..runs + 11].copy_from_slice(&[
+        0x81, 0x08, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x7F, 0,
+    ]);
+    assert!(invalid(image));
+    // A $DATA far bigger than the volume only gives the records in it
+    let mut image = volume();
+    let sizes = find(&image, &[12 * 1024u64; 3].map(u64::to_le_bytes).concat());
+    image[sizes..sizes + 24].copy_from_slice(&[u64::MAX / 2; 3].map(u64::to_le_bytes).concat());
+    let mut mft = Mft::open_volume(Cursor::new(image)).unwrap();
+    let mut volume = Mft::open_volume(Cursor::new(volume())).unwrap();
+    assert_eq!(mft.entries().unwrap(), volume.entries().unwrap());
+}
+#[test]
+fn parent_cycles() {
+    let directory = RECORD_IN_USE | RECORD_IS_DIRECTORY;
+    let mut records = records();
+    // Users and Temp are each other's parent, so the loop is cut where it repeats
+    for (number, parent, name) in [(6, 9, "Users"), (9, 6, "Temp")] {
+        records[number] = record(
+            number as u32,
+            directory,
+            0,
+            &[
+                standard_information(0, 1, 1),
+                file_name(parent | 7 << 48, name, 3, FILE_NAME_INDEX_PRESENT, 1),
+            ],
+        );
+        records[number].resize(1024, 0);
+    }
+    let boot = BootSector::parse(&boot_sector()).unwrap();
+    let mut mft = Mft::new(Cursor::new(records.concat()))
+        .boot_sector(&boot)
+        .unwrap();
+    let entries = mft.entries().unwrap();
+    let paths: Vec<(&str, bool)> = entries
+        .iter()
+        .map(|entry| (entry.path.as_str(), entry.orphan))
+        .collect();
+    assert_eq!(
+        paths,
+        [
+            ("$MFT", false),
+            ("$OrphanFiles/Users/Temp/Users", true),
+            ("$OrphanFiles/Users/notes.txt", true),
+            ("$OrphanFiles/Users/old.doc", true),
+            ("$OrphanFiles/Users/Temp", true),
+            ("$OrphanFiles/x.tmp", true),
+            ("$OrphanFiles/lost.txt", true),
+        ]
+    );
+}
@@ -0,0 +1,458 @@
+//! Parses the change journal of NTFS and ReFS, the `$J` stream of `$Extend\$UsnJrnl`, or the
+//! output of `FSCTL_READ_USN_JOURNAL`. Every change to an element appends a record with why it
+//! changed and, in the version 2 and 3 records, the attributes it had then, so following the
+//! records of an element tells when an attribute like hidden or system was set or cleared.
+//!
+//! The `$J` stream is sparse, with the old records freed to zeros, and the records are padded
+//! with zeros to not cross the 4096 byte pages, so the zeros are skipped.
+//! ```no_run
+//! use keypropdecode::image::ntfs::usn::{AttributeTracker, UsnJournal};
+//!
+//! let journal = std::fs::File::open("$J").unwrap();
+//! let mut tracker = AttributeTracker::default();
+//! for record in UsnJournal::new(std::io::BufReader::new(journal)) {
+//!     if let Some(change) = tracker.update(&record.unwrap()) {
+//!         println!("{} {:#x} +{:#x} -{:#x}", change.usn, change.file, change.set, change.cleared);
+//!     }
+//! }
+//! ```
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::Read;
+use std::ops::{BitAnd, BitOr, BitOrAssign};
+
+use super::{utf16_at, FileReference};
+use crate::archive::io_error;
+use crate::error::*;
+use crate::image::invalid;
+use crate::reader::Reader;
+use crate::Props;
+
+const WHAT: &str = "USN record";
+/// The longest record, one with a name of 255 characters, rounded up a lot
+const MAX_RECORD_LENGTH: usize = 4096;
+/// The bytes read at once by [`UsnJournal`]
+const BUFFER_SIZE: usize = 64 * 1024;
+
+/// The reasons of a change, the `USN_REASON_*` flags of a record
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub struct UsnReason(pub u32);
+impl UsnReason {
+    /// The unnamed data stream was overwritten
+    pub const DATA_OVERWRITE: UsnReason = UsnReason(0x0000_0001);
+    /// Data was added to the unnamed data stream
+    pub const DATA_EXTEND: UsnReason = UsnReason(0x0000_0002);
+    /// The unnamed data stream was truncated
+    pub const DATA_TRUNCATION: UsnReason = UsnReason(0x0000_0004);
+    /// A named data stream was overwritten
+    pub const NAMED_DATA_OVERWRITE: UsnReason = UsnReason(0x0000_0010);
+    /// Data was added to a named data stream
+    pub const NAMED_DATA_EXTEND: UsnReason = UsnReason(0x0000_0020);
+    /// A named data stream was truncated
+    pub const NAMED_DATA_TRUNCATION: UsnReason = UsnReason(0x0000_0040);
+    /// The element was created
+    pub const FILE_CREATE: UsnReason = UsnReason(0x0000_0100);
+    /// The element was deleted
+    pub const FILE_DELETE: UsnReason = UsnReason(0x0000_0200);
+    /// The extended attributes changed
+    pub const EA_CHANGE: UsnReason = UsnReason(0x0000_0400);
+    /// The security descriptor changed
+    pub const SECURITY_CHANGE: UsnReason = UsnReason(0x0000_0800);
+    /// The element was renamed, and the record has the old name
+    pub const RENAME_OLD_NAME: UsnReason = UsnReason(0x0000_1000);
+    /// The element was renamed, and the record has the new name
+    pub const RENAME_NEW_NAME: UsnReason = UsnReason(0x0000_2000);
+    /// The not content indexed attribute changed
+    pub const INDEXABLE_CHANGE: UsnReason = UsnReason(0x0000_4000);
+    /// The attributes or the times changed
+    pub const BASIC_INFO_CHANGE: UsnReason = UsnReason(0x0000_8000);
+    /// A hard link was added or removed
+    pub const HARD_LINK_CHANGE: UsnReason = UsnReason(0x0001_0000);
+    /// The element was compressed or decompressed
+    pub const COMPRESSION_CHANGE: UsnReason = UsnReason(0x0002_0000);
+    /// The element was encrypted or decrypted
+    pub const ENCRYPTION_CHANGE: UsnReason = UsnReason(0x0004_0000);
+    /// The object identifier changed
+    pub const OBJECT_ID_CHANGE: UsnReason = UsnReason(0x0008_0000);
+    /// The reparse point was added, changed or removed
+    pub const REPARSE_POINT_CHANGE: UsnReason = UsnReason(0x0010_0000);
+    /// A named data stream was added, renamed or removed
+    pub const STREAM_CHANGE: UsnReason = UsnReason(0x0020_0000);
+    /// A transaction changed the unnamed data stream
+    pub const TRANSACTED_CHANGE: UsnReason = UsnReason(0x0040_0000);
+    /// The integrity attribute changed
+    pub const INTEGRITY_CHANGE: UsnReason = UsnReason(0x0080_0000);
+    /// The storage class the element should be in changed
+    pub const DESIRED_STORAGE_CLASS_CHANGE: UsnReason = UsnReason(0x0100_0000);
+    /// The element was closed, which ends the changes of the record
+    pub const CLOSE: UsnReason = UsnReason(0x8000_0000);
+
+    /// The name of every flag, without the `USN_REASON_` prefix
+    pub const NAMES: [(&'static str, UsnReason); 24] = [
+        ("DATA_OVERWRITE", UsnReason::DATA_OVERWRITE),
+        ("DATA_EXTEND", UsnReason::DATA_EXTEND),
+        ("DATA_TRUNCATION", UsnReason::DATA_TRUNCATION),
+        ("NAMED_DATA_OVERWRITE", UsnReason::NAMED_DATA_OVERWRITE),
+        ("NAMED_DATA_EXTEND", UsnReason::NAMED_DATA_EXTEND),
+        ("NAMED_DATA_TRUNCATION", UsnReason::NAMED_DATA_TRUNCATION),
+        ("FILE_CREATE", UsnReason::FILE_CREATE),
+        ("FILE_DELETE", UsnReason::FILE_DELETE),
+        ("EA_CHANGE", UsnReason::EA_CHANGE),
+        ("SECURITY_CHANGE", UsnReason::SECURITY_CHANGE),
+        ("RENAME_OLD_NAME", UsnReason::RENAME_OLD_NAME),
+        ("RENAME_NEW_NAME", UsnReason::RENAME_NEW_NAME),
+        ("INDEXABLE_CHANGE", UsnReason::INDEXABLE_CHANGE),
+        ("BASIC_INFO_CHANGE", UsnReason::BASIC_INFO_CHANGE),
+        ("HARD_LINK_CHANGE", UsnReason::HARD_LINK_CHANGE),
+        ("COMPRESSION_CHANGE", UsnReason::COMPRESSION_CHANGE),
+        ("ENCRYPTION_CHANGE", UsnReason::ENCRYPTION_CHANGE),
+        ("OBJECT_ID_CHANGE", UsnReason::OBJECT_ID_CHANGE),
+        ("REPARSE_POINT_CHANGE", UsnReason::REPARSE_POINT_CHANGE),
+        ("STREAM_CHANGE", UsnReason::STREAM_CHANGE),
+        ("TRANSACTED_CHANGE", UsnReason::TRANSACTED_CHANGE),
+        ("INTEGRITY_CHANGE", UsnReason::INTEGRITY_CHANGE),
+        (
+            "DESIRED_STORAGE_CLASS_CHANGE",
+            UsnReason::DESIRED_STORAGE_CLASS_CHANGE,
+        ),
+        ("CLOSE", UsnReason::CLOSE),
+    ];
+
+    /// Returns true if every flag of `other` is set
+    pub fn contains(self, other: UsnReason) -> bool {
+        self.0 & other.0 == other.0
+    }
+    /// Gets the names of the flags that are set, in the order of their values
+    pub fn names(self) -> impl Iterator<Item = &'static str> {
+        UsnReason::NAMES
+            .into_iter()
+            .filter(move |(_, flag)| self.contains(*flag))
+            .map(|(name, _)| name)
+    }
+}
+impl From<u32> for UsnReason {
+    fn from(reason: u32) -> Self {
+        UsnReason(reason)
+    }
+}
+impl From<UsnReason> for u32 {
+    fn from(reason: UsnReason) -> Self {
+        reason.0
+    }
+}
+impl BitOr for UsnReason {
+    type Output = UsnReason;
+    fn bitor(self, other: UsnReason) -> UsnReason {
+        UsnReason(self.0 | other.0)
+    }
+}
+impl BitOrAssign for UsnReason {
+    fn bitor_assign(&mut self, other: UsnReason) {
+        self.0 |= other.0;
+    }
+}
+impl BitAnd for UsnReason {
+    type Output = UsnReason;
+    fn bitand(self, other: UsnReason) -> UsnReason {
+        UsnReason(self.0 & other.0)
+    }
+}
+/// Writes the names of the flags joined with ` | `, like `BASIC_INFO_CHANGE | CLOSE`, followed
+/// by the unknown ones in hexadecimal
+impl Display for UsnReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut names: Vec<String> = self.names().map(str::to_string).collect();
+        let known = UsnReason::NAMES
+            .iter()
+            .fold(0, |known, (_, flag)| known | flag.0);
+        if self.0 & !known != 0 {
+            names.push(format!("{:#x}", self.0 & !known));
+        }
+        match names.is_empty() {
+            true => write!(f, "0"),
+            false => write!(f, "{}", names.join(" | ")),
+        }
+    }
+}
+
+/// A range of the data that changed, from version 4 records
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct UsnExtent {
+    /// Where the range starts in the data
+    pub offset: i64,
+    /// The length of the range
+    pub length: i64,
+}
+
+/// A record of the journal. Version 4 records, which ReFS writes for the ranges that changed,
+/// have no name, time or attributes.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct UsnRecord {
+    /// The version of the record: 2, 3 or 4
+    pub major_version: u16,
+    /// The minor version of the record
+    pub minor_version: u16,
+    /// The file reference number of the element, 64 bits long in version 2 records and 128 in
+    /// the others
+    pub file: u128,
+    /// The file reference number of the directory the element is in
+    pub parent: u128,
+    /// The update sequence number, which is where the record is in `$J`
+    pub usn: i64,
+    /// When the change happened, as a FILETIME value
+    pub timestamp: Option<u64>,
+    /// Why the element changed
+    pub reason: UsnReason,
+    /// The `USN_SOURCE_*` flags, set when the change wasn't made by the user
+    pub source_info: u32,
+    /// The identifier of the security descriptor of the element
+    pub security_id: Option<u32>,
+    /// The attribute word of the element after the change
+    pub attributes: Option<u32>,
+    /// The name of the element, without the path
+    pub name: Option<String>,
+    /// The ranges that changed, only in version 4 records
+    pub extents: Vec<UsnExtent>,
+}
+impl UsnRecord {
+    /// Parses the record at the start of `bytes`
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes, WHAT);
+        let length = reader.u32()? as usize;
+        let major_version = reader.u16()?;
+        let minor_version = reader.u16()?;
+        if length < 8 || length > bytes.len() {
+            return Err(invalid("The USN record has an invalid length"));
+        }
+        let bytes = &bytes[..length];
+        let mut reader = Reader::new(bytes, WHAT);
+        reader.skip(8)?;
+        let (file, parent) = match major_version {
+            2 => (reader.u64()? as u128, reader.u64()? as u128),
+            3 | 4 => (u128_from(&mut reader)?, u128_from(&mut reader)?),
+            _ => {
+                return Err(Error {
+                    kind: ErrorKind::NotSupported(format!(
+                        "USN records of version {} aren't supported",
+                        major_version
+                    )),
+                })
+            }
+        };
+        let usn = reader.u64()? as i64;
+        let mut record = UsnRecord {
+            major_version,
+            minor_version,
+            file,
+            parent,
+            usn,
+            timestamp: None,
+            reason: UsnReason(0),
+            source_info: 0,
+            security_id: None,
+            attributes: None,
+            name: None,
+            extents: Vec::new(),
+        };
+        if major_version == 4 {
+            record.reason = UsnReason(reader.u32()?);
+            record.source_info = reader.u32()?;
+            // The extents left for the next records
+            reader.skip(4)?;
+            let count = reader.u16()? as usize;
+            let size = reader.u16()? as usize;
+            if size < 16 {
+                return Err(invalid("The USN record has an invalid extent size"));
+            }
+            for _ in 0..count {
+                let extent = reader.take(size)?;
+                let mut extent = Reader::new(extent, WHAT);
+                record.extents.push(UsnExtent {
+                    offset: extent.u64()? as i64,
+                    length: extent.u64()? as i64,
+                });
+            }
+            return Ok(record);
+        }
+        record.timestamp = Some(reader.u64()?);
+        record.reason = UsnReason(reader.u32()?);
+        record.source_info = reader.u32()?;
+        record.security_id = Some(reader.u32()?);
+        record.attributes = Some(reader.u32()?);
+        let name_length = reader.u16()? as usize;
+        let name_offset = reader.u16()? as usize;
+        if !name_length.is_multiple_of(2) {
+            return Err(invalid("The USN record has a name of an odd length"));
+        }
+        record.name = Some(utf16_at(bytes, name_offset, name_length / 2, WHAT)?);
+        Ok(record)
+    }
+    /// Gets the Props of the attributes after the change
+    pub fn props(&self) -> Option<Props> {
+        self.attributes.map(|attributes| {
+            Props::from(crate::backends::with_element_type(
+                attributes,
+                attributes & crate::props::constants::DIRECTORY != 0,
+            ))
+        })
+    }
+    /// Gets the MFT reference of the element, unless the file reference number is longer than
+    /// the 64 bits NTFS uses
+    pub fn file_reference(&self) -> Option<FileReference> {
+        u64::try_from(self.file).ok().map(FileReference::from)
+    }
+    /// Gets the MFT reference of the directory the element is in
+    pub fn parent_reference(&self) -> Option<FileReference> {
+        u64::try_from(self.parent).ok().map(FileReference::from)
+    }
+}
+fn u128_from(reader: &mut Reader) -> Result<u128> {
+    let low = reader.u64()? as u128;
+    let high = reader.u64()? as u128;
+    Ok(high << 64 | low)
+}
+
+/// Reads the records of a journal one after the other, without keeping them.
+/// A malformed record gives an error and the reading goes on 8 bytes later, where the next
+/// record could start.
+pub struct UsnJournal<R> {
+    reader: R,
+    buffer: Vec<u8>,
+    start: usize,
+    end: usize,
+    finished: bool,
+}
+impl<R: Read> UsnJournal<R> {
+    /// Reads the records from `reader`, which must start at the start of a record or of a page
+    pub fn new(reader: R) -> Self {
+        UsnJournal {
+            reader,
+            buffer: vec![0; BUFFER_SIZE],
+            start: 0,
+            end: 0,
+            finished: false,
+        }
+    }
+    /// Gets the reader back
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+    /// Reads until `count` bytes are in the buffer or the reader ends
+    fn fill(&mut self, count: usize) -> Result<()> {
+        if self.end - self.start >= count {
+            return Ok(());
+        }
+        self.buffer.copy_within(self.start..self.end, 0);
+        self.end -= self.start;
+        self.start = 0;
+        while self.end < count && !self.finished {
+            match self.reader.read(&mut self.buffer[self.end..]) {
+                Ok(0) => self.finished = true,
+                Ok(read) => self.end += read,
+                Err(error) if error.kind() == std::io::ErrorKind::Interrupted => (),
+                Err(error) => return Err(io_error(error)),
+            }
+        }
+        Ok(())
+    }
+}
+impl<R: Read> Iterator for UsnJournal<R> {
+    type Item = Result<UsnRecord>;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Err(error) = self.fill(8) {
+                self.finished = true;
+                self.start = self.end;
+                return Some(Err(error));
+            }
+            if self.end - self.start < 8 {
+                return None;
+            }
+            // The zeros of the freed parts and of the end of the pages
+            let zeros = self.buffer[self.start..self.end]
+                .chunks_exact(8)
+                .take_while(|chunk| chunk.iter().all(|byte| *byte == 0))
+                .count();
+            if zeros > 0 {
+                self.start += zeros * 8;
+                continue;
+            }
+            let bytes = &self.buffer[self.start..self.start + 4];
+            let length = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize;
+            if !(8..=MAX_RECORD_LENGTH).contains(&length) || !length.is_multiple_of(8) {
+                self.start += 8;
+                return Some(Err(invalid("The USN record has an invalid length")));
+            }
+            if let Err(error) = self.fill(length) {
+                self.finished = true;
+                self.start = self.end;
+                return Some(Err(error));
+            }
+            if self.end - self.start < length {
+                self.start = self.end;
+                return Some(Err(crate::archive::truncated(WHAT)));
+            }
+            let record = UsnRecord::parse(&self.buffer[self.start..self.start + length]);
+            // Records of other versions still have a valid length
+            self.start += match &record {
+                Ok(_)
+                | Err(Error {
+                    kind: ErrorKind::NotSupported(_),
+                }) => length,
+                Err(_) => 8,
+            };
+            return Some(record);
+        }
+    }
+}
+
+/// A change of the attributes of an element, found by [`AttributeTracker`]
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AttributeChange {
+    /// The file reference number of the element
+    pub file: u128,
+    /// The name of the element when it changed
+    pub name: String,
+    /// The update sequence number of the record with the new attributes
+    pub usn: i64,
+    /// When the change happened, as a FILETIME value
+    pub timestamp: u64,
+    /// The attributes that were set
+    pub set: u32,
+    /// The attributes that were cleared
+    pub cleared: u32,
+    /// The Props after the change
+    pub props: Props,
+}
+
+/// Follows the attributes of every element through the records of a journal, in order, to
+/// find when each of them changed
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct AttributeTracker {
+    attributes: HashMap<u128, u32>,
+}
+impl AttributeTracker {
+    /// Gives the next record to the tracker, getting the change if its attributes aren't the
+    /// ones of the last record of the element. The first record of every element only sets
+    /// what it had then. Deleting an element forgets it, since its number can be reused.
+    pub fn update(&mut self, record: &UsnRecord) -> Option<AttributeChange> {
+        let (Some(attributes), Some(timestamp), Some(props)) =
+            (record.attributes, record.timestamp, record.props())
+        else {
+            return None;
+        };
+        let previous = match record.reason.contains(UsnReason::FILE_DELETE) {
+            true => self.attributes.remove(&record.file),
+            false => self.attributes.insert(record.file, attributes),
+        }?;
+        (previous != attributes).then(|| AttributeChange {
+            file: record.file,
+            name: record.name.clone().unwrap_or_default(),
+            usn: record.usn,
+            timestamp,
+            set: attributes & !previous,
+            cleared: previous & !attributes,
+            props,
+        })
+    }
+}
@@ -0,0 +1,384 @@
+//! Reads a whole NTFS volume, from an image of it or from a copy of its `$MFT` with its boot
+//! sector, giving every element with its full path, rebuilt from the parent references of the
+//! `$FILE_NAME` attributes, whether it was deleted and its Props.
+//!
+//! The records are read in batches and the paths of the directories are built once and reused,
+//! so volumes with millions of elements only take one pass over the `$MFT`.
+//! ```no_run
+//! use keypropdecode::image::ntfs::mft::Mft;
+//!
+//! let image = std::fs::File::open("volume.img").unwrap();
+//! let mut mft = Mft::open_volume(image).unwrap();
+//! for entry in mft.entries().unwrap() {
+//!     let state = if entry.in_use { "" } else { " (deleted)" };
+//!     println!("{} {}{}", entry.props, entry.path, state);
+//! }
+//! ```
+//! ```no_run
+//! use keypropdecode::image::ntfs::{mft::Mft, volume::BootSector};
+//!
+//! let boot = BootSector::parse(&std::fs::read("boot.bin").unwrap()).unwrap();
+//! let mut mft = Mft::new(std::fs::File::open("$MFT").unwrap()).boot_sector(&boot).unwrap();
+//! let hidden = mft.entries().unwrap().into_iter().filter(|entry| entry.props.is_hidden());
+//! ```
+use std::collections::{HashMap, HashSet};
+use std::io::{Read, Seek};
+
+use super::mft::{
+    Extent, FileName, FileNameNamespace, Mft, MftRecord, NonResidentData, StandardInformation,
+    DATA, FILE_NAME_INDEX_PRESENT, FILE_NAME_VIEW_INDEX_PRESENT,
+};
+use super::FileReference;
+use crate::error::*;
+use crate::image::{invalid, read_at};
+use crate::Props;
+
+/// The record of the root directory
+pub const ROOT_RECORD: u64 = 5;
+/// The directory the elements whose parent is gone are put in, like The Sleuth Kit does
+pub const ORPHAN_DIRECTORY: &str = "$OrphanFiles";
+
+/// The fields of the boot sector of an NTFS volume needed to read it
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct BootSector {
+    /// The size of a sector, in bytes
+    pub bytes_per_sector: u64,
+    /// The size of a cluster, in bytes
+    pub cluster_size: u64,
+    /// The cluster the `$MFT` starts at
+    pub mft_cluster: u64,
+    /// The size of an MFT record, in bytes
+    pub record_size: usize,
+    /// The size of an index buffer, in bytes
+    pub index_record_size: usize,
+    /// The serial number of the volume
+    pub serial_number: u64,
+}
+impl BootSector {
+    /// Parses the first sector of the volume
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 512 || &bytes[3..11] != b"NTFS    " {
+            return Err(invalid("The volume doesn't have an NTFS boot sector"));
+        }
+        let bytes_per_sector = u16::from_le_bytes([bytes[11], bytes[12]]) as u64;
+        let cluster_size = match bytes[13] {
+            // Clusters of 128 KiB and more have the power of two, negated
+            sectors @ 0..=0x80 => sectors as u64 * bytes_per_sector,
+            exponent => 1u64.checked_shl(256 - exponent as u32).unwrap_or(0),
+        };
+        if !matches!(bytes_per_sector, 256..=4096) || !cluster_size.is_power_of_two() {
+            return Err(invalid("The boot sector of the volume is invalid"));
+        }
+        // Sizes in clusters, or the power of two of the size in bytes if negative
+        let size = |value: u8| match value as i8 {
+            clusters @ 1.. => Some(clusters as u64 * cluster_size),
+            exponent @ -31..=-1 => Some(1u64 << -exponent),
+            _ => None,
+        };
+        let u64_at = |offset: usize| {
+            let mut value = [0; 8];
+            value.copy_from_slice(&bytes[offset..offset + 8]);
+            u64::from_le_bytes(value)
+        };
+        let record_size = size(bytes[64]).filter(|size| *size >= 512);
+        let (Some(record_size), Some(index_record_size)) = (record_size, size(bytes[68])) else {
+            return Err(invalid(
+                "The boot sector of the volume has invalid record sizes",
+            ));
+        };
+        Ok(BootSector {
+            bytes_per_sector,
+            cluster_size,
+            mft_cluster: u64_at(48),
+            record_size: record_size as usize,
+            index_record_size: index_record_size as usize,
+            serial_number: u64_at(72),
+        })
+    }
+}
+
+/// An element of the volume
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct VolumeEntry {
+    /// The record of the element, with its sequence number
+    pub reference: FileReference,
+    /// The directory the element is in
+    pub parent: FileReference,
+    /// The path from the root, with `/` between the names. Elements whose directory is gone are
+    /// under [`ORPHAN_DIRECTORY`].
+    pub path: String,
+    /// The long name, or the only one the element has. Elements with hard links get one of
+    /// them.
+    pub name: String,
+    /// False when the element was deleted and its record is free
+    pub in_use: bool,
+    /// True when the directory the element was in is gone or its record was reused
+    pub orphan: bool,
+    /// The Props of `$STANDARD_INFORMATION`, or of the name if the record doesn't have it
+    pub props: Props,
+}
+
+/// What's kept of every record while the volume is read
+struct Node {
+    sequence: u16,
+    in_use: bool,
+    directory: bool,
+    name: Option<FileName>,
+    information: Option<StandardInformation>,
+}
+impl Node {
+    /// Keeps the best name: the long one over the DOS one
+    fn add_name(&mut self, file_name: FileName) {
+        let better = match &self.name {
+            None => true,
+            Some(name) => {
+                name.namespace == FileNameNamespace::Dos
+                    && file_name.namespace != FileNameNamespace::Dos
+            }
+        };
+        if better {
+            self.name = Some(file_name);
+        }
+    }
+}
+
+impl<R: Read + Seek> Mft<R> {
+    /// Reads the `$MFT` of the volume at the start of `reader`, following the data runs of its
+    /// own record, so a fragmented `$MFT` is read in order
+    pub fn open_volume(mut reader: R) -> Result<Self> {
+        let mut boot = [0; 512];
+        read_at(&mut reader, 0, &mut boot, "NTFS volume")?;
+        let boot = BootSector::parse(&boot)?;
+        let mut first = vec![0; boot.record_size];
+        let mft_offset = boot
+            .mft_cluster
+            .checked_mul(boot.cluster_size)
+            .ok_or_else(|| invalid("The $MFT of the volume starts past its end"))?;
+        read_at(&mut reader, mft_offset, &mut first, "NTFS volume")?;
+        let record = MftRecord::parse(&first)?;
+        let Some(data) = record.data else {
+            return Err(invalid("The $MFT record has no $DATA"));
+        };
+        let mut mft = Mft::new(reader).record_size(boot.record_size)?;
+        mft.length = Some(data.data_size);
+        mft.extents = Some(extents(std::slice::from_ref(&data), boot.cluster_size)?);
+        // A very fragmented $MFT has the rest of its data runs in extension records, which are
+        // found with the part already known
+        let pieces: Vec<u64> = record
+            .attribute_list
+            .iter()
+            .flatten()
+            .filter(|entry| entry.type_code == DATA && entry.name.is_empty())
+            .filter(|entry| entry.reference.record != 0)
+            .map(|entry| entry.reference.record)
+            .collect();
+        if !pieces.is_empty() {
+            let mut data = vec![data];
+            for piece in pieces {
+                if let Some(piece) = mft.record(piece)?.data {
+                    data.push(piece);
+                }
+            }
+            data.sort_by_key(|piece| piece.starting_vcn);
+            mft.extents = Some(extents(&data, boot.cluster_size)?);
+        }
+        Ok(mft)
+    }
+    /// Uses the record size of `boot`, for copies of the `$MFT` of a volume
+    pub fn boot_sector(self, boot: &BootSector) -> Result<Self> {
+        self.record_size(boot.record_size)
+    }
+    /// Gets every element of the volume, in the order of their records, except the root
+    /// directory itself.
+    /// The names in extension records are joined to their base records, and the records that
+    /// can't be parsed, like the ones chkdsk marked as bad, are skipped.
+    pub fn entries(&mut self) -> Result<Vec<VolumeEntry>> {
+        let count = self.count()?;
+        let mut nodes: Vec<Option<Node>> = Vec::new();
+        nodes.resize_with(count as usize, || None);
+        let mut extensions = Vec::new();
+        for record in self.records() {
+            let (number, record) = match record {
+                Ok(record) => record,
+                Err(Error {
+                    kind: ErrorKind::InvalidData(_),
+                }) => continue,
+                Err(error) => return Err(error),
+            };
+            if record.base_record.record != 0 {
+                extensions.push(record);
+                continue;
+            }
+            let mut node = Node {
+                sequence: record.sequence,
+                in_use: record.in_use(),
+                directory: record.is_directory(),
+                name: None,
+                information: record.standard_information,
+            };
+            for file_name in record.file_names {
+                node.add_name(file_name);
+            }
+            nodes[number as usize] = Some(node);
+        }
+        for extension in extensions {
+            let base = extension.base_record;
+            let Some(Some(node)) = nodes.get_mut(base.record as usize) else {
+                continue;
+            };
+            if node.sequence != base.sequence {
+                continue;
+            }
+            if node.information.is_none() {
+                node.information = extension.standard_information;
+            }
+            for file_name in extension.file_names {
+                node.add_name(file_name);
+            }
+        }
+
+        let mut paths = Paths {
+            nodes: &nodes,
+            directories: HashMap::new(),
+        };
+        let mut entries = Vec::new();
+        for (number, node) in nodes.iter().enumerate() {
+            let Some(
+                node @ Node {
+                    name: Some(name), ..
+                },
+            ) = node
+            else {
+                continue;
+            };
+            if number as u64 == ROOT_RECORD {
+                continue;
+            }
+            let (directory, orphan) = paths.directory(name.parent, node.in_use);
+            let attributes = match node.information {
+                Some(information) => information.attributes,
+                None => name.attributes,
+            };
+            entries.push(VolumeEntry {
+                reference: FileReference {
+                    record: number as u64,
+                    sequence: node.sequence,
+                },
+                parent: name.parent,
+                path: match directory.is_empty() {
+                    true => name.name.clone(),
+                    false => format!("{}/{}", directory, name.name),
+                },
+                name: name.name.clone(),
+                in_use: node.in_use,
+                orphan,
+                props: Props::from(crate::backends::with_element_type(
+                    attributes & !(FILE_NAME_INDEX_PRESENT | FILE_NAME_VIEW_INDEX_PRESENT),
+                    node.directory,
+                )),
+            });
+        }
+        Ok(entries)
+    }
+}
+
+/// Builds the paths of the directories, remembering them
+struct Paths<'n> {
+    nodes: &'n [Option<Node>],
+    directories: HashMap<u64, (String, bool)>,
+}
+impl Paths<'_> {
+    /// Gets the path of the directory `reference` points to, and whether it's an orphan.
+    fn directory(&mut self, reference: FileReference, child_in_use: bool) -> (String, bool) {
+        if reference.record == ROOT_RECORD {
+            return (String::new(), false);
+        }
+        if !self.is_parent(reference, child_in_use) {
+            return (ORPHAN_DIRECTORY.to_string(), true);
+        }
+        if let Some(path) = self.directories.get(&reference.record) {
+            return path.clone();
+        }
+        // The chain of parents up to the root or one whose path is known
+        let mut chain = vec![reference.record];
+        // A corrupt MFT can have parents that loop, which make the chain orphaned
+        let mut visited = HashSet::from([reference.record]);
+        let mut known = (String::new(), false);
+        let mut current = reference.record;
+        loop {
+            let node = self.nodes[current as usize].as_ref();
+            let Some(name) = node.and_then(|node| node.name.as_ref()) else {
+                known = (ORPHAN_DIRECTORY.to_string(), true);
+                break;
+            };
+            let parent = name.parent;
+            let in_use = node.is_some_and(|node| node.in_use);
+            if parent.record == ROOT_RECORD {
+                break;
+            }
+            if !self.is_parent(parent, in_use) || visited.contains(&parent.record) {
+                known = (ORPHAN_DIRECTORY.to_string(), true);
+                break;
+            }
+            if let Some(path) = self.directories.get(&parent.record) {
+                known = path.clone();
+                break;
+            }
+            chain.push(parent.record);
+            visited.insert(parent.record);
+            current = parent.record;
+        }
+        let (mut path, orphan) = known;
+        for record in chain.into_iter().rev() {
+            let name = self.nodes[record as usize]
+                .as_ref()
+                .and_then(|node| node.name.as_ref())
+                .map_or("", |name| name.name.as_str());
+            if !path.is_empty() {
+                path.push('/');
+            }
+            path.push_str(name);
+            self.directories.insert(record, (path.clone(), orphan));
+        }
+        (path, orphan)
+    }
+    /// Returns true if `reference` still points to the directory the child was in.
+    /// A deleted child can point to a deleted directory whose sequence number went up by one
+    /// when its record was freed.
+    fn is_parent(&self, reference: FileReference, child_in_use: bool) -> bool {
+        let Some(Some(node)) = self.nodes.get(reference.record as usize) else {
+            return false;
+        };
+        node.directory
+            && (node.sequence == reference.sequence
+                || (!child_in_use
+                    && !node.in_use
+                    && node.sequence == reference.sequence.wrapping_add(1)))
+    }
+}
+
+/// Turns the data runs of the pieces of the `$DATA` of the `$MFT` into extents of the file
+fn extents(pieces: &[NonResidentData], cluster_size: u64) -> Result<Vec<Extent>> {
+    let too_big = || invalid("A data run of the $MFT goes past the end of the volume");
+    let mut extents = Vec::new();
+    for piece in pieces {
+        let mut offset = piece
+            .starting_vcn
+            .checked_mul(cluster_size)
+            .ok_or_else(too_big)?;
+        for run in &piece.runs {
+            let length = run.length.checked_mul(cluster_size).ok_or_else(too_big)?;
+            let volume_offset = match run.lcn {
+                Some(lcn) => Some(lcn.checked_mul(cluster_size).ok_or_else(too_big)?),
+                None => None,
+            };
+            extents.push(Extent {
+                offset,
+                length,
+                volume_offset,
+            });
+            offset = offset.checked_add(length).ok_or_else(too_big)?;
+        }
+    }
+    Ok(extents)
+}
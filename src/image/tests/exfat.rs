@@ -0,0 +1,220 @@
+use std::io::Cursor;
+
+use crate::error::*;
+use crate::image::exfat::*;
+use crate::props::constants::*;
+use crate::Props;
+
+const CLUSTER: usize = 512;
+const FAT: usize = 24 * 512;
+const HEAP: usize = 32 * 512;
+
+fn boot_sector() -> Vec<u8> {
+    let mut boot = vec![0; 512];
+    boot[3..11].copy_from_slice(b"EXFAT   ");
+    boot[80..84].copy_from_slice(&24u32.to_le_bytes());
+    boot[84..88].copy_from_slice(&1u32.to_le_bytes());
+    boot[88..92].copy_from_slice(&32u32.to_le_bytes());
+    boot[92..96].copy_from_slice(&100u32.to_le_bytes());
+    boot[96..100].copy_from_slice(&2u32.to_le_bytes());
+    // 512 byte sectors and one sector clusters
+    boot[108] = 9;
+    boot[109] = 0;
+    boot[110] = 1;
+    boot[510] = 0x55;
+    boot[511] = 0xAA;
+    boot
+}
+/// The entries of the set of a file, with its checksum
+fn entry_set(
+    name: &str,
+    attributes: u16,
+    first_cluster: u32,
+    length: u64,
+    contiguous: bool,
+) -> Vec<u8> {
+    let name: Vec<u16> = name.encode_utf16().collect();
+    let name_entries = name.len().div_ceil(15);
+    let mut set = vec![0x85, 1 + name_entries as u8, 0, 0];
+    set.extend(attributes.to_le_bytes());
+    set.resize(32, 0);
+    let mut stream = vec![
+        0xC0,
+        0x01 | if contiguous { 0x02 } else { 0 },
+        0,
+        name.len() as u8,
+    ];
+    stream.resize(8, 0);
+    stream.extend(length.to_le_bytes());
+    stream.extend([0; 4]);
+    stream.extend(first_cluster.to_le_bytes());
+    stream.extend(length.to_le_bytes());
+    set.extend(stream);
+    for characters in name.chunks(15) {
+        let mut entry = vec![0xC1, 0];
+        entry.extend(
+            characters
+                .iter()
+                .flat_map(|character| character.to_le_bytes()),
+        );
+        entry.resize(32, 0);
+        set.extend(entry);
+    }
+    let checksum = set
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !matches!(index, 2 | 3))
+        .fold(0u16, |sum, (_, byte)| {
+            sum.rotate_right(1).wrapping_add(*byte as u16)
+        });
+    set[2..4].copy_from_slice(&checksum.to_le_bytes());
+    set
+}
+fn put(image: &mut [u8], offset: usize, bytes: &[u8]) {
+    image[offset..offset + bytes.len()].copy_from_slice(bytes);
+}
+fn cluster(number: usize) -> usize {
+    HEAP + (number - 2) * CLUSTER
+}
+/// A card with the root in clusters 2 and 5, DCIM in the contiguous cluster 3 and DCIM/100CANON
+/// in cluster 4
+fn card() -> Vec<u8> {
+    let mut image = boot_sector();
+    image.resize(HEAP + 10 * CLUSTER, 0);
+    for (number, next) in [(2, 5u32), (4, 0xFFFF_FFFF), (5, 0xFFFF_FFFF)] {
+        put(&mut image, FAT + number * 4, &next.to_le_bytes());
+    }
+
+    let mut root = Vec::new();
+    let mut label = vec![0x83, 6];
+    label.extend(
+        "CAMERA"
+            .encode_utf16()
+            .flat_map(|character| character.to_le_bytes()),
+    );
+    label.resize(32, 0);
+    root.extend(label);
+    // The allocation bitmap, which isn't a file
+    let mut bitmap = vec![0x81, 0];
+    bitmap.resize(32, 0);
+    root.extend(bitmap);
+    root.extend(entry_set("DCIM", 0x10, 3, CLUSTER as u64, true));
+    let mut deleted = entry_set("deleted.jpg", 0x20, 0, 0, false);
+    for entry in deleted.chunks_mut(32) {
+        entry[0] &= 0x7F;
+    }
+    root.extend(deleted);
+    // The last set starts at the end of cluster 2 and goes on in cluster 5
+    root.resize(CLUSTER - 64, 0xFF);
+    for entry in root[32 * 8..].chunks_mut(32) {
+        // Unknown benign secondary entries in use
+        entry[0] = 0xE1;
+    }
+    let long = entry_set("Holiday photo with a long name.jpg", 0x21, 0, 0, false);
+    root.extend(&long);
+    put(&mut image, cluster(2), &root[..CLUSTER]);
+    put(&mut image, cluster(5), &root[CLUSTER..]);
+
+    let mut dcim = entry_set("IMG_0001.JPG", 0x22, 6, 1024, true);
+    dcim.extend(entry_set("100CANON", 0x10, 4, 0, false));
+    put(&mut image, cluster(3), &dcim);
+    put(
+        &mut image,
+        cluster(4),
+        &entry_set("x.raw", 0x20, 0, 0, false),
+    );
+    image
+}
+
+#[test]
+fn conversions() {
+    assert_eq!(from_exfat(0x0010), Props::from(DIRECTORY));
+    assert_eq!(
+        from_exfat(0x0007),
+        Props::from(ARCHIVE | READ_ONLY | HIDDEN | SYSTEM)
+    );
+    // Reserved bits are ignored
+    assert_eq!(from_exfat(0xFF00), Props::from(ARCHIVE));
+    assert_eq!(to_exfat(Props::from(DIRECTORY | SYSTEM)), (0x0014, 0));
+    assert_eq!(to_exfat(Props::from(ARCHIVE | NORMAL)), (0x0020, 0));
+    assert_eq!(
+        to_exfat(Props::from(ARCHIVE | ENCRYPTED | NOT_CONTENT_INDEXED)),
+        (0x0020, ENCRYPTED | NOT_CONTENT_INDEXED)
+    );
+}
+#[test]
+fn walk() {
+    let mut image = ExfatImage::open(Cursor::new(card())).unwrap();
+    let walked = image.walk().unwrap();
+    let paths: Vec<&str> = walked.iter().map(|entry| entry.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "DCIM",
+            "DCIM/IMG_0001.JPG",
+            "DCIM/100CANON",
+            "DCIM/100CANON/x.raw",
+            "Holiday photo with a long name.jpg",
+        ]
+    );
+    assert_eq!(walked[0].props, Props::from(DIRECTORY));
+    assert!(walked[0].contiguous);
+    assert_eq!(walked[1].props, Props::from(ARCHIVE | HIDDEN));
+    assert_eq!(walked[1].data_length, 1024);
+    assert_eq!(walked[1].first_cluster, 6);
+    assert_eq!(walked[4].props, Props::from(ARCHIVE | READ_ONLY));
+    assert_eq!(walked[4].offset, cluster(2) as u64 + CLUSTER as u64 - 64);
+    assert_eq!(image.volume_label(), Ok(Some("CAMERA".to_string())));
+}
+#[test]
+fn lookup() {
+    let mut image = ExfatImage::open(Cursor::new(card())).unwrap();
+    assert_eq!(image.entry("dcim/100canon/X.RAW").unwrap().attributes, 0x20);
+    assert_eq!(image.read_dir(r"\DCIM").unwrap().len(), 2);
+    assert_eq!(
+        image.entry("deleted.jpg"),
+        Err(Error {
+            kind: ErrorKind::FileNotFound
+        })
+    );
+    assert!(image.read_dir("DCIM/IMG_0001.JPG").is_err());
+}
+#[test]
+fn corrupt_images() {
+    let mut image = card();
+    // Changing the attributes without fixing the checksum
+    image[cluster(3) + 4] = 0x23;
+    // A set whose count goes past the end of the directory
+    put(&mut image, cluster(4), &[0x85, 0xFF]);
+    let mut image = ExfatImage::open(Cursor::new(image)).unwrap();
+    let names = |entries: Vec<ExfatEntry>| -> Vec<String> {
+        entries.into_iter().map(|entry| entry.name).collect()
+    };
+    // The corrupt sets are skipped and the rest are still found
+    assert_eq!(names(image.read_dir("DCIM").unwrap()), ["100CANON"]);
+    assert!(image.read_dir("DCIM/100CANON").unwrap().is_empty());
+    assert_eq!(image.walk().unwrap().len(), 3);
+    assert!(ExfatImage::open(Cursor::new(vec![0; 512])).is_err());
+    let mut fat32 = boot_sector();
+    fat32[3..11].copy_from_slice(b"MSDOS5.0");
+    assert!(ExfatImage::open(Cursor::new(fat32)).is_err());
+}
+#[test]
+fn huge_contiguous_directory() {
+    let mut image = card();
+    // A DCIM whose length goes past the 100 clusters of the heap
+    for length in [1 << 60, 100 * CLUSTER as u64] {
+        put(
+            &mut image,
+            cluster(2) + 64,
+            &entry_set("DCIM", 0x10, 3, length, true),
+        );
+        let mut image = ExfatImage::open(Cursor::new(image.clone())).unwrap();
+        assert!(matches!(
+            image.read_dir("DCIM"),
+            Err(Error {
+                kind: ErrorKind::InvalidData(_)
+            })
+        ));
+    }
+}
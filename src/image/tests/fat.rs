@@ -0,0 +1,283 @@
+use std::io::Cursor;
+
+use crate::error::*;
+use crate::image::fat::*;
+use crate::props::constants::*;
+use crate::Props;
+
+const SECTOR: usize = 512;
+
+fn short_entry(name: &[u8; 11], attribute_byte: u8, case: u8, cluster: u32, size: u32) -> Vec<u8> {
+    let mut entry = name.to_vec();
+    entry.push(attribute_byte);
+    entry.push(case);
+    entry.extend([0; 7]);
+    entry.extend(((cluster >> 16) as u16).to_le_bytes());
+    entry.extend([0; 4]);
+    entry.extend((cluster as u16).to_le_bytes());
+    entry.extend(size.to_le_bytes());
+    entry
+}
+/// The long name slots of `name`, last one first, like they're stored
+fn long_entries(name: &str, short_name: &[u8; 11]) -> Vec<u8> {
+    let checksum = short_name
+        .iter()
+        .fold(0u8, |sum, byte| sum.rotate_right(1).wrapping_add(*byte));
+    let mut characters: Vec<u16> = name.encode_utf16().collect();
+    if !characters.len().is_multiple_of(13) {
+        characters.push(0);
+    }
+    characters.resize(characters.len().div_ceil(13) * 13, 0xFFFF);
+    let slots: Vec<&[u16]> = characters.chunks(13).collect();
+    let mut entries = Vec::new();
+    for (index, slot) in slots.iter().enumerate().rev() {
+        let mut order = index as u8 + 1;
+        if index == slots.len() - 1 {
+            order |= 0x40;
+        }
+        let bytes = |characters: &[u16]| -> Vec<u8> {
+            characters.iter().flat_map(|c| c.to_le_bytes()).collect()
+        };
+        entries.push(order);
+        entries.extend(bytes(&slot[..5]));
+        entries.extend([0x0F, 0, checksum]);
+        entries.extend(bytes(&slot[5..11]));
+        entries.extend([0, 0]);
+        entries.extend(bytes(&slot[11..]));
+    }
+    entries
+}
+fn boot_sector(total_sectors: u32, reserved: u16, root_entries: u16, fat_size: u32) -> Vec<u8> {
+    let mut boot = vec![0; SECTOR];
+    boot[11..13].copy_from_slice(&(SECTOR as u16).to_le_bytes());
+    boot[13] = 1;
+    boot[14..16].copy_from_slice(&reserved.to_le_bytes());
+    boot[16] = 2;
+    boot[17..19].copy_from_slice(&root_entries.to_le_bytes());
+    if total_sectors < 0x10000 {
+        boot[19..21].copy_from_slice(&(total_sectors as u16).to_le_bytes());
+    } else {
+        boot[32..36].copy_from_slice(&total_sectors.to_le_bytes());
+    }
+    if root_entries != 0 {
+        boot[22..24].copy_from_slice(&(fat_size as u16).to_le_bytes());
+    } else {
+        boot[36..40].copy_from_slice(&fat_size.to_le_bytes());
+        boot[44..48].copy_from_slice(&2u32.to_le_bytes());
+    }
+    boot[510] = 0x55;
+    boot[511] = 0xAA;
+    boot
+}
+fn set_fat12(fat: &mut [u8], cluster: usize, value: u16) {
+    let offset = cluster + cluster / 2;
+    if cluster % 2 == 1 {
+        fat[offset] = fat[offset] & 0x0F | (value << 4) as u8;
+        fat[offset + 1] = (value >> 4) as u8;
+    } else {
+        fat[offset] = value as u8;
+        fat[offset + 1] = fat[offset + 1] & 0xF0 | (value >> 8) as u8 & 0x0F;
+    }
+}
+/// A FAT12 image with one sector clusters: sector 0 boots, 1 and 2 are the FATs, 3 is the root
+/// and cluster 2 starts at sector 4
+fn fat12_image() -> Vec<u8> {
+    let mut image = boot_sector(64, 1, 16, 1);
+    image.resize(64 * SECTOR, 0);
+    let mut fat = vec![0; SECTOR];
+    set_fat12(&mut fat, 0, 0xFF8);
+    set_fat12(&mut fat, 1, 0xFFF);
+    set_fat12(&mut fat, 2, 3);
+    set_fat12(&mut fat, 3, 0xFFF);
+    set_fat12(&mut fat, 4, 0xFFF);
+    image[SECTOR..2 * SECTOR].copy_from_slice(&fat);
+    image[2 * SECTOR..3 * SECTOR].copy_from_slice(&fat);
+
+    let mut root = Vec::new();
+    root.extend(short_entry(b"MY DISK    ", 0x08, 0, 0, 0));
+    root.extend(long_entries("Long File Name.txt", b"LONGFI~1TXT"));
+    root.extend(short_entry(b"LONGFI~1TXT", 0x22, 0, 0, 10));
+    root.extend(short_entry(b"DOCS       ", 0x10, 0, 2, 0));
+    // A deleted entry, with its long name
+    let mut deleted = long_entries("gone.txt", b"GONE    TXT");
+    deleted[0] = 0xE5;
+    root.extend(deleted);
+    root.extend(short_entry(b"\xE5ONE    TXT", 0x20, 0, 0, 0));
+    root.extend(short_entry(b"README  TXT", 0x01, 0x18, 0, 3));
+    // Long name slots whose checksum doesn't match the entry are ignored
+    root.extend(long_entries("orphan", b"OTHER      "));
+    root.extend(short_entry(b"\x05XTRA   DAT", 0x27, 0, 0, 0));
+    image[3 * SECTOR..3 * SECTOR + root.len()].copy_from_slice(&root);
+
+    // DOCS takes two clusters, 2 and 3
+    let mut docs = Vec::new();
+    docs.extend(short_entry(b".          ", 0x10, 0, 2, 0));
+    docs.extend(short_entry(b"..         ", 0x10, 0, 0, 0));
+    docs.extend(short_entry(b"SUB        ", 0x10, 0x08, 4, 0));
+    docs.resize(SECTOR - 32, 0xE5);
+    docs.extend(short_entry(b"A       TXT", 0x06, 0, 0, 0));
+    docs.extend(short_entry(b"B       TXT", 0x20, 0, 0, 0));
+    image[4 * SECTOR..4 * SECTOR + docs.len()].copy_from_slice(&docs);
+    let mut sub = Vec::new();
+    sub.extend(short_entry(b".          ", 0x10, 0, 4, 0));
+    sub.extend(short_entry(b"..         ", 0x10, 0, 2, 0));
+    sub.extend(long_entries(
+        "Very long name of a nested file.md",
+        b"VERYLO~1MD ",
+    ));
+    sub.extend(short_entry(b"VERYLO~1MD ", 0x21, 0, 0, 0));
+    image[6 * SECTOR..6 * SECTOR + sub.len()].copy_from_slice(&sub);
+    image
+}
+
+#[test]
+fn fat12_walk() {
+    let mut image = FatImage::open(Cursor::new(fat12_image())).unwrap();
+    assert_eq!(image.kind(), FatKind::Fat12);
+    let walked = image.walk().unwrap();
+    let paths: Vec<&str> = walked.iter().map(|entry| entry.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "MY DISK",
+            "Long File Name.txt",
+            "DOCS",
+            "DOCS/sub",
+            "DOCS/sub/Very long name of a nested file.md",
+            "DOCS/A.TXT",
+            "DOCS/B.TXT",
+            "readme.txt",
+            "\u{E5}XTRA.DAT",
+        ]
+    );
+    assert!(walked[0].is_volume_label());
+    assert_eq!(walked[1].short_name, "LONGFI~1.TXT");
+    assert_eq!(walked[1].props, Props::from(ARCHIVE | HIDDEN));
+    assert_eq!(walked[1].size, 10);
+    assert_eq!(walked[2].props, Props::from(DIRECTORY));
+    assert_eq!(walked[5].props, Props::from(ARCHIVE | HIDDEN | SYSTEM));
+    assert_eq!(walked[8].attribute_byte, 0x27);
+    assert_eq!(image.volume_label(), Ok(Some("MY DISK".to_string())));
+}
+#[test]
+fn fat12_lookup() {
+    let mut image = FatImage::open(Cursor::new(fat12_image())).unwrap();
+    let entry = image
+        .entry("/docs/SUB/very long name of a nested FILE.md")
+        .unwrap();
+    assert_eq!(entry.props, Props::from(ARCHIVE | READ_ONLY));
+    assert_eq!(
+        image.entry(r"docs\verylo~1.md").map(|entry| entry.path),
+        Err(Error {
+            kind: ErrorKind::FileNotFound
+        })
+    );
+    assert_eq!(
+        image.entry(r"docs\sub\verylo~1.md").unwrap().name,
+        "Very long name of a nested file.md"
+    );
+    assert_eq!(
+        image.entry("my disk"),
+        Err(Error {
+            kind: ErrorKind::FileNotFound
+        })
+    );
+    let listed = image.read_dir("Docs").unwrap();
+    assert_eq!(listed.len(), 3);
+    assert_eq!(listed[0].path, "DOCS/sub");
+    assert!(image.read_dir("readme.txt").is_err());
+}
+#[test]
+fn fat12_rewrite() {
+    let mut image = FatImage::open(Cursor::new(fat12_image())).unwrap();
+    let mut entry = image.entry("readme.txt").unwrap();
+    let attribute_byte = apply_mattrib(entry.attribute_byte, "+hs -r").unwrap();
+    assert_eq!(attribute_byte, 0x06);
+    image
+        .set_attribute_byte(&mut entry, attribute_byte)
+        .unwrap();
+    assert_eq!(entry.props, Props::from(ARCHIVE | HIDDEN | SYSTEM));
+
+    let written = image
+        .set_props("docs/a.txt", Props::from(ARCHIVE | READ_ONLY))
+        .unwrap();
+    assert_eq!(written.attribute_byte, 0x21);
+    let mut directory = image.entry("docs").unwrap();
+    assert!(matches!(
+        image.set_attribute_byte(&mut directory, 0x20),
+        Err(Error {
+            kind: ErrorKind::NotSupported(_)
+        })
+    ));
+    assert!(matches!(
+        image.set_props("docs/b.txt", Props::from(ARCHIVE | COMPRESSED)),
+        Err(Error {
+            kind: ErrorKind::NotSupported(_)
+        })
+    ));
+
+    // A stale entry isn't written
+    let mut stale = entry.clone();
+    stale.attribute_byte = 0x01;
+    assert!(image.set_attribute_byte(&mut stale, 0x03).is_err());
+
+    let mut image = FatImage::open(Cursor::new(image.into_inner().into_inner())).unwrap();
+    assert_eq!(image.entry("readme.txt").unwrap().attribute_byte, 0x06);
+    assert_eq!(image.entry("docs/a.txt").unwrap().attribute_byte, 0x21);
+    // The long name slots are still joined to their entry
+    assert_eq!(
+        image.entry("Long File Name.txt").unwrap().attribute_byte,
+        0x22
+    );
+}
+#[test]
+fn mattrib_changes() {
+    assert_eq!(apply_mattrib(0x20, "-a +r"), Ok(0x01));
+    assert_eq!(apply_mattrib(0x10, ""), Ok(0x10));
+    assert!(apply_mattrib(0x20, "h").is_err());
+    assert!(apply_mattrib(0x20, "+x").is_err());
+    assert!(apply_mattrib(0x20, "-").is_err());
+}
+#[test]
+fn fat32() {
+    // 70000 clusters make it FAT32, but the image only needs the sectors that are read
+    let reserved = 32;
+    let fat_size: u32 = 560;
+    let data = (reserved as u32 + 2 * fat_size) as usize * SECTOR;
+    let mut image = boot_sector(70_000, reserved, 0, fat_size);
+    image.resize(data + 3 * SECTOR, 0);
+    let fat = reserved as usize * SECTOR;
+    for (cluster, next) in [(2, 0x0FFF_FFFF), (3, 0x0FFF_FFF8), (4, 0x0FFF_FFFF)] {
+        image[fat + cluster * 4..fat + cluster * 4 + 4]
+            .copy_from_slice(&(next as u32).to_le_bytes());
+    }
+    let mut root = Vec::new();
+    root.extend(short_entry(b"USB        ", 0x08, 0, 0, 0));
+    root.extend(short_entry(b"PROGRA~1   ", 0x11, 0, 3, 0));
+    image[data..data + root.len()].copy_from_slice(&root);
+    let files = short_entry(b"SETUP   EXE", 0x23, 0, 4, 0);
+    image[data + SECTOR..data + SECTOR + 32].copy_from_slice(&files);
+
+    let mut image = FatImage::open(Cursor::new(image)).unwrap();
+    assert_eq!(image.kind(), FatKind::Fat32);
+    let walked = image.walk().unwrap();
+    assert_eq!(walked.len(), 3);
+    assert_eq!(walked[1].props, Props::from(DIRECTORY | READ_ONLY));
+    assert_eq!(walked[2].path, "PROGRA~1/SETUP.EXE");
+    assert_eq!(walked[2].props, Props::from(ARCHIVE | READ_ONLY | HIDDEN));
+}
+#[test]
+fn corrupt_images() {
+    assert!(FatImage::open(Cursor::new(vec![0; SECTOR])).is_err());
+    assert!(FatImage::open(Cursor::new(vec![0; 100])).is_err());
+    // A directory whose cluster chain points back to itself
+    let mut image = fat12_image();
+    set_fat12(&mut image[SECTOR..2 * SECTOR], 3, 2);
+    let mut image = FatImage::open(Cursor::new(image)).unwrap();
+    assert!(matches!(
+        image.walk(),
+        Err(Error {
+            kind: ErrorKind::InvalidData(_)
+        })
+    ));
+}
@@ -0,0 +1,2 @@
+mod exfat;
+mod fat;
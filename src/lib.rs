@@ -5,10 +5,20 @@
 //! You can use this crate with different purposes:
 //! 1. You can provide an `u32`, with the From trait, and get back a Props instance with the correspondent properties.
 //! 2. You can provide a `PathBuf` or a reference to it, with the TryFrom trait, and you won't have
-//!    to extract the correspondent properties. You can also provide a valid `&str`.  
+//!    to extract the correspondent properties. You can also provide a valid `&str`, or call
+//!    `Props::from_path` with anything that works as a path. Outside of Windows the attributes
+//!    are read from where the file system or server keeps them, check the `backends` module.  
 //! 3. With the properties correctly set you can get the `u32` correpondent to those properties you
 //!    set. The library will ensure you don't set invalid states
 //! 4. The `Display` implementation of the struct return a `String` identical as the one that prints with `GetChild-Item` in PowerShell, which are the most commonly used.  
+//! 5. The `props!` macro builds a Props from the attribute names, checking at compile time that
+//!    they exist and that file-exclusive ones aren't given to a directory.
+//! 6. With the `derive` feature, `#[derive(FileAttributes)]` generates the conversions between
+//!    your own structs of booleans and Props.
+//! 7. The `scan` module walks whole trees through any backend, in parallel if you want.
+//! 8. The `archive` module reads and writes the attributes archive formats store for every entry.
+//! 9. The `image` module reads the attributes straight from file system images, without mounting them.
+//! 10. The `wire` module decodes the attributes in captured Win32 structures and SMB traffic.
 //!
 //! For reference with all the file system element properties go to the [Microsoft File Attribute Constants Documentation](https://learn.microsoft.com/en-us/windows/win32/fileio/file-attribute-constants).  
 //! The implementation of the library uses enums to make invalid states unrepresentable.  
@@ -21,9 +31,25 @@
 //! assert_eq!(Props::try_from(r"hidden_file_example.txt").unwrap(), props);
 //! ```
 
+/// The attributes stored in archive formats
+pub mod archive;
+/// The backends that read and write the attributes where Windows isn't the one storing them
+pub mod backends;
 /// The Error type for the crate
 pub mod error;
+/// The attributes stored inside file system images
+pub mod image;
+/// The `props!` macro
+mod macros;
 /// The main module of the library
 pub mod props;
+mod reader;
+/// Walks a tree reading the attributes of every element
+pub mod scan;
+/// The attributes inside the structures Windows APIs and file protocols pass around
+pub mod wire;
+/// The derive macro that maps your structs to Props, only with the `derive` feature
+#[cfg(feature = "derive")]
+pub use keypropdecode_derive::FileAttributes;
 /// The re-export of the main Struct of the library
 pub use props::Props;
@@ -0,0 +1,152 @@
+/// Builds a [`Props`](crate::Props) from a list of attribute names.
+/// The first word says if the element is a `file` or a `dir`, and after the colon come the
+/// attributes that are set to true. The names are the same ones the setters of `Props` use.
+/// Unknown attribute names and file-exclusive attributes on directories are compile errors, so
+/// the invalid states the setters return errors for can't be written with this macro.
+/// ```
+/// use keypropdecode::props;
+/// use keypropdecode::props::*;
+///
+/// let mut file = Props::default();
+/// file.change_element_type(ArcDir::Archive(ArchiveProps::default()));
+/// file.hidden(true);
+/// file.read_only(true).unwrap();
+/// assert_eq!(props!(file: hidden, read_only), file);
+///
+/// let mut dir = Props::default();
+/// dir.hidden(true);
+/// assert_eq!(props!(dir: hidden), dir);
+/// assert_eq!(props!(dir), Props::default());
+/// ```
+/// Unknown attributes don't compile:
+/// ```compile_fail
+/// use keypropdecode::props;
+/// let props = props!(file: hiden);
+/// ```
+/// Neither do file-exclusive attributes on directories:
+/// ```compile_fail
+/// use keypropdecode::props;
+/// let props = props!(dir: read_only);
+/// ```
+/// And the normal attribute must go alone, as [`Props::normal`](crate::Props::normal) requires:
+/// ```compile_fail
+/// use keypropdecode::props;
+/// let props = props!(file: normal, hidden);
+/// ```
+#[macro_export]
+macro_rules! props {
+    (file: normal $(,)?) => {
+        $crate::Props::from(
+            $crate::props::constants::ARCHIVE | $crate::props::constants::NORMAL,
+        )
+    };
+    (file $(: $($attr:ident),* $(,)?)?) => {
+        $crate::Props::from(
+            $crate::props::constants::ARCHIVE $($(| $crate::__props_file_attr!($attr))*)?,
+        )
+    };
+    (dir $(: $($attr:ident),* $(,)?)?) => {
+        $crate::Props::from(
+            $crate::props::constants::DIRECTORY $($(| $crate::__props_dir_attr!($attr))*)?,
+        )
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __props_file_attr {
+    (read_only) => {
+        $crate::props::constants::READ_ONLY
+    };
+    (normal) => {
+        compile_error!("The normal property must be the only one set, use `props!(file: normal)`")
+    };
+    (temporary) => {
+        $crate::props::constants::TEMPORARY
+    };
+    (sparse) => {
+        $crate::props::constants::SPARSE
+    };
+    (offline) => {
+        $crate::props::constants::OFFLINE
+    };
+    ($attr:ident) => {
+        $crate::__props_common_attr!($attr)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __props_dir_attr {
+    (read_only) => {
+        compile_error!("The read_only property is exclusive for files")
+    };
+    (normal) => {
+        compile_error!("The normal property is exclusive for files")
+    };
+    (temporary) => {
+        compile_error!("The temporary property is exclusive for files")
+    };
+    (sparse) => {
+        compile_error!("The sparse property is exclusive for files")
+    };
+    (offline) => {
+        compile_error!("The offline property is exclusive for files")
+    };
+    ($attr:ident) => {
+        $crate::__props_common_attr!($attr)
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __props_common_attr {
+    (hidden) => {
+        $crate::props::constants::HIDDEN
+    };
+    (system) => {
+        $crate::props::constants::SYSTEM
+    };
+    (device) => {
+        $crate::props::constants::DEVICE
+    };
+    (reparse) => {
+        $crate::props::constants::REPARSE
+    };
+    (compressed) => {
+        $crate::props::constants::COMPRESSED
+    };
+    (not_content_indexed) => {
+        $crate::props::constants::NOT_CONTENT_INDEXED
+    };
+    (encrypted) => {
+        $crate::props::constants::ENCRYPTED
+    };
+    (integrity_stream) => {
+        $crate::props::constants::INTEGRITY_STREAM
+    };
+    (virtual_file) => {
+        $crate::props::constants::VIRTUAL_FILE
+    };
+    (no_scrub_data) => {
+        $crate::props::constants::NO_SCRUB_DATA
+    };
+    (extended_attributes) => {
+        $crate::props::constants::EXTENDED_ATTRIBUTES
+    };
+    (pinned) => {
+        $crate::props::constants::PINNED
+    };
+    (unpinned) => {
+        $crate::props::constants::UNPINNED
+    };
+    (recall_on_open) => {
+        $crate::props::constants::RECALL_ON_OPEN
+    };
+    (recall_on_data_access) => {
+        $crate::props::constants::RECALL_ON_DATA_ACCESS
+    };
+    ($attr:ident) => {
+        compile_error!(concat!("Unknown file attribute `", stringify!($attr), "`"))
+    };
+}
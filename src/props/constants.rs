@@ -0,0 +1,47 @@
+//! Each one of them is the bit that the property takes in the number Windows stores.
+//! Check the [Microsoft File Attribute Constants Documentation](https://learn.microsoft.com/en-us/windows/win32/fileio/file-attribute-constants) for the meaning of each one.
+
+/// FILE_ATTRIBUTE_READONLY
+pub const READ_ONLY: u32 = 1;
+/// FILE_ATTRIBUTE_HIDDEN
+pub const HIDDEN: u32 = 1 << 1;
+/// FILE_ATTRIBUTE_SYSTEM
+pub const SYSTEM: u32 = 1 << 2;
+/// FILE_ATTRIBUTE_DIRECTORY
+pub const DIRECTORY: u32 = 1 << 4;
+/// FILE_ATTRIBUTE_ARCHIVE
+pub const ARCHIVE: u32 = 1 << 5;
+/// FILE_ATTRIBUTE_DEVICE
+pub const DEVICE: u32 = 1 << 6;
+/// FILE_ATTRIBUTE_NORMAL
+pub const NORMAL: u32 = 1 << 7;
+/// FILE_ATTRIBUTE_TEMPORARY
+pub const TEMPORARY: u32 = 1 << 8;
+/// FILE_ATTRIBUTE_SPARSE_FILE
+pub const SPARSE: u32 = 1 << 9;
+/// FILE_ATTRIBUTE_REPARSE_POINT
+pub const REPARSE: u32 = 1 << 10;
+/// FILE_ATTRIBUTE_COMPRESSED
+pub const COMPRESSED: u32 = 1 << 11;
+/// FILE_ATTRIBUTE_OFFLINE
+pub const OFFLINE: u32 = 1 << 12;
+/// FILE_ATTRIBUTE_NOT_CONTENT_INDEXED
+pub const NOT_CONTENT_INDEXED: u32 = 1 << 13;
+/// FILE_ATTRIBUTE_ENCRYPTED
+pub const ENCRYPTED: u32 = 1 << 14;
+/// FILE_ATTRIBUTE_INTEGRITY_STREAM
+pub const INTEGRITY_STREAM: u32 = 1 << 15;
+/// FILE_ATTRIBUTE_VIRTUAL
+pub const VIRTUAL_FILE: u32 = 1 << 16;
+/// FILE_ATTRIBUTE_NO_SCRUB_DATA
+pub const NO_SCRUB_DATA: u32 = 1 << 17;
+/// FILE_ATTRIBUTE_EA
+pub const EXTENDED_ATTRIBUTES: u32 = 1 << 18;
+/// FILE_ATTRIBUTE_PINNED
+pub const PINNED: u32 = 1 << 19;
+/// FILE_ATTRIBUTE_UNPINNED
+pub const UNPINNED: u32 = 1 << 20;
+/// FILE_ATTRIBUTE_RECALL_ON_OPEN
+pub const RECALL_ON_OPEN: u32 = 1 << 21;
+/// FILE_ATTRIBUTE_RECALL_ON_DATA_ACCESS
+pub const RECALL_ON_DATA_ACCESS: u32 = 1 << 22;
@@ -1,36 +1,13 @@
 use crate::{
-    props::{ArcDir, ArchiveProps},
+    props::{constants::*, ArcDir, ArchiveProps},
     Props,
 };
 
-#[cfg(windows)]
-use std::os::windows::prelude::*;
-use std::{fs::Metadata, path::PathBuf};
+use std::path::{Path, PathBuf};
 
+use crate::backends::{AttributeSource, Auto, Links};
 use crate::error::*;
 
-const READ_ONLY: u32 = 1;
-const HIDDEN: u32 = 1 << 1;
-const SYSTEM: u32 = 1 << 2;
-const DIRECTORY: u32 = 1 << 4;
-const ARCHIVE: u32 = 1 << 5;
-const DEVICE: u32 = 1 << 6;
-const NORMAL: u32 = 1 << 7;
-const TEMPORARY: u32 = 1 << 8;
-const SPARSE: u32 = 1 << 9;
-const REPARSE: u32 = 1 << 10;
-const COMPRESSED: u32 = 1 << 11;
-const OFFLINE: u32 = 1 << 12;
-const NOT_CONTENT_INDEXED: u32 = 1 << 13;
-const ENCRYPTED: u32 = 1 << 14;
-const INTEGRITY_STREAM: u32 = 1 << 15;
-const VIRTUAL_FILE: u32 = 1 << 16;
-const NO_SCRUB_DATA: u32 = 1 << 17;
-const EXTENDED_ATTRIBUTES: u32 = 1 << 18;
-const PINNED: u32 = 1 << 19;
-const UNPINNED: u32 = 1 << 20;
-const RECALL_ON_OPEN: u32 = 1 << 21;
-const RECALL_ON_DATA_ACCESS: u32 = 1 << 22;
 impl From<u32> for Props {
     fn from(value: u32) -> Self {
         // ! OS FS correcteness is assumed.
@@ -107,42 +84,51 @@ impl From<u32> for Props {
         props
     }
 }
-#[cfg(windows)]
+impl Props {
+    /// Gets the Props of the element at `path`, following symbolic links.
+    /// The attributes are read with [`Auto`], which picks the right backend for the platform and
+    /// the file system.
+    /// ```no_run
+    /// use keypropdecode::Props;
+    ///
+    /// let props = Props::from_path("Cargo.toml").unwrap();
+    /// assert!(props.is_archive());
+    /// ```
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Props> {
+        Props::from_path_with(path, &Auto, Links::Follow)
+    }
+    /// Gets the Props of the element at `path` from the backend you choose, following symbolic
+    /// links or not.
+    pub fn from_path_with<S: AttributeSource + ?Sized>(
+        path: impl AsRef<Path>,
+        source: &S,
+        links: Links,
+    ) -> Result<Props> {
+        source.read(path.as_ref(), links)
+    }
+}
 impl TryFrom<PathBuf> for Props {
     type Error = crate::error::Error;
     fn try_from(value: PathBuf) -> std::prelude::v1::Result<Self, Self::Error> {
-        let metadata: Metadata = match std::fs::metadata(value.clone()) {
-            Ok(obtained_metadata) => obtained_metadata,
-            Err(_) => {
-                return Err(Error {
-                    kind: ErrorKind::FileNotFound,
-                })
-            }
-        };
-        Ok(Props::from(metadata.file_attributes()))
+        Props::from_path(value)
     }
 }
-#[cfg(windows)]
 impl TryFrom<&PathBuf> for Props {
     type Error = crate::error::Error;
     fn try_from(value: &PathBuf) -> std::prelude::v1::Result<Self, Self::Error> {
-        let metadata: Metadata = match std::fs::metadata(value.clone()) {
-            Ok(obtained_metadata) => obtained_metadata,
-            Err(_) => {
-                return Err(Error {
-                    kind: ErrorKind::FileNotFound,
-                })
-            }
-        };
-        Ok(Props::from(metadata.file_attributes()))
+        Props::from_path(value)
+    }
+}
+impl TryFrom<&Path> for Props {
+    type Error = crate::error::Error;
+    fn try_from(value: &Path) -> std::prelude::v1::Result<Self, Self::Error> {
+        Props::from_path(value)
     }
 }
-#[cfg(windows)]
 impl TryFrom<&str> for Props {
     type Error = crate::error::Error;
     fn try_from(value: &str) -> std::prelude::v1::Result<Self, Self::Error> {
-        let path = PathBuf::from(value);
-        Props::try_from(path)
+        Props::from_path(value)
     }
 }
 impl From<Props> for u32 {
@@ -1,3 +1,5 @@
+/// The bit values of every file attribute
+pub mod constants;
 mod implementations;
 #[cfg(test)]
 mod tests;
@@ -12,8 +14,9 @@ use crate::error::*;
 /// 3. From<Props> for u32
 /// 4. TryFrom<Pathbuf> for Props
 /// 5. TryFrom<&Pathbuf> for Props
-/// 6. TryFrom<&str> for Props  
-/// (The TryFrom ones won't appear in the documentation because they use Windows specific behaviour and it is put behind a conditional flag because docs.rs only uses a Linux container)
+/// 6. TryFrom<&Path> for Props
+/// 7. TryFrom<&str> for Props  
+///    (The TryFrom ones are the same as `Props::from_path`)
 #[derive(Debug, PartialEq, PartialOrd, Eq, Ord, Clone, Copy, Default)]
 pub struct Props {
     element_type: ArcDir,
@@ -0,0 +1,68 @@
+use crate::error::*;
+
+/// Little endian reader over a byte buffer, used by every decoder of binary structures.
+/// Running out of bytes gives an `InvalidData` error that names the structure being read.
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    what: &'static str,
+}
+impl<'a> Reader<'a> {
+    pub(crate) fn new(bytes: &'a [u8], what: &'static str) -> Self {
+        Reader {
+            bytes,
+            offset: 0,
+            what,
+        }
+    }
+    pub(crate) fn remaining(&self) -> usize {
+        self.bytes.len() - self.offset
+    }
+    pub(crate) fn skip(&mut self, count: usize) -> Result<()> {
+        self.take(count).map(|_| ())
+    }
+    /// Skips the padding until the offset is a multiple of `alignment`
+    pub(crate) fn align(&mut self, alignment: usize) -> Result<()> {
+        let padding = (alignment - self.offset % alignment) % alignment;
+        self.skip(padding)
+    }
+    pub(crate) fn take(&mut self, count: usize) -> Result<&'a [u8]> {
+        let end = match self.offset.checked_add(count) {
+            Some(end) if end <= self.bytes.len() => end,
+            _ => return Err(self.truncated()),
+        };
+        let taken = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(taken)
+    }
+    pub(crate) fn u16(&mut self) -> Result<u16> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+    pub(crate) fn u32(&mut self) -> Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+    pub(crate) fn u64(&mut self) -> Result<u64> {
+        let bytes = self.take(8)?;
+        let mut value = [0; 8];
+        value.copy_from_slice(bytes);
+        Ok(u64::from_le_bytes(value))
+    }
+    /// Reads up to the next nul byte, consuming it
+    pub(crate) fn nul_terminated(&mut self) -> Result<&'a [u8]> {
+        let rest = &self.bytes[self.offset..];
+        match rest.iter().position(|byte| *byte == 0) {
+            Some(end) => {
+                self.offset += end + 1;
+                Ok(&rest[..end])
+            }
+            None => Err(self.truncated()),
+        }
+    }
+    fn truncated(&self) -> Error {
+        Error {
+            kind: ErrorKind::InvalidData(format!("The {} is truncated", self.what)),
+        }
+    }
+}
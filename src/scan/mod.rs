@@ -0,0 +1,396 @@
+//! Walks every element under a root, reading its Props through any backend.
+//! An element whose attributes can't be read, or a directory that can't be listed, gives an
+//! entry with the error and the walk goes on with the rest. The directories whose attributes
+//! can't be read are still walked, and the ones reached again through links aren't.
+//! ```no_run
+//! use keypropdecode::backends::Auto;
+//! use keypropdecode::scan::Scanner;
+//!
+//! let scanner = Scanner::new(&Auto).max_depth(3).prune(|_, props| props.is_system());
+//! for entry in scanner.iter("/mnt/share") {
+//!     match entry.props {
+//!         Ok(props) => println!("{} {}", props, entry.path.display()),
+//!         Err(error) => eprintln!("{}: {}", entry.path.display(), error),
+//!     }
+//! }
+//! ```
+//! Very large trees can be walked by several threads at once with [`Scanner::par_for_each`](crate::scan::Scanner::par_for_each).
+//! ```
+//! use keypropdecode::backends::memory::MemoryStore;
+//! use keypropdecode::scan::Scanner;
+//! use keypropdecode::props;
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//!
+//! let store = MemoryStore::new();
+//! store.insert("/data/a.txt", props!(file: hidden)).unwrap();
+//! store.insert("/data/dir/b.txt", props!(file)).unwrap();
+//!
+//! let hidden = AtomicUsize::new(0);
+//! Scanner::new(&store).listing(&store).par_for_each("/data", 4, |entry| {
+//!     if entry.props.is_ok_and(|props| props.is_hidden()) {
+//!         hidden.fetch_add(1, Ordering::Relaxed);
+//!     }
+//! });
+//! assert_eq!(hidden.into_inner(), 1);
+//! ```
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Condvar, Mutex, PoisonError};
+
+use crate::backends::memory::MemoryStore;
+use crate::backends::{AttributeSource, Links};
+use crate::error::*;
+use crate::Props;
+
+/// A place the children of a directory can be listed from.
+pub trait DirectoryListing {
+    /// The paths of the elements directly inside the directory at `path`
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>>;
+    /// Returns true if `path` is a directory, which is asked when its attributes can't be read.
+    /// By default it checks if it can be listed.
+    fn is_dir(&self, path: &Path, links: Links) -> bool {
+        let _ = links;
+        self.read_dir(path).is_ok()
+    }
+    /// Something that tells apart every directory, like the device and inode numbers, so the
+    /// ones reached again through links aren't walked twice. By default there's none.
+    fn identity(&self, path: &Path, links: Links) -> Option<(u64, u64)> {
+        let _ = (path, links);
+        None
+    }
+}
+/// Lists the directories of the real file system, sorted by name.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct FileSystem;
+impl DirectoryListing for FileSystem {
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        let to_error = |error: std::io::Error| Error {
+            kind: match error.kind() {
+                std::io::ErrorKind::NotFound => ErrorKind::FileNotFound,
+                _ => ErrorKind::Other(format!("{}: {}", path.display(), error)),
+            },
+        };
+        let mut children = std::fs::read_dir(path)
+            .map_err(to_error)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<std::io::Result<Vec<PathBuf>>>()
+            .map_err(to_error)?;
+        children.sort();
+        Ok(children)
+    }
+    fn is_dir(&self, path: &Path, links: Links) -> bool {
+        metadata(path, links).is_ok_and(|metadata| metadata.is_dir())
+    }
+    #[cfg(unix)]
+    fn identity(&self, path: &Path, links: Links) -> Option<(u64, u64)> {
+        use std::os::unix::fs::MetadataExt;
+        let metadata = metadata(path, links).ok()?;
+        Some((metadata.dev(), metadata.ino()))
+    }
+}
+fn metadata(path: &Path, links: Links) -> std::io::Result<std::fs::Metadata> {
+    match links {
+        Links::Follow => std::fs::metadata(path),
+        Links::NoFollow => std::fs::symlink_metadata(path),
+    }
+}
+impl DirectoryListing for MemoryStore {
+    fn read_dir(&self, path: &Path) -> Result<Vec<PathBuf>> {
+        MemoryStore::read_dir(self, path)
+    }
+    fn is_dir(&self, path: &Path, links: Links) -> bool {
+        self.read(path, links)
+            .is_ok_and(|props| props.is_directory())
+    }
+}
+
+/// An element found by the scanner
+#[derive(Debug, PartialEq)]
+pub struct ScanEntry {
+    /// Where the element is
+    pub path: PathBuf,
+    /// How many directories down from the root it is, the root being 0
+    pub depth: usize,
+    /// The attributes of the element, or why they couldn't be read.
+    /// A directory that can't be listed gives a second entry with the error.
+    pub props: Result<Props>,
+}
+/// How far the scan has gone, given to the progress callback after every entry
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Progress {
+    /// The entries given so far, errors included
+    pub entries: usize,
+    /// The entries given so far that have an error
+    pub errors: usize,
+    /// The elements skipped by the prune function
+    pub pruned: usize,
+}
+
+type Prune<'a> = dyn Fn(&Path, &Props) -> bool + Sync + 'a;
+type ProgressCallback<'a> = dyn Fn(Progress) + Sync + 'a;
+
+/// Walks a tree reading the attributes through a backend.
+/// By default it lists the real file system, doesn't follow links, has no depth limit and
+/// doesn't descend into reparse point directories.
+pub struct Scanner<'a> {
+    source: &'a (dyn AttributeSource + Sync),
+    listing: &'a (dyn DirectoryListing + Sync),
+    links: Links,
+    max_depth: Option<usize>,
+    descend_reparse: bool,
+    prune: Option<Box<Prune<'a>>>,
+    progress: Option<Box<ProgressCallback<'a>>>,
+}
+impl<'a> Scanner<'a> {
+    /// A scanner that reads the attributes from `source`
+    pub fn new(source: &'a (dyn AttributeSource + Sync)) -> Self {
+        Scanner {
+            source,
+            listing: &FileSystem,
+            links: Links::NoFollow,
+            max_depth: None,
+            descend_reparse: false,
+            prune: None,
+            progress: None,
+        }
+    }
+    /// Lists the directories with `listing` instead of the real file system
+    pub fn listing(mut self, listing: &'a (dyn DirectoryListing + Sync)) -> Self {
+        self.listing = listing;
+        self
+    }
+    /// What to do with links when reading the attributes
+    pub fn links(mut self, links: Links) -> Self {
+        self.links = links;
+        self
+    }
+    /// Doesn't give elements deeper than `max_depth`. With 0 only the root is given.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+    /// Also walks the directories that are reparse points, like junctions and links
+    pub fn descend_reparse(mut self, descend_reparse: bool) -> Self {
+        self.descend_reparse = descend_reparse;
+        self
+    }
+    /// Skips the elements `prune` returns true for, and everything inside them
+    pub fn prune(mut self, prune: impl Fn(&Path, &Props) -> bool + Sync + 'a) -> Self {
+        self.prune = Some(Box::new(prune));
+        self
+    }
+    /// Calls `progress` after every entry
+    pub fn progress(mut self, progress: impl Fn(Progress) + Sync + 'a) -> Self {
+        self.progress = Some(Box::new(progress));
+        self
+    }
+    /// Walks the tree under `root` one element at a time, depth first and in the order of the
+    /// listing
+    pub fn iter(&self, root: impl AsRef<Path>) -> ScanIter<'_, 'a> {
+        ScanIter {
+            scanner: self,
+            pending: vec![(root.as_ref().to_path_buf(), 0)],
+            listing_error: None,
+            progress: Progress::default(),
+            walked: Mutex::default(),
+        }
+    }
+    /// Walks the tree under `root` with `threads` threads, calling `on_entry` for every entry from
+    /// any of them, so the order isn't the one of [`Scanner::iter`].
+    /// With 0 threads it uses as many as the machine can run in parallel.
+    pub fn par_for_each(
+        &self,
+        root: impl AsRef<Path>,
+        threads: usize,
+        on_entry: impl Fn(ScanEntry) + Sync,
+    ) {
+        let threads = match threads {
+            0 => std::thread::available_parallelism().map_or(1, |threads| threads.get()),
+            threads => threads,
+        };
+        let queue = Mutex::new(WorkQueue {
+            pending: vec![(root.as_ref().to_path_buf(), 0)],
+            working: 0,
+        });
+        let changed = Condvar::new();
+        let walked = Mutex::default();
+        let counters = [
+            AtomicUsize::new(0),
+            AtomicUsize::new(0),
+            AtomicUsize::new(0),
+        ];
+        let worker = || loop {
+            let mut work = queue.lock().expect("poisoned scan queue");
+            let (path, depth) = loop {
+                if let Some(next) = work.pending.pop() {
+                    break next;
+                }
+                if work.working == 0 {
+                    return;
+                }
+                work = changed.wait(work).expect("poisoned scan queue");
+            };
+            work.working += 1;
+            drop(work);
+
+            // The other threads wait for this one until it's done, even if the visit panics
+            let working = Working {
+                queue: &queue,
+                changed: &changed,
+            };
+            let visit = self.visit(path, depth, &walked);
+            let mut work = queue.lock().expect("poisoned scan queue");
+            work.pending.extend(visit.children.into_iter().rev());
+            drop(work);
+            drop(working);
+
+            if visit.entry.is_none() {
+                counters[2].fetch_add(1, Ordering::Relaxed);
+                self.report(&counters);
+            }
+            for entry in visit.entry.into_iter().chain(visit.listing_error) {
+                counters[0].fetch_add(1, Ordering::Relaxed);
+                if entry.props.is_err() {
+                    counters[1].fetch_add(1, Ordering::Relaxed);
+                }
+                on_entry(entry);
+                self.report(&counters);
+            }
+        };
+        std::thread::scope(|scope| {
+            for _ in 0..threads {
+                scope.spawn(worker);
+            }
+        });
+    }
+    fn report(&self, counters: &[AtomicUsize; 3]) {
+        if let Some(progress) = &self.progress {
+            progress(Progress {
+                entries: counters[0].load(Ordering::Relaxed),
+                errors: counters[1].load(Ordering::Relaxed),
+                pruned: counters[2].load(Ordering::Relaxed),
+            });
+        }
+    }
+    /// Reads one element and lists it if it has to be walked.
+    /// `walked` has the identities of the directories already listed.
+    fn visit(&self, path: PathBuf, depth: usize, walked: &Mutex<HashSet<(u64, u64)>>) -> Visit {
+        let props = self.source.read(&path, self.links);
+        let mut visit = Visit {
+            entry: None,
+            listing_error: None,
+            children: Vec::new(),
+        };
+        let directory = match &props {
+            Ok(props) => {
+                if self.prune.as_ref().is_some_and(|prune| prune(&path, props)) {
+                    return visit;
+                }
+                props.is_directory() && (self.descend_reparse || !props.is_reparse())
+            }
+            // The error is given in the entry, but what's inside can still be read
+            Err(_) => self.listing.is_dir(&path, self.links),
+        };
+        let walk = directory
+            && self.max_depth.is_none_or(|max_depth| depth < max_depth)
+            && self
+                .listing
+                .identity(&path, self.links)
+                .is_none_or(|identity| {
+                    walked
+                        .lock()
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .insert(identity)
+                });
+        if walk {
+            match self.listing.read_dir(&path) {
+                Ok(children) => {
+                    visit.children = children
+                        .into_iter()
+                        .map(|child| (child, depth + 1))
+                        .collect()
+                }
+                Err(error) => {
+                    visit.listing_error = Some(ScanEntry {
+                        path: path.clone(),
+                        depth,
+                        props: Err(error),
+                    })
+                }
+            }
+        }
+        visit.entry = Some(ScanEntry { path, depth, props });
+        visit
+    }
+}
+struct Visit {
+    /// None when the element is pruned
+    entry: Option<ScanEntry>,
+    listing_error: Option<ScanEntry>,
+    children: Vec<(PathBuf, usize)>,
+}
+struct WorkQueue {
+    pending: Vec<(PathBuf, usize)>,
+    /// The threads visiting an element, which may add more to the queue
+    working: usize,
+}
+/// Marks the end of a visit when dropped, waking the threads waiting for more work
+struct Working<'q> {
+    queue: &'q Mutex<WorkQueue>,
+    changed: &'q Condvar,
+}
+impl Drop for Working<'_> {
+    fn drop(&mut self) {
+        let mut work = self.queue.lock().unwrap_or_else(PoisonError::into_inner);
+        work.working -= 1;
+        drop(work);
+        self.changed.notify_all();
+    }
+}
+
+/// The entries of [`Scanner::iter`]
+pub struct ScanIter<'s, 'a> {
+    scanner: &'s Scanner<'a>,
+    pending: Vec<(PathBuf, usize)>,
+    listing_error: Option<ScanEntry>,
+    progress: Progress,
+    walked: Mutex<HashSet<(u64, u64)>>,
+}
+impl Iterator for ScanIter<'_, '_> {
+    type Item = ScanEntry;
+    fn next(&mut self) -> Option<ScanEntry> {
+        let entry = loop {
+            if let Some(error) = self.listing_error.take() {
+                break error;
+            }
+            let (path, depth) = self.pending.pop()?;
+            let visit = self.scanner.visit(path, depth, &self.walked);
+            self.pending.extend(visit.children.into_iter().rev());
+            self.listing_error = visit.listing_error;
+            match visit.entry {
+                Some(entry) => break entry,
+                None => {
+                    self.progress.pruned += 1;
+                    self.report();
+                }
+            }
+        };
+        self.progress.entries += 1;
+        if entry.props.is_err() {
+            self.progress.errors += 1;
+        }
+        self.report();
+        Some(entry)
+    }
+}
+impl ScanIter<'_, '_> {
+    fn report(&self) {
+        if let Some(progress) = &self.scanner.progress {
+            progress(self.progress);
+        }
+    }
+}
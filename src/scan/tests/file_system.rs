@@ -0,0 +1,62 @@
+use std::path::PathBuf;
+
+use crate::error::*;
+use crate::scan::*;
+
+#[test]
+fn lists_sorted() {
+    let dir = std::env::temp_dir().join("keypropdecode_scan_listing");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("b")).unwrap();
+    std::fs::write(dir.join("c.txt"), b"").unwrap();
+    std::fs::write(dir.join("a.txt"), b"").unwrap();
+    assert_eq!(
+        FileSystem.read_dir(&dir),
+        Ok(["a.txt", "b", "c.txt"].map(|name| dir.join(name)).to_vec())
+    );
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+#[test]
+fn missing_directory() {
+    assert_eq!(
+        FileSystem.read_dir(&PathBuf::from("doesn't_exists")),
+        Err(Error {
+            kind: ErrorKind::FileNotFound
+        })
+    );
+}
+#[cfg(unix)]
+#[test]
+fn walks_the_real_file_system() {
+    use crate::backends::emulation::MetadataEmulation;
+
+    let dir = std::env::temp_dir().join("keypropdecode_scan_walk");
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    std::fs::write(dir.join("sub/.hidden"), b"").unwrap();
+    std::os::unix::fs::symlink(&dir, dir.join("sub/loop")).unwrap();
+    let entries: Vec<ScanEntry> = Scanner::new(&MetadataEmulation).iter(&dir).collect();
+    assert_eq!(entries.len(), 4);
+    assert!(entries[2].props.as_ref().unwrap().is_hidden());
+    assert!(entries[3].props.as_ref().unwrap().is_reparse());
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+#[cfg(unix)]
+#[test]
+fn links_are_followed_once() {
+    use crate::backends::{emulation::MetadataEmulation, Links};
+
+    let dir =
+        std::env::temp_dir().join(format!("keypropdecode_scan_follow_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("sub")).unwrap();
+    std::os::unix::fs::symlink(&dir, dir.join("sub/loop")).unwrap();
+    let entries: Vec<ScanEntry> = Scanner::new(&MetadataEmulation)
+        .links(Links::Follow)
+        .iter(&dir)
+        .collect();
+    std::fs::remove_dir_all(&dir).unwrap();
+    // The link is given, but the directory it points to isn't walked again
+    let paths: Vec<PathBuf> = entries.into_iter().map(|entry| entry.path).collect();
+    assert_eq!(paths, [dir.clone(), dir.join("sub"), dir.join("sub/loop")]);
+}
@@ -0,0 +1,177 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::backends::memory::MemoryStore;
+use crate::backends::AttributeSource;
+use crate::backends::Links;
+use crate::error::*;
+use crate::props::constants::*;
+use crate::scan::*;
+use crate::Props;
+
+fn store() -> MemoryStore {
+    let store = MemoryStore::new();
+    store.insert("/root/a.txt", Props::from(ARCHIVE)).unwrap();
+    store
+        .insert("/root/dir/b.txt", Props::from(ARCHIVE | HIDDEN))
+        .unwrap();
+    store
+        .insert("/root/dir/sub/c.txt", Props::from(ARCHIVE))
+        .unwrap();
+    store
+        .insert("/root/link", Props::from(DIRECTORY | REPARSE))
+        .unwrap();
+    store
+        .insert("/root/link/d.txt", Props::from(ARCHIVE))
+        .unwrap();
+    store
+        .insert("/root/system", Props::from(DIRECTORY | SYSTEM))
+        .unwrap();
+    store
+        .insert("/root/system/e.txt", Props::from(ARCHIVE))
+        .unwrap();
+    store
+}
+fn paths(entries: impl IntoIterator<Item = ScanEntry>) -> Vec<PathBuf> {
+    entries.into_iter().map(|entry| entry.path).collect()
+}
+
+#[test]
+fn depth_first_in_order() {
+    let store = store();
+    let scanner = Scanner::new(&store).listing(&store);
+    let entries: Vec<ScanEntry> = scanner.iter("/root").collect();
+    assert_eq!(
+        paths(entries),
+        [
+            "/root",
+            "/root/a.txt",
+            "/root/dir",
+            "/root/dir/b.txt",
+            "/root/dir/sub",
+            "/root/dir/sub/c.txt",
+            "/root/link",
+            "/root/system",
+            "/root/system/e.txt",
+        ]
+        .map(PathBuf::from)
+    );
+    let entry = scanner.iter("/root").nth(3).unwrap();
+    assert_eq!(entry.depth, 2);
+    assert_eq!(entry.props, Ok(Props::from(ARCHIVE | HIDDEN)));
+}
+#[test]
+fn reparse_points_on_request() {
+    let store = store();
+    let scanner = Scanner::new(&store).listing(&store).descend_reparse(true);
+    assert!(paths(scanner.iter("/root")).contains(&"/root/link/d.txt".into()));
+}
+#[test]
+fn depth_limit() {
+    let store = store();
+    let scanner = Scanner::new(&store).listing(&store).max_depth(1);
+    assert_eq!(scanner.iter("/root").count(), 5);
+    let scanner = Scanner::new(&store).listing(&store).max_depth(0);
+    assert_eq!(paths(scanner.iter("/root")), [PathBuf::from("/root")]);
+}
+#[test]
+fn pruning_and_progress() {
+    let store = store();
+    let last = Mutex::new(Progress::default());
+    let scanner = Scanner::new(&store)
+        .listing(&store)
+        .prune(|_, props| props.is_system() || props.is_hidden())
+        .progress(|progress| *last.lock().unwrap() = progress);
+    let found = paths(scanner.iter("/root"));
+    assert!(!found.contains(&"/root/system".into()));
+    assert!(!found.contains(&"/root/system/e.txt".into()));
+    assert!(!found.contains(&"/root/dir/b.txt".into()));
+    assert_eq!(
+        *last.lock().unwrap(),
+        Progress {
+            entries: 6,
+            errors: 0,
+            pruned: 2
+        }
+    );
+}
+/// Fails for the paths with one name, to check the walk goes on
+struct Failing(MemoryStore, &'static str);
+impl AttributeSource for Failing {
+    fn read(&self, path: &std::path::Path, links: Links) -> Result<Props> {
+        if path.ends_with(self.1) {
+            return Err(Error {
+                kind: ErrorKind::Other("broken".to_string()),
+            });
+        }
+        self.0.read(path, links)
+    }
+}
+#[test]
+fn errors_dont_stop_the_walk() {
+    let store = store();
+    let failing = Failing(store, "a.txt");
+    let scanner = Scanner::new(&failing).listing(&failing.0);
+    let entries: Vec<ScanEntry> = scanner.iter("/root").collect();
+    assert_eq!(entries.len(), 9);
+    assert!(entries[1].props.is_err());
+    let missing: Vec<ScanEntry> = scanner.iter("/nowhere").collect();
+    assert_eq!(
+        missing,
+        [ScanEntry {
+            path: "/nowhere".into(),
+            depth: 0,
+            props: Err(Error {
+                kind: ErrorKind::FileNotFound
+            })
+        }]
+    );
+}
+#[test]
+fn directories_without_props_are_walked() {
+    let failing = Failing(store(), "dir");
+    let scanner = Scanner::new(&failing).listing(&failing.0);
+    let entries: Vec<ScanEntry> = scanner.iter("/root").collect();
+    assert_eq!(
+        paths(scanner.iter("/root")),
+        paths(Scanner::new(&failing.0).listing(&failing.0).iter("/root"))
+    );
+    assert!(entries[2].props.is_err());
+    assert!(entries[3].props.is_ok());
+}
+#[test]
+fn panics_dont_hang_the_other_threads() {
+    let store = store();
+    let scanner = Scanner::new(&store)
+        .listing(&store)
+        .prune(|path, _| path.ends_with("sub") && panic!("prune failed"));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        scanner.par_for_each("/root", 4, |_| ())
+    }));
+    assert!(result.is_err());
+}
+#[test]
+fn parallel_finds_the_same() {
+    let store = store();
+    for _ in 0..50 {
+        store
+            .insert(
+                format!("/root/dir/many/{}.txt", store.elements().len()),
+                Props::from(ARCHIVE),
+            )
+            .unwrap();
+    }
+    let last = Mutex::new(Progress::default());
+    let scanner = Scanner::new(&store).listing(&store).progress(|progress| {
+        let mut last = last.lock().unwrap();
+        last.entries = last.entries.max(progress.entries);
+    });
+    let found = Mutex::new(Vec::new());
+    scanner.par_for_each("/root", 4, |entry| found.lock().unwrap().push(entry.path));
+    let mut found = found.into_inner().unwrap();
+    found.sort();
+    let mut expected = paths(scanner.iter("/root"));
+    expected.sort();
+    assert_eq!(found, expected);
+    assert_eq!(last.lock().unwrap().entries, expected.len());
+}
@@ -0,0 +1,2 @@
+mod file_system;
+mod memory;
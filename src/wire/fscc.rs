@@ -0,0 +1,553 @@
+//! Reads and writes the buffers of the file information classes of MS-FSCC, the ones
+//! `NtQueryInformationFile`, `NtQueryDirectoryFile` and the SMB2 `QUERY_INFO`, `SET_INFO` and
+//! `QUERY_DIRECTORY` commands use, for the classes that have the attributes.
+//!
+//! The times are `LARGE_INTEGER` FILETIME values, kept signed since `SET_INFO` gives meaning to
+//! 0 and the negative ones, and an attribute word of 0 in a `SET_INFO` means that the
+//! attributes don't change.
+//! ```
+//! use keypropdecode::props;
+//! use keypropdecode::wire::fscc::{FileInformation, FILE_ATTRIBUTE_TAG_INFORMATION};
+//!
+//! let bytes = [0x02, 0x04, 0x00, 0x00, 0x0C, 0x00, 0x00, 0xA0];
+//! let information = FileInformation::parse(FILE_ATTRIBUTE_TAG_INFORMATION, &bytes).unwrap();
+//! assert_eq!(information.props(), [props!(file: hidden, reparse)]);
+//! assert_eq!(information.to_bytes(), bytes);
+//! ```
+use super::{props_of, utf16};
+use crate::error::*;
+use crate::reader::Reader;
+use crate::Props;
+
+/// The class of [`FileBasicInformation`]
+pub const FILE_BASIC_INFORMATION: u8 = 4;
+/// The class of [`FileStandardInformation`]
+pub const FILE_STANDARD_INFORMATION: u8 = 5;
+/// The class of [`FileAllInformation`]
+pub const FILE_ALL_INFORMATION: u8 = 18;
+/// The class of [`FileNetworkOpenInformation`]
+pub const FILE_NETWORK_OPEN_INFORMATION: u8 = 34;
+/// The class of [`FileAttributeTagInformation`]
+pub const FILE_ATTRIBUTE_TAG_INFORMATION: u8 = 35;
+/// The class of [`FileIdBothDirectoryInformation`]
+pub const FILE_ID_BOTH_DIRECTORY_INFORMATION: u8 = 37;
+/// The class of [`FileStatInformation`]
+pub const FILE_STAT_INFORMATION: u8 = 68;
+
+/// The four times of an element, as FILETIME values
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct FsccTimes {
+    /// When the element was created
+    pub creation: i64,
+    /// When the element was last read or written
+    pub last_access: i64,
+    /// When the data was last written
+    pub last_write: i64,
+    /// When the data or the metadata was last changed
+    pub change: i64,
+}
+impl FsccTimes {
+    fn read(reader: &mut Reader) -> Result<Self> {
+        Ok(FsccTimes {
+            creation: reader.u64()? as i64,
+            last_access: reader.u64()? as i64,
+            last_write: reader.u64()? as i64,
+            change: reader.u64()? as i64,
+        })
+    }
+    fn write(&self, bytes: &mut Vec<u8>) {
+        for time in [
+            self.creation,
+            self.last_access,
+            self.last_write,
+            self.change,
+        ] {
+            bytes.extend(time.to_le_bytes());
+        }
+    }
+}
+
+/// `FILE_BASIC_INFORMATION`
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct FileBasicInformation {
+    /// The times
+    pub times: FsccTimes,
+    /// The attribute word
+    pub attributes: u32,
+}
+impl FileBasicInformation {
+    /// The size of the buffer
+    pub const SIZE: usize = 40;
+    /// Parses the buffer. Some clients send it without the last 4 reserved bytes.
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes, "FileBasicInformation");
+        Ok(FileBasicInformation {
+            times: FsccTimes::read(&mut reader)?,
+            attributes: reader.u32()?,
+        })
+    }
+    /// Writes the buffer
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::SIZE);
+        self.times.write(&mut bytes);
+        bytes.extend(self.attributes.to_le_bytes());
+        bytes.extend([0; 4]);
+        bytes
+    }
+    /// Gets the Props of the attributes
+    pub fn props(&self) -> Props {
+        props_of(self.attributes)
+    }
+}
+
+/// `FILE_STANDARD_INFORMATION`, which only tells if the element is a directory
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct FileStandardInformation {
+    /// The space the data takes
+    pub allocation_size: i64,
+    /// The size of the data
+    pub end_of_file: i64,
+    /// The number of hard links
+    pub number_of_links: u32,
+    /// True when the element will be deleted when it's closed
+    pub delete_pending: bool,
+    /// True for directories
+    pub directory: bool,
+}
+impl FileStandardInformation {
+    /// The size of the buffer
+    pub const SIZE: usize = 24;
+    /// Parses the buffer
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes, "FileStandardInformation");
+        Ok(FileStandardInformation {
+            allocation_size: reader.u64()? as i64,
+            end_of_file: reader.u64()? as i64,
+            number_of_links: reader.u32()?,
+            delete_pending: reader.take(1)?[0] != 0,
+            directory: reader.take(1)?[0] != 0,
+        })
+    }
+    /// Writes the buffer
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::SIZE);
+        bytes.extend(self.allocation_size.to_le_bytes());
+        bytes.extend(self.end_of_file.to_le_bytes());
+        bytes.extend(self.number_of_links.to_le_bytes());
+        bytes.extend([self.delete_pending as u8, self.directory as u8, 0, 0]);
+        bytes
+    }
+}
+
+/// `FILE_NETWORK_OPEN_INFORMATION`
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct FileNetworkOpenInformation {
+    /// The times
+    pub times: FsccTimes,
+    /// The space the data takes
+    pub allocation_size: i64,
+    /// The size of the data
+    pub end_of_file: i64,
+    /// The attribute word
+    pub attributes: u32,
+}
+impl FileNetworkOpenInformation {
+    /// The size of the buffer
+    pub const SIZE: usize = 56;
+    /// Parses the buffer
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes, "FileNetworkOpenInformation");
+        Ok(FileNetworkOpenInformation {
+            times: FsccTimes::read(&mut reader)?,
+            allocation_size: reader.u64()? as i64,
+            end_of_file: reader.u64()? as i64,
+            attributes: reader.u32()?,
+        })
+    }
+    /// Writes the buffer
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(Self::SIZE);
+        self.times.write(&mut bytes);
+        bytes.extend(self.allocation_size.to_le_bytes());
+        bytes.extend(self.end_of_file.to_le_bytes());
+        bytes.extend(self.attributes.to_le_bytes());
+        bytes.extend([0; 4]);
+        bytes
+    }
+    /// Gets the Props of the attributes
+    pub fn props(&self) -> Props {
+        props_of(self.attributes)
+    }
+}
+
+/// `FILE_ATTRIBUTE_TAG_INFORMATION`
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct FileAttributeTagInformation {
+    /// The attribute word
+    pub attributes: u32,
+    /// The reparse tag, only meaningful when the element is a reparse point
+    pub reparse_tag: u32,
+}
+impl FileAttributeTagInformation {
+    /// The size of the buffer
+    pub const SIZE: usize = 8;
+    /// Parses the buffer
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes, "FileAttributeTagInformation");
+        Ok(FileAttributeTagInformation {
+            attributes: reader.u32()?,
+            reparse_tag: reader.u32()?,
+        })
+    }
+    /// Writes the buffer
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.attributes.to_le_bytes().to_vec();
+        bytes.extend(self.reparse_tag.to_le_bytes());
+        bytes
+    }
+    /// Gets the Props of the attributes
+    pub fn props(&self) -> Props {
+        props_of(self.attributes)
+    }
+}
+
+/// `FILE_STAT_INFORMATION`
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct FileStatInformation {
+    /// The file reference number
+    pub file_id: i64,
+    /// The times
+    pub times: FsccTimes,
+    /// The space the data takes
+    pub allocation_size: i64,
+    /// The size of the data
+    pub end_of_file: i64,
+    /// The attribute word
+    pub attributes: u32,
+    /// The reparse tag, only meaningful when the element is a reparse point
+    pub reparse_tag: u32,
+    /// The number of hard links
+    pub number_of_links: u32,
+    /// The access mask the caller has
+    pub effective_access: u32,
+}
+impl FileStatInformation {
+    /// The size of the buffer
+    pub const SIZE: usize = 72;
+    /// Parses the buffer
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(bytes, "FileStatInformation");
+        Ok(FileStatInformation {
+            file_id: reader.u64()? as i64,
+            times: FsccTimes::read(&mut reader)?,
+            allocation_size: reader.u64()? as i64,
+            end_of_file: reader.u64()? as i64,
+            attributes: reader.u32()?,
+            reparse_tag: reader.u32()?,
+            number_of_links: reader.u32()?,
+            effective_access: reader.u32()?,
+        })
+    }
+    /// Writes the buffer
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.file_id.to_le_bytes().to_vec();
+        self.times.write(&mut bytes);
+        bytes.extend(self.allocation_size.to_le_bytes());
+        bytes.extend(self.end_of_file.to_le_bytes());
+        for value in [
+            self.attributes,
+            self.reparse_tag,
+            self.number_of_links,
+            self.effective_access,
+        ] {
+            bytes.extend(value.to_le_bytes());
+        }
+        bytes
+    }
+    /// Gets the Props of the attributes
+    pub fn props(&self) -> Props {
+        props_of(self.attributes)
+    }
+}
+
+/// `FILE_ALL_INFORMATION`, many classes one after the other
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct FileAllInformation {
+    /// The times and the attributes
+    pub basic: FileBasicInformation,
+    /// The sizes and whether the element is a directory
+    pub standard: FileStandardInformation,
+    /// `FileInternalInformation`, the file reference number
+    pub index_number: i64,
+    /// `FileEaInformation`, the size of the extended attributes
+    pub ea_size: u32,
+    /// `FileAccessInformation`, the access mask of the handle
+    pub access_flags: u32,
+    /// `FilePositionInformation`, the position of the handle
+    pub current_byte_offset: i64,
+    /// `FileModeInformation`
+    pub mode: u32,
+    /// `FileAlignmentInformation`
+    pub alignment_requirement: u32,
+    /// `FileNameInformation`, the path from the root of the share or the volume
+    pub name: String,
+}
+impl FileAllInformation {
+    /// Parses the buffer. The name can be cut when the buffer of the caller was too small.
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let what = "FileAllInformation";
+        let basic = FileBasicInformation::parse(bytes.get(..40).unwrap_or(bytes))?;
+        let standard = FileStandardInformation::parse(bytes.get(40..).unwrap_or_default())?;
+        let mut reader = Reader::new(bytes, what);
+        reader.skip(64)?;
+        let index_number = reader.u64()? as i64;
+        let ea_size = reader.u32()?;
+        let access_flags = reader.u32()?;
+        let current_byte_offset = reader.u64()? as i64;
+        let mode = reader.u32()?;
+        let alignment_requirement = reader.u32()?;
+        let name_length = reader.u32()? as usize;
+        let name = reader.take(name_length.min(reader.remaining()) & !1)?;
+        Ok(FileAllInformation {
+            basic,
+            standard,
+            index_number,
+            ea_size,
+            access_flags,
+            current_byte_offset,
+            mode,
+            alignment_requirement,
+            name: utf16(name),
+        })
+    }
+    /// Writes the buffer
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.basic.to_bytes();
+        bytes.extend(self.standard.to_bytes());
+        bytes.extend(self.index_number.to_le_bytes());
+        bytes.extend(self.ea_size.to_le_bytes());
+        bytes.extend(self.access_flags.to_le_bytes());
+        bytes.extend(self.current_byte_offset.to_le_bytes());
+        bytes.extend(self.mode.to_le_bytes());
+        bytes.extend(self.alignment_requirement.to_le_bytes());
+        let name = to_utf16(&self.name);
+        bytes.extend((name.len() as u32).to_le_bytes());
+        bytes.extend(name);
+        bytes
+    }
+    /// Gets the Props of the attributes
+    pub fn props(&self) -> Props {
+        self.basic.props()
+    }
+}
+
+/// An entry of a `FILE_ID_BOTH_DIR_INFORMATION` listing
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct FileIdBothDirectoryInformation {
+    /// The position of the entry in the directory, which is 0 on most file systems
+    pub file_index: u32,
+    /// The times
+    pub times: FsccTimes,
+    /// The size of the data
+    pub end_of_file: i64,
+    /// The space the data takes
+    pub allocation_size: i64,
+    /// The attribute word
+    pub attributes: u32,
+    /// The size of the extended attributes or, for reparse points, the reparse tag
+    pub ea_size: u32,
+    /// The 8.3 name, empty when the name is already one
+    pub short_name: String,
+    /// The file reference number
+    pub file_id: i64,
+    /// The name
+    pub name: String,
+}
+impl FileIdBothDirectoryInformation {
+    /// The size of an entry without its name
+    pub const SIZE: usize = 104;
+    /// Parses the entries of a listing, following the offsets to the next ones
+    pub fn parse_all(bytes: &[u8]) -> Result<Vec<Self>> {
+        let mut entries = Vec::new();
+        let mut offset = 0;
+        loop {
+            let entry = bytes.get(offset..).unwrap_or_default();
+            let mut reader = Reader::new(entry, "FileIdBothDirectoryInformation");
+            let next = reader.u32()? as usize;
+            entries.push(Self::parse(entry)?);
+            if next == 0 {
+                return Ok(entries);
+            }
+            offset += next;
+        }
+    }
+    /// Parses the entry at the start of `bytes`
+    pub fn parse(bytes: &[u8]) -> Result<Self> {
+        let what = "FileIdBothDirectoryInformation";
+        let mut reader = Reader::new(bytes, what);
+        reader.skip(4)?;
+        let file_index = reader.u32()?;
+        let times = FsccTimes::read(&mut reader)?;
+        let end_of_file = reader.u64()? as i64;
+        let allocation_size = reader.u64()? as i64;
+        let attributes = reader.u32()?;
+        let name_length = reader.u32()? as usize;
+        let ea_size = reader.u32()?;
+        let short_name_length = (reader.take(2)?[0] as usize).min(24);
+        let short_name = reader.take(24)?;
+        reader.skip(2)?;
+        let file_id = reader.u64()? as i64;
+        Ok(FileIdBothDirectoryInformation {
+            file_index,
+            times,
+            end_of_file,
+            allocation_size,
+            attributes,
+            ea_size,
+            short_name: utf16(&short_name[..short_name_length]),
+            file_id,
+            name: utf16(reader.take(name_length)?),
+        })
+    }
+    /// Writes the entries as a listing, each of them aligned to 8 bytes
+    pub fn to_bytes_all(entries: &[Self]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        for (index, entry) in entries.iter().enumerate() {
+            let start = bytes.len();
+            bytes.extend(entry.to_bytes());
+            if index + 1 < entries.len() {
+                bytes.resize(bytes.len().next_multiple_of(8), 0);
+                let next = (bytes.len() - start) as u32;
+                bytes[start..start + 4].copy_from_slice(&next.to_le_bytes());
+            }
+        }
+        bytes
+    }
+    /// Writes the entry alone, with an offset to the next one of 0
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let name = to_utf16(&self.name);
+        let mut short_name = to_utf16(&self.short_name);
+        short_name.truncate(24);
+        let mut bytes = 0u32.to_le_bytes().to_vec();
+        bytes.extend(self.file_index.to_le_bytes());
+        self.times.write(&mut bytes);
+        bytes.extend(self.end_of_file.to_le_bytes());
+        bytes.extend(self.allocation_size.to_le_bytes());
+        bytes.extend(self.attributes.to_le_bytes());
+        bytes.extend((name.len() as u32).to_le_bytes());
+        bytes.extend(self.ea_size.to_le_bytes());
+        bytes.extend([short_name.len() as u8, 0]);
+        bytes.extend(&short_name);
+        bytes.resize(96, 0);
+        bytes.extend(self.file_id.to_le_bytes());
+        bytes.extend(name);
+        bytes
+    }
+    /// Gets the Props of the attributes
+    pub fn props(&self) -> Props {
+        props_of(self.attributes)
+    }
+    /// Gets the reparse tag of reparse points, which is where the size of the extended
+    /// attributes would be
+    pub fn reparse_tag(&self) -> Option<u32> {
+        (self.attributes & crate::props::constants::REPARSE != 0).then_some(self.ea_size)
+    }
+}
+
+/// The buffer of one of the classes, to handle them without knowing which one it is
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum FileInformation {
+    /// [`FILE_BASIC_INFORMATION`]
+    Basic(FileBasicInformation),
+    /// [`FILE_STANDARD_INFORMATION`]
+    Standard(FileStandardInformation),
+    /// [`FILE_ALL_INFORMATION`]
+    All(FileAllInformation),
+    /// [`FILE_NETWORK_OPEN_INFORMATION`]
+    NetworkOpen(FileNetworkOpenInformation),
+    /// [`FILE_ATTRIBUTE_TAG_INFORMATION`]
+    AttributeTag(FileAttributeTagInformation),
+    /// [`FILE_ID_BOTH_DIRECTORY_INFORMATION`], a whole listing
+    IdBothDirectory(Vec<FileIdBothDirectoryInformation>),
+    /// [`FILE_STAT_INFORMATION`]
+    Stat(FileStatInformation),
+}
+impl FileInformation {
+    /// Parses the buffer of the class `class`. The other classes give a `NotSupported` error.
+    pub fn parse(class: u8, bytes: &[u8]) -> Result<Self> {
+        Ok(match class {
+            FILE_BASIC_INFORMATION => FileInformation::Basic(FileBasicInformation::parse(bytes)?),
+            FILE_STANDARD_INFORMATION => {
+                FileInformation::Standard(FileStandardInformation::parse(bytes)?)
+            }
+            FILE_ALL_INFORMATION => FileInformation::All(FileAllInformation::parse(bytes)?),
+            FILE_NETWORK_OPEN_INFORMATION => {
+                FileInformation::NetworkOpen(FileNetworkOpenInformation::parse(bytes)?)
+            }
+            FILE_ATTRIBUTE_TAG_INFORMATION => {
+                FileInformation::AttributeTag(FileAttributeTagInformation::parse(bytes)?)
+            }
+            FILE_ID_BOTH_DIRECTORY_INFORMATION => {
+                FileInformation::IdBothDirectory(FileIdBothDirectoryInformation::parse_all(bytes)?)
+            }
+            FILE_STAT_INFORMATION => FileInformation::Stat(FileStatInformation::parse(bytes)?),
+            _ => {
+                return Err(Error {
+                    kind: ErrorKind::NotSupported(format!(
+                        "The file information class {} doesn't have the attributes",
+                        class
+                    )),
+                })
+            }
+        })
+    }
+    /// Gets the class of the buffer
+    pub fn class(&self) -> u8 {
+        match self {
+            FileInformation::Basic(_) => FILE_BASIC_INFORMATION,
+            FileInformation::Standard(_) => FILE_STANDARD_INFORMATION,
+            FileInformation::All(_) => FILE_ALL_INFORMATION,
+            FileInformation::NetworkOpen(_) => FILE_NETWORK_OPEN_INFORMATION,
+            FileInformation::AttributeTag(_) => FILE_ATTRIBUTE_TAG_INFORMATION,
+            FileInformation::IdBothDirectory(_) => FILE_ID_BOTH_DIRECTORY_INFORMATION,
+            FileInformation::Stat(_) => FILE_STAT_INFORMATION,
+        }
+    }
+    /// Writes the buffer
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            FileInformation::Basic(information) => information.to_bytes(),
+            FileInformation::Standard(information) => information.to_bytes(),
+            FileInformation::All(information) => information.to_bytes(),
+            FileInformation::NetworkOpen(information) => information.to_bytes(),
+            FileInformation::AttributeTag(information) => information.to_bytes(),
+            FileInformation::IdBothDirectory(entries) => {
+                FileIdBothDirectoryInformation::to_bytes_all(entries)
+            }
+            FileInformation::Stat(information) => information.to_bytes(),
+        }
+    }
+    /// Gets the attribute words in the buffer: one for most classes, one for every entry of a
+    /// listing and none for `FileStandardInformation`
+    pub fn attributes(&self) -> Vec<u32> {
+        match self {
+            FileInformation::Basic(information) => vec![information.attributes],
+            FileInformation::Standard(_) => Vec::new(),
+            FileInformation::All(information) => vec![information.basic.attributes],
+            FileInformation::NetworkOpen(information) => vec![information.attributes],
+            FileInformation::AttributeTag(information) => vec![information.attributes],
+            FileInformation::IdBothDirectory(entries) => {
+                entries.iter().map(|entry| entry.attributes).collect()
+            }
+            FileInformation::Stat(information) => vec![information.attributes],
+        }
+    }
+    /// Gets the Props of every attribute word in the buffer
+    pub fn props(&self) -> Vec<Props> {
+        self.attributes().into_iter().map(props_of).collect()
+    }
+}
+
+fn to_utf16(text: &str) -> Vec<u8> {
+    text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
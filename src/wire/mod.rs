@@ -0,0 +1,58 @@
+//! Windows APIs and the protocols that carry file information pass the attributes around in
+//! fixed binary structures. These modules decode them from captured bytes, so the captures can
+//! be analyzed anywhere.
+/// The buffers of the file information classes of MS-FSCC
+pub mod fscc;
+/// The attributes in SMB2 messages
+pub mod smb2;
+#[cfg(test)]
+mod tests;
+/// The structures the Win32 file information functions fill
+pub mod win32;
+
+use crate::error::*;
+use crate::Props;
+
+/// Fails unless `bytes` has at least `size` bytes
+pub(crate) fn check_size(bytes: &[u8], size: usize, what: &str) -> Result<()> {
+    match bytes.len() >= size {
+        true => Ok(()),
+        false => Err(crate::archive::truncated(what)),
+    }
+}
+/// Reads the `u32` at `offset`, which must be inside `bytes`
+pub(crate) fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+/// Reads the `u64` at `offset`, which must be inside `bytes`, like a FILETIME
+pub(crate) fn u64_at(bytes: &[u8], offset: usize) -> u64 {
+    u32_at(bytes, offset) as u64 | (u32_at(bytes, offset + 4) as u64) << 32
+}
+/// Gets the UTF-16 characters of `bytes` up to the first nul one
+pub(crate) fn utf16_until_nul(bytes: &[u8]) -> impl Iterator<Item = u16> + '_ {
+    bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .take_while(|character| *character != 0)
+}
+/// Decodes UTF-16 text, replacing the characters that aren't valid
+pub(crate) fn utf16(bytes: &[u8]) -> String {
+    let characters: Vec<u16> = bytes
+        .chunks_exact(2)
+        .map(|pair| u16::from_le_bytes([pair[0], pair[1]]))
+        .collect();
+    String::from_utf16_lossy(&characters)
+}
+/// Gets the Props of an attribute word Windows gave, which doesn't have the archive bit on
+/// every file
+pub(crate) fn props_of(attributes: u32) -> Props {
+    Props::from(crate::backends::with_element_type(
+        attributes,
+        attributes & crate::props::constants::DIRECTORY != 0,
+    ))
+}
@@ -0,0 +1,520 @@
+//! Decodes the SMB2 messages that carry attributes from captured payloads: the `CREATE`
+//! requests, with the attributes a new element gets, and their responses, the `CLOSE` responses
+//! with the attributes after the handle was closed, the buffers of `QUERY_INFO` and `SET_INFO`
+//! for the file information classes, and the listings of `QUERY_DIRECTORY`.
+//!
+//! The responses of `QUERY_INFO` and `QUERY_DIRECTORY` don't say which class they have, so
+//! [`Smb2Decoder`](crate::wire::smb2::Smb2Decoder) remembers the requests by their message
+//! identifier. Give it every message of a connection, in order, to decode the responses.
+//! ```no_run
+//! use keypropdecode::wire::smb2::Smb2Decoder;
+//!
+//! let mut decoder = Smb2Decoder::default();
+//! # let payloads: Vec<Vec<u8>> = Vec::new();
+//! for payload in payloads {
+//!     for message in decoder.decode(&payload).unwrap() {
+//!         println!("{:#x} {:?} {:?}", message.command, message.response, message.props());
+//!     }
+//! }
+//! ```
+use std::collections::HashMap;
+
+use super::fscc::{FileInformation, FileNetworkOpenInformation, FsccTimes};
+use super::{props_of, utf16};
+use crate::error::*;
+use crate::image::invalid;
+use crate::reader::Reader;
+use crate::Props;
+
+/// The `CREATE` command
+pub const SMB2_CREATE: u16 = 0x05;
+/// The `CLOSE` command
+pub const SMB2_CLOSE: u16 = 0x06;
+/// The `QUERY_DIRECTORY` command
+pub const SMB2_QUERY_DIRECTORY: u16 = 0x0E;
+/// The `QUERY_INFO` command
+pub const SMB2_QUERY_INFO: u16 = 0x10;
+/// The `SET_INFO` command
+pub const SMB2_SET_INFO: u16 = 0x11;
+/// The info type of the file information classes in `QUERY_INFO` and `SET_INFO`
+pub const SMB2_0_INFO_FILE: u8 = 0x01;
+/// The flag of `CLOSE` that asks for the attributes in the response
+pub const SMB2_CLOSE_FLAG_POSTQUERY_ATTRIB: u16 = 0x0001;
+
+const WHAT: &str = "SMB2 message";
+const HEADER_SIZE: usize = 64;
+/// The flag of the header of responses
+const SMB2_FLAGS_SERVER_TO_REDIR: u32 = 0x0000_0001;
+/// The flag of the header of asynchronous messages, which have no tree identifier
+const SMB2_FLAGS_ASYNC_COMMAND: u32 = 0x0000_0002;
+/// The status of a `QUERY_INFO` response whose buffer was too small, which still has data
+const STATUS_BUFFER_OVERFLOW: u32 = 0x8000_0005;
+/// The status of the interim response the server sends before going asynchronous
+const STATUS_PENDING: u32 = 0x0000_0103;
+/// The class of the listings without attributes
+const FILE_NAMES_INFORMATION: u8 = 12;
+/// The requests remembered at once, the credits Windows grants at most
+const MAX_PENDING: usize = 8192;
+
+/// The identifier of an open handle
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash, Default)]
+pub struct Smb2FileId {
+    /// The part that survives reconnections
+    pub persistent: u64,
+    /// The part that only lasts while the connection does
+    pub volatile: u64,
+}
+impl Smb2FileId {
+    fn read(reader: &mut Reader) -> Result<Self> {
+        Ok(Smb2FileId {
+            persistent: reader.u64()?,
+            volatile: reader.u64()?,
+        })
+    }
+}
+
+/// What a message says about attributes
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Smb2Content {
+    /// A `CREATE` request
+    CreateRequest {
+        /// The path, relative to the share
+        name: String,
+        /// The attributes the element gets if it's created, 0 for the default ones
+        attributes: u32,
+        /// The access the client asked for
+        desired_access: u32,
+        /// What to do if the element exists or doesn't
+        create_disposition: u32,
+        /// The `FILE_*` options, like `FILE_DIRECTORY_FILE`
+        create_options: u32,
+    },
+    /// A `CREATE` response
+    CreateResponse {
+        /// The handle that was opened
+        file_id: Smb2FileId,
+        /// Whether the element was opened, created or overwritten
+        create_action: u32,
+        /// The times, sizes and attributes of the element
+        information: FileNetworkOpenInformation,
+    },
+    /// A `CLOSE` request
+    CloseRequest {
+        /// The handle to close
+        file_id: Smb2FileId,
+        /// True when the client asked for the attributes in the response
+        postquery: bool,
+    },
+    /// A `CLOSE` response
+    CloseResponse {
+        /// The times, sizes and attributes of the element, if the client asked for them
+        information: Option<FileNetworkOpenInformation>,
+    },
+    /// A `QUERY_INFO` request
+    QueryInfoRequest {
+        /// The handle of the element
+        file_id: Smb2FileId,
+        /// The kind of information, like [`SMB2_0_INFO_FILE`]
+        info_type: u8,
+        /// The class of the information
+        class: u8,
+    },
+    /// A `QUERY_INFO` response
+    QueryInfoResponse {
+        /// The class of the request, if it was seen
+        class: Option<u8>,
+        /// The buffer, if its class has the attributes and was known
+        information: Option<FileInformation>,
+    },
+    /// A `SET_INFO` request
+    SetInfoRequest {
+        /// The handle of the element
+        file_id: Smb2FileId,
+        /// The kind of information, like [`SMB2_0_INFO_FILE`]
+        info_type: u8,
+        /// The class of the information
+        class: u8,
+        /// The buffer, if its class has the attributes
+        information: Option<FileInformation>,
+    },
+    /// A `QUERY_DIRECTORY` request
+    QueryDirectoryRequest {
+        /// The handle of the directory
+        file_id: Smb2FileId,
+        /// The class of the entries
+        class: u8,
+        /// The pattern of the names to list
+        pattern: String,
+    },
+    /// A `QUERY_DIRECTORY` response
+    QueryDirectoryResponse {
+        /// The class of the request, if it was seen
+        class: Option<u8>,
+        /// The attributes of every entry
+        attributes: Vec<u32>,
+        /// The whole listing, for the classes that are decoded
+        information: Option<FileInformation>,
+    },
+    /// Any other command, an error response or an interim one
+    Other,
+    /// A message whose header was read but whose body isn't valid
+    Malformed {
+        /// Why the body couldn't be decoded
+        error: String,
+    },
+}
+
+/// A message of a payload
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Smb2Message {
+    /// The command, like [`SMB2_CREATE`]
+    pub command: u16,
+    /// True for the responses of the server
+    pub response: bool,
+    /// The status of responses, 0 for success
+    pub status: u32,
+    /// The identifier that pairs the request with its response
+    pub message_id: u64,
+    /// The session
+    pub session_id: u64,
+    /// The share, unless the message is asynchronous
+    pub tree_id: Option<u32>,
+    /// What the message says about attributes
+    pub content: Smb2Content,
+}
+impl Smb2Message {
+    /// Gets the attribute words of the message. Requests only have the ones the client gave:
+    /// a 0 in a `CREATE` or a `SET_INFO` means the client didn't give any.
+    pub fn attributes(&self) -> Vec<u32> {
+        match &self.content {
+            Smb2Content::CreateRequest { attributes, .. } => [*attributes]
+                .into_iter()
+                .filter(|word| *word != 0)
+                .collect(),
+            Smb2Content::CreateResponse { information, .. } => vec![information.attributes],
+            Smb2Content::CloseResponse {
+                information: Some(information),
+            } => vec![information.attributes],
+            Smb2Content::QueryInfoResponse {
+                information: Some(information),
+                ..
+            } => information.attributes(),
+            Smb2Content::SetInfoRequest {
+                information: Some(information),
+                ..
+            } => information
+                .attributes()
+                .into_iter()
+                .filter(|word| *word != 0)
+                .collect(),
+            Smb2Content::QueryDirectoryResponse { attributes, .. } => attributes.clone(),
+            _ => Vec::new(),
+        }
+    }
+    /// Gets the Props of every attribute word of the message
+    pub fn props(&self) -> Vec<Props> {
+        self.attributes().into_iter().map(props_of).collect()
+    }
+}
+
+/// Decodes the messages of a connection, pairing the responses with their requests
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Smb2Decoder {
+    /// The class of the `QUERY_INFO` and `QUERY_DIRECTORY` requests without a response yet.
+    /// Captures of only one direction never have the responses, so the oldest are forgotten.
+    pending: HashMap<u64, u8>,
+}
+impl Smb2Decoder {
+    /// Decodes every message of a payload, which can have several compounded and can start
+    /// with the 4 bytes of the Direct TCP transport.
+    /// A message with a valid header but a malformed body is given as
+    /// [`Smb2Content::Malformed`]. When a message can't even be framed, the ones before it are
+    /// still given, and only a payload whose first message can't be framed gives an error.
+    pub fn decode(&mut self, payload: &[u8]) -> Result<Vec<Smb2Message>> {
+        let payload = match payload.get(4..8) {
+            Some(b"\xFESMB") if payload[0] == 0 => &payload[4..],
+            _ => payload,
+        };
+        let mut messages = Vec::new();
+        let mut offset = 0;
+        loop {
+            match self.next_message(&payload[offset..]) {
+                Ok((message, next)) => {
+                    messages.push(message);
+                    if next == 0 {
+                        return Ok(messages);
+                    }
+                    offset += next;
+                }
+                Err(error) if messages.is_empty() => return Err(error),
+                Err(_) => return Ok(messages),
+            }
+        }
+    }
+    /// Decodes the message at the start of `rest`, giving it with the offset of the next one
+    fn next_message(&mut self, rest: &[u8]) -> Result<(Smb2Message, usize)> {
+        match rest.get(..4) {
+            Some(b"\xFESMB") => (),
+            Some(b"\xFDSMB") | Some(b"\xFCSMB") => {
+                return Err(Error {
+                    kind: ErrorKind::NotSupported(
+                        "Encrypted and compressed SMB2 messages can't be decoded".to_string(),
+                    ),
+                })
+            }
+            _ => {
+                return Err(invalid(
+                    "The SMB2 message doesn't start with its protocol id",
+                ))
+            }
+        }
+        let next = match rest.get(20..24) {
+            Some(next) => u32::from_le_bytes([next[0], next[1], next[2], next[3]]) as usize,
+            None => return Err(crate::archive::truncated(WHAT)),
+        };
+        if next != 0 && (next < HEADER_SIZE || next > rest.len()) {
+            return Err(invalid("The SMB2 message has an invalid next command"));
+        }
+        let message = match next {
+            0 => rest,
+            _ => &rest[..next],
+        };
+        Ok((self.message(message)?, next))
+    }
+    /// Decodes one message, with its header
+    fn message(&mut self, message: &[u8]) -> Result<Smb2Message> {
+        let mut header = Reader::new(message, WHAT);
+        header.skip(8)?;
+        let status = header.u32()?;
+        let command = header.u16()?;
+        header.skip(2)?;
+        let flags = header.u32()?;
+        header.skip(4)?;
+        let message_id = header.u64()?;
+        header.skip(4)?;
+        let tree_id = header.u32()?;
+        let session_id = header.u64()?;
+        header.skip(16)?;
+        let response = flags & SMB2_FLAGS_SERVER_TO_REDIR != 0;
+        let content = self
+            .content(message, command, response, status, flags, message_id)
+            .unwrap_or_else(|error| Smb2Content::Malformed {
+                error: error.to_string(),
+            });
+        Ok(Smb2Message {
+            command,
+            response,
+            status,
+            message_id,
+            session_id,
+            tree_id: (flags & SMB2_FLAGS_ASYNC_COMMAND == 0).then_some(tree_id),
+            content,
+        })
+    }
+    /// Decodes the body of a message
+    fn content(
+        &mut self,
+        message: &[u8],
+        command: u16,
+        response: bool,
+        status: u32,
+        flags: u32,
+        message_id: u64,
+    ) -> Result<Smb2Content> {
+        let mut body = Reader::new(&message[HEADER_SIZE..], WHAT);
+        // The final response of an asynchronous command comes after an interim one, and has the
+        // same message identifier
+        let interim = response && status == STATUS_PENDING && flags & SMB2_FLAGS_ASYNC_COMMAND != 0;
+        let failed = response && status != 0 && status != STATUS_BUFFER_OVERFLOW;
+        Ok(match (command, response) {
+            _ if interim => Smb2Content::Other,
+            _ if failed => {
+                self.pending.remove(&message_id);
+                Smb2Content::Other
+            }
+            (SMB2_CREATE, false) => {
+                body.skip(24)?;
+                let desired_access = body.u32()?;
+                let attributes = body.u32()?;
+                body.skip(4)?;
+                let create_disposition = body.u32()?;
+                let create_options = body.u32()?;
+                let name_offset = body.u16()? as usize;
+                let name_length = body.u16()? as usize;
+                Smb2Content::CreateRequest {
+                    name: utf16(buffer(message, name_offset, name_length)?),
+                    attributes,
+                    desired_access,
+                    create_disposition,
+                    create_options,
+                }
+            }
+            (SMB2_CREATE, true) => {
+                body.skip(4)?;
+                let create_action = body.u32()?;
+                let information = network_open(&mut body)?;
+                body.skip(4)?;
+                Smb2Content::CreateResponse {
+                    file_id: Smb2FileId::read(&mut body)?,
+                    create_action,
+                    information,
+                }
+            }
+            (SMB2_CLOSE, false) => {
+                body.skip(2)?;
+                let flags = body.u16()?;
+                body.skip(4)?;
+                Smb2Content::CloseRequest {
+                    file_id: Smb2FileId::read(&mut body)?,
+                    postquery: flags & SMB2_CLOSE_FLAG_POSTQUERY_ATTRIB != 0,
+                }
+            }
+            (SMB2_CLOSE, true) => {
+                body.skip(2)?;
+                let flags = body.u16()?;
+                body.skip(4)?;
+                let information = network_open(&mut body)?;
+                Smb2Content::CloseResponse {
+                    information: (flags & SMB2_CLOSE_FLAG_POSTQUERY_ATTRIB != 0)
+                        .then_some(information),
+                }
+            }
+            (SMB2_QUERY_INFO, false) => {
+                body.skip(2)?;
+                let info_type = body.take(1)?[0];
+                let class = body.take(1)?[0];
+                body.skip(20)?;
+                if info_type == SMB2_0_INFO_FILE {
+                    self.remember(message_id, class);
+                }
+                Smb2Content::QueryInfoRequest {
+                    file_id: Smb2FileId::read(&mut body)?,
+                    info_type,
+                    class,
+                }
+            }
+            (SMB2_QUERY_INFO, true) => {
+                let class = self.pending.remove(&message_id);
+                let output = output_buffer(message, &mut body)?;
+                Smb2Content::QueryInfoResponse {
+                    class,
+                    information: class.and_then(|class| FileInformation::parse(class, output).ok()),
+                }
+            }
+            (SMB2_SET_INFO, false) => {
+                body.skip(2)?;
+                let info_type = body.take(1)?[0];
+                let class = body.take(1)?[0];
+                let length = body.u32()? as usize;
+                let offset = body.u16()? as usize;
+                body.skip(6)?;
+                let information = match info_type {
+                    SMB2_0_INFO_FILE => {
+                        FileInformation::parse(class, buffer(message, offset, length)?).ok()
+                    }
+                    _ => None,
+                };
+                Smb2Content::SetInfoRequest {
+                    file_id: Smb2FileId::read(&mut body)?,
+                    info_type,
+                    class,
+                    information,
+                }
+            }
+            (SMB2_QUERY_DIRECTORY, false) => {
+                body.skip(2)?;
+                let class = body.take(1)?[0];
+                body.skip(5)?;
+                let file_id = Smb2FileId::read(&mut body)?;
+                let offset = body.u16()? as usize;
+                let length = body.u16()? as usize;
+                self.remember(message_id, class);
+                Smb2Content::QueryDirectoryRequest {
+                    file_id,
+                    class,
+                    pattern: utf16(buffer(message, offset, length)?),
+                }
+            }
+            (SMB2_QUERY_DIRECTORY, true) => {
+                let class = self.pending.remove(&message_id);
+                let output = output_buffer(message, &mut body)?;
+                let (attributes, information) = match class {
+                    Some(FILE_NAMES_INFORMATION) | None => (Vec::new(), None),
+                    Some(class) => (
+                        directory_attributes(output)?,
+                        FileInformation::parse(class, output).ok(),
+                    ),
+                };
+                Smb2Content::QueryDirectoryResponse {
+                    class,
+                    attributes,
+                    information,
+                }
+            }
+            _ => Smb2Content::Other,
+        })
+    }
+    /// Keeps the class of a request until its response, forgetting the oldest request when
+    /// there are too many
+    fn remember(&mut self, message_id: u64, class: u8) {
+        if self.pending.len() >= MAX_PENDING && !self.pending.contains_key(&message_id) {
+            if let Some(oldest) = self.pending.keys().min().copied() {
+                self.pending.remove(&oldest);
+            }
+        }
+        self.pending.insert(message_id, class);
+    }
+}
+
+/// Reads the times, sizes and attributes `CREATE` and `CLOSE` responses share
+fn network_open(body: &mut Reader) -> Result<FileNetworkOpenInformation> {
+    let mut times = [0; 4];
+    for time in &mut times {
+        *time = body.u64()? as i64;
+    }
+    Ok(FileNetworkOpenInformation {
+        times: FsccTimes {
+            creation: times[0],
+            last_access: times[1],
+            last_write: times[2],
+            change: times[3],
+        },
+        allocation_size: body.u64()? as i64,
+        end_of_file: body.u64()? as i64,
+        attributes: body.u32()?,
+    })
+}
+/// Gets the output buffer of a `QUERY_INFO` or `QUERY_DIRECTORY` response
+fn output_buffer<'a>(message: &'a [u8], body: &mut Reader) -> Result<&'a [u8]> {
+    body.skip(2)?;
+    let offset = body.u16()? as usize;
+    let length = body.u32()? as usize;
+    buffer(message, offset, length)
+}
+/// Gets the bytes at `offset` from the start of the header
+fn buffer(message: &[u8], offset: usize, length: usize) -> Result<&[u8]> {
+    if length == 0 {
+        return Ok(&[]);
+    }
+    offset
+        .checked_add(length)
+        .and_then(|end| message.get(offset..end))
+        .ok_or_else(|| invalid("A buffer of the SMB2 message is outside of it"))
+}
+/// Reads the attributes of every entry of a listing of any class but `FileNamesInformation`,
+/// which all have them at the same place
+fn directory_attributes(listing: &[u8]) -> Result<Vec<u32>> {
+    let mut attributes = Vec::new();
+    let mut offset = 0;
+    while offset < listing.len() {
+        let mut entry = Reader::new(&listing[offset..], "directory listing");
+        let next = entry.u32()? as usize;
+        entry.skip(52)?;
+        attributes.push(entry.u32()?);
+        if next == 0 {
+            break;
+        }
+        offset += next;
+    }
+    Ok(attributes)
+}
@@ -0,0 +1,172 @@
+use crate::error::*;
+use crate::props::constants::*;
+use crate::wire::fscc::*;
+use crate::Props;
+
+const TIMES: FsccTimes = FsccTimes {
+    creation: 100,
+    last_access: 200,
+    last_write: 300,
+    change: 400,
+};
+
+#[test]
+fn basic_information() {
+    let information = FileBasicInformation {
+        times: TIMES,
+        attributes: HIDDEN | SYSTEM | DIRECTORY,
+    };
+    let bytes = information.to_bytes();
+    assert_eq!(bytes.len(), FileBasicInformation::SIZE);
+    assert_eq!(&bytes[32..36], &0x16u32.to_le_bytes());
+    assert_eq!(FileBasicInformation::parse(&bytes), Ok(information));
+    // Without the reserved bytes
+    assert_eq!(FileBasicInformation::parse(&bytes[..36]), Ok(information));
+    assert!(FileBasicInformation::parse(&bytes[..35]).is_err());
+    assert_eq!(
+        information.props(),
+        Props::from(DIRECTORY | HIDDEN | SYSTEM)
+    );
+}
+#[test]
+fn fixed_size_classes() {
+    let open = FileNetworkOpenInformation {
+        times: TIMES,
+        allocation_size: 4096,
+        end_of_file: 10,
+        attributes: ARCHIVE | READ_ONLY,
+    };
+    let bytes = open.to_bytes();
+    assert_eq!(bytes.len(), FileNetworkOpenInformation::SIZE);
+    assert_eq!(FileNetworkOpenInformation::parse(&bytes), Ok(open));
+    assert_eq!(open.props(), Props::from(ARCHIVE | READ_ONLY));
+
+    let stat = FileStatInformation {
+        file_id: 0x0005_0000_0000_0024,
+        times: TIMES,
+        allocation_size: 0,
+        end_of_file: 0,
+        attributes: REPARSE | DIRECTORY,
+        reparse_tag: 0xA000_0003,
+        number_of_links: 1,
+        effective_access: 0x001F_01FF,
+    };
+    let bytes = stat.to_bytes();
+    assert_eq!(bytes.len(), FileStatInformation::SIZE);
+    assert_eq!(&bytes[56..64], &[0x10, 0x04, 0, 0, 0x03, 0, 0, 0xA0]);
+    assert_eq!(FileStatInformation::parse(&bytes), Ok(stat));
+
+    let standard = FileStandardInformation {
+        allocation_size: 8,
+        end_of_file: 7,
+        number_of_links: 2,
+        delete_pending: false,
+        directory: true,
+    };
+    let bytes = standard.to_bytes();
+    assert_eq!(bytes.len(), FileStandardInformation::SIZE);
+    assert_eq!(FileStandardInformation::parse(&bytes), Ok(standard));
+}
+#[test]
+fn all_information() {
+    let all = FileAllInformation {
+        basic: FileBasicInformation {
+            times: TIMES,
+            attributes: ARCHIVE | HIDDEN,
+        },
+        standard: FileStandardInformation {
+            number_of_links: 1,
+            ..Default::default()
+        },
+        index_number: 0x24,
+        ea_size: 0,
+        access_flags: 0x0012_0089,
+        current_byte_offset: 0,
+        mode: 0x20,
+        alignment_requirement: 0,
+        name: r"\docs\report.txt".to_string(),
+    };
+    let bytes = all.to_bytes();
+    assert_eq!(bytes.len(), 100 + 32);
+    assert_eq!(FileAllInformation::parse(&bytes), Ok(all.clone()));
+    assert_eq!(all.props(), Props::from(ARCHIVE | HIDDEN));
+    // The buffer of the caller was too small for the whole name
+    let cut = FileAllInformation::parse(&bytes[..110]).unwrap();
+    assert_eq!(cut.name, r"\docs");
+    assert!(FileAllInformation::parse(&bytes[..90]).is_err());
+}
+#[test]
+fn directory_listings() {
+    let entries = vec![
+        FileIdBothDirectoryInformation {
+            times: TIMES,
+            attributes: DIRECTORY,
+            file_id: 5,
+            name: ".".to_string(),
+            ..Default::default()
+        },
+        FileIdBothDirectoryInformation {
+            times: TIMES,
+            end_of_file: 1000,
+            allocation_size: 4096,
+            attributes: ARCHIVE | HIDDEN,
+            short_name: "LONGFI~1.TXT".to_string(),
+            file_id: 0x24,
+            name: "long file name.txt".to_string(),
+            ..Default::default()
+        },
+        FileIdBothDirectoryInformation {
+            attributes: ARCHIVE | REPARSE,
+            ea_size: 0xA000_000C,
+            name: "link".to_string(),
+            ..Default::default()
+        },
+    ];
+    let bytes = FileIdBothDirectoryInformation::to_bytes_all(&entries);
+    // The first entry is padded from 106 to 112 bytes
+    assert_eq!(&bytes[..4], &112u32.to_le_bytes());
+    assert_eq!(bytes[112 + 68], 24);
+    let parsed = FileIdBothDirectoryInformation::parse_all(&bytes).unwrap();
+    assert_eq!(parsed, entries);
+    assert_eq!(parsed[1].props(), Props::from(ARCHIVE | HIDDEN));
+    assert_eq!(parsed[1].reparse_tag(), None);
+    assert_eq!(parsed[2].reparse_tag(), Some(0xA000_000C));
+
+    let information = FileInformation::parse(FILE_ID_BOTH_DIRECTORY_INFORMATION, &bytes).unwrap();
+    assert_eq!(information.class(), FILE_ID_BOTH_DIRECTORY_INFORMATION);
+    assert_eq!(
+        information.props(),
+        [
+            Props::from(DIRECTORY),
+            Props::from(ARCHIVE | HIDDEN),
+            Props::from(ARCHIVE | REPARSE)
+        ]
+    );
+    assert_eq!(information.to_bytes(), bytes);
+    // An offset past the end
+    let mut broken = bytes.clone();
+    broken[112..116].copy_from_slice(&4000u32.to_le_bytes());
+    assert!(FileIdBothDirectoryInformation::parse_all(&broken).is_err());
+}
+#[test]
+fn information_classes() {
+    let basic = FileBasicInformation {
+        times: TIMES,
+        attributes: READ_ONLY,
+    }
+    .to_bytes();
+    let information = FileInformation::parse(FILE_BASIC_INFORMATION, &basic).unwrap();
+    assert_eq!(information.attributes(), [READ_ONLY]);
+    assert_eq!(information.props(), [Props::from(ARCHIVE | READ_ONLY)]);
+    assert_eq!(information.to_bytes(), basic);
+    let standard = FileStandardInformation::default().to_bytes();
+    let information = FileInformation::parse(FILE_STANDARD_INFORMATION, &standard).unwrap();
+    assert_eq!(information.props(), []);
+    // FileNameInformation
+    assert!(matches!(
+        FileInformation::parse(9, &[0; 8]),
+        Err(Error {
+            kind: ErrorKind::NotSupported(_)
+        })
+    ));
+}
@@ -0,0 +1,3 @@
+mod fscc;
+mod smb2;
+mod win32;
@@ -0,0 +1,377 @@
+use crate::error::*;
+use crate::props::constants::*;
+use crate::wire::fscc::*;
+use crate::wire::smb2::*;
+use crate::Props;
+
+fn utf16(text: &str) -> Vec<u8> {
+    text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+fn encode(command: u16, response: bool, message_id: u64, status: u32, body: &[u8]) -> Vec<u8> {
+    let mut message = b"\xFESMB".to_vec();
+    message.extend(64u16.to_le_bytes());
+    message.extend([0, 0]);
+    message.extend(status.to_le_bytes());
+    message.extend(command.to_le_bytes());
+    message.extend(1u16.to_le_bytes());
+    message.extend((response as u32).to_le_bytes());
+    message.extend(0u32.to_le_bytes());
+    message.extend(message_id.to_le_bytes());
+    message.extend(0xFEFFu32.to_le_bytes());
+    message.extend(7u32.to_le_bytes());
+    message.extend(0x4000_0001u64.to_le_bytes());
+    message.extend([0; 16]);
+    message.extend(body);
+    message
+}
+/// Chains messages as a compound, each aligned to 8 bytes
+fn compound(messages: &[Vec<u8>]) -> Vec<u8> {
+    let mut payload = Vec::new();
+    for (index, message) in messages.iter().enumerate() {
+        let start = payload.len();
+        payload.extend(message);
+        if index + 1 < messages.len() {
+            payload.resize(payload.len().next_multiple_of(8), 0);
+            let next = (payload.len() - start) as u32;
+            payload[start + 20..start + 24].copy_from_slice(&next.to_le_bytes());
+        }
+    }
+    payload
+}
+fn file_id() -> Vec<u8> {
+    let mut id = 1u64.to_le_bytes().to_vec();
+    id.extend(2u64.to_le_bytes());
+    id
+}
+fn network_open(attributes: u32) -> FileNetworkOpenInformation {
+    FileNetworkOpenInformation {
+        times: FsccTimes {
+            creation: 1,
+            last_access: 2,
+            last_write: 3,
+            change: 4,
+        },
+        allocation_size: 4096,
+        end_of_file: 12,
+        attributes,
+    }
+}
+
+#[test]
+fn create() {
+    let name = utf16(r"docs\new.txt");
+    let mut body = 57u16.to_le_bytes().to_vec();
+    body.resize(24, 0);
+    body.extend(0x0012_019Fu32.to_le_bytes());
+    body.extend((HIDDEN | ARCHIVE).to_le_bytes());
+    body.extend(7u32.to_le_bytes());
+    body.extend(2u32.to_le_bytes());
+    body.extend(0x40u32.to_le_bytes());
+    body.extend(120u16.to_le_bytes());
+    body.extend((name.len() as u16).to_le_bytes());
+    body.extend([0; 8]);
+    body.extend(&name);
+    // Over the Direct TCP transport
+    let request = encode(SMB2_CREATE, false, 10, 0, &body);
+    let mut payload = vec![0, 0, 0, request.len() as u8];
+    payload.extend(request);
+
+    let mut body = 89u16.to_le_bytes().to_vec();
+    body.extend([0, 0]);
+    body.extend(2u32.to_le_bytes());
+    body.extend(&network_open(HIDDEN | ARCHIVE).to_bytes()[..52]);
+    body.extend([0; 4]);
+    body.extend(file_id());
+    body.extend([0; 8]);
+    let response = encode(SMB2_CREATE, true, 10, 0, &body);
+
+    let mut decoder = Smb2Decoder::default();
+    let request = decoder.decode(&payload).unwrap().remove(0);
+    assert_eq!(
+        request.content,
+        Smb2Content::CreateRequest {
+            name: r"docs\new.txt".to_string(),
+            attributes: HIDDEN | ARCHIVE,
+            desired_access: 0x0012_019F,
+            create_disposition: 2,
+            create_options: 0x40,
+        }
+    );
+    assert_eq!(request.tree_id, Some(7));
+    assert_eq!(request.session_id, 0x4000_0001);
+    assert_eq!(request.props(), [Props::from(ARCHIVE | HIDDEN)]);
+    let response = decoder.decode(&response).unwrap().remove(0);
+    assert!(response.response);
+    assert_eq!(
+        response.content,
+        Smb2Content::CreateResponse {
+            file_id: Smb2FileId {
+                persistent: 1,
+                volatile: 2
+            },
+            create_action: 2,
+            information: network_open(HIDDEN | ARCHIVE),
+        }
+    );
+    // Without attributes, the server picks them
+    payload[4 + 64 + 28] = 0;
+    let request = decoder.decode(&payload).unwrap().remove(0);
+    assert_eq!(request.props(), []);
+}
+#[test]
+fn close() {
+    let mut body = 24u16.to_le_bytes().to_vec();
+    body.extend(SMB2_CLOSE_FLAG_POSTQUERY_ATTRIB.to_le_bytes());
+    body.extend([0; 4]);
+    body.extend(file_id());
+    let request = encode(SMB2_CLOSE, false, 11, 0, &body);
+    let mut body = 60u16.to_le_bytes().to_vec();
+    body.extend(SMB2_CLOSE_FLAG_POSTQUERY_ATTRIB.to_le_bytes());
+    body.extend([0; 4]);
+    body.extend(&network_open(DIRECTORY | SYSTEM).to_bytes()[..52]);
+    let response = encode(SMB2_CLOSE, true, 11, 0, &body);
+
+    let messages = Smb2Decoder::default()
+        .decode(&compound(&[request, response.clone()]))
+        .unwrap();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(
+        messages[0].content,
+        Smb2Content::CloseRequest {
+            file_id: Smb2FileId {
+                persistent: 1,
+                volatile: 2
+            },
+            postquery: true,
+        }
+    );
+    assert_eq!(messages[1].props(), [Props::from(DIRECTORY | SYSTEM)]);
+    // Without the flag, the times and attributes are zeros
+    let mut response = response;
+    response[66] = 0;
+    let message = Smb2Decoder::default().decode(&response).unwrap().remove(0);
+    assert_eq!(
+        message.content,
+        Smb2Content::CloseResponse { information: None }
+    );
+}
+#[test]
+fn query_and_set_info() {
+    let mut body = 41u16.to_le_bytes().to_vec();
+    body.extend([SMB2_0_INFO_FILE, FILE_ATTRIBUTE_TAG_INFORMATION]);
+    body.resize(24, 0);
+    body.extend(file_id());
+    body.push(0);
+    let request = encode(SMB2_QUERY_INFO, false, 12, 0, &body);
+    let mut body = 9u16.to_le_bytes().to_vec();
+    body.extend(72u16.to_le_bytes());
+    body.extend(8u32.to_le_bytes());
+    body.extend(
+        FileAttributeTagInformation {
+            attributes: ARCHIVE | REPARSE,
+            reparse_tag: 0xA000_000C,
+        }
+        .to_bytes(),
+    );
+    let response = encode(SMB2_QUERY_INFO, true, 12, 0, &body);
+
+    // Without the request, the class isn't known
+    let alone = Smb2Decoder::default().decode(&response).unwrap().remove(0);
+    assert_eq!(
+        alone.content,
+        Smb2Content::QueryInfoResponse {
+            class: None,
+            information: None
+        }
+    );
+    let mut decoder = Smb2Decoder::default();
+    decoder.decode(&request).unwrap();
+    // The interim response of a command that went asynchronous keeps the request
+    let mut interim = encode(
+        SMB2_QUERY_INFO,
+        true,
+        12,
+        0x103,
+        &[9, 0, 0, 0, 0, 0, 0, 0, 0],
+    );
+    interim[16] |= 0x02;
+    let message = decoder.decode(&interim).unwrap().remove(0);
+    assert_eq!(message.content, Smb2Content::Other);
+    assert_eq!(message.tree_id, None);
+    let message = decoder.decode(&response).unwrap().remove(0);
+    assert_eq!(message.props(), [Props::from(ARCHIVE | REPARSE)]);
+    assert!(matches!(
+        message.content,
+        Smb2Content::QueryInfoResponse {
+            class: Some(FILE_ATTRIBUTE_TAG_INFORMATION),
+            information: Some(FileInformation::AttributeTag(_))
+        }
+    ));
+
+    let basic = FileBasicInformation {
+        times: FsccTimes::default(),
+        attributes: READ_ONLY | ARCHIVE,
+    };
+    let mut body = 33u16.to_le_bytes().to_vec();
+    body.extend([SMB2_0_INFO_FILE, FILE_BASIC_INFORMATION]);
+    body.extend(40u32.to_le_bytes());
+    body.extend(96u16.to_le_bytes());
+    body.extend([0; 6]);
+    body.extend(file_id());
+    body.extend(basic.to_bytes());
+    let set = encode(SMB2_SET_INFO, false, 13, 0, &body);
+    let message = decoder.decode(&set).unwrap().remove(0);
+    assert_eq!(message.props(), [Props::from(ARCHIVE | READ_ONLY)]);
+    // Only the times change
+    let mut times_only = set.clone();
+    times_only[96 + 32] = 0;
+    times_only[96 + 33] = 0;
+    let message = decoder.decode(&times_only).unwrap().remove(0);
+    assert_eq!(message.attributes(), []);
+}
+#[test]
+fn query_directory() {
+    let pattern = utf16("*");
+    let mut body = 33u16.to_le_bytes().to_vec();
+    body.extend([FILE_ID_BOTH_DIRECTORY_INFORMATION, 0]);
+    body.extend([0; 4]);
+    body.extend(file_id());
+    body.extend(96u16.to_le_bytes());
+    body.extend((pattern.len() as u16).to_le_bytes());
+    body.extend(65536u32.to_le_bytes());
+    body.extend(&pattern);
+    let request = encode(SMB2_QUERY_DIRECTORY, false, 14, 0, &body);
+    let listing = FileIdBothDirectoryInformation::to_bytes_all(&[
+        FileIdBothDirectoryInformation {
+            attributes: DIRECTORY,
+            name: ".".to_string(),
+            ..Default::default()
+        },
+        FileIdBothDirectoryInformation {
+            attributes: ARCHIVE | HIDDEN | SYSTEM,
+            name: "desktop.ini".to_string(),
+            ..Default::default()
+        },
+    ]);
+    let mut body = 9u16.to_le_bytes().to_vec();
+    body.extend(72u16.to_le_bytes());
+    body.extend((listing.len() as u32).to_le_bytes());
+    body.extend(&listing);
+    let response = encode(SMB2_QUERY_DIRECTORY, true, 14, 0, &body);
+    // STATUS_NO_MORE_FILES
+    let end = encode(
+        SMB2_QUERY_DIRECTORY,
+        true,
+        15,
+        0x8000_0006,
+        &[9, 0, 0, 0, 0, 0, 0, 0, 0],
+    );
+
+    let mut decoder = Smb2Decoder::default();
+    let request = decoder.decode(&request).unwrap().remove(0);
+    assert!(matches!(
+        request.content,
+        Smb2Content::QueryDirectoryRequest { ref pattern, .. } if pattern == "*"
+    ));
+    let message = decoder.decode(&response).unwrap().remove(0);
+    assert_eq!(
+        message.props(),
+        [
+            Props::from(DIRECTORY),
+            Props::from(ARCHIVE | HIDDEN | SYSTEM)
+        ]
+    );
+    let Smb2Content::QueryDirectoryResponse {
+        information: Some(FileInformation::IdBothDirectory(entries)),
+        ..
+    } = message.content
+    else {
+        panic!("The listing wasn't decoded");
+    };
+    assert_eq!(entries[1].name, "desktop.ini");
+    let end = decoder.decode(&end).unwrap().remove(0);
+    assert_eq!(end.status, 0x8000_0006);
+    assert_eq!(end.content, Smb2Content::Other);
+}
+#[test]
+fn invalid_payloads() {
+    assert!(Smb2Decoder::default().decode(b"\xFFSMB").is_err());
+    assert!(matches!(
+        Smb2Decoder::default().decode(&[0xFD, b'S', b'M', b'B', 0, 0, 0, 0]),
+        Err(Error {
+            kind: ErrorKind::NotSupported(_)
+        })
+    ));
+    let mut short = encode(SMB2_CREATE, false, 1, 0, &[57, 0]);
+    let message = Smb2Decoder::default().decode(&short).unwrap().remove(0);
+    assert!(matches!(message.content, Smb2Content::Malformed { .. }));
+    short.truncate(40);
+    assert!(Smb2Decoder::default().decode(&short).is_err());
+    // Commands without attributes
+    let echo = encode(0x0D, false, 1, 0, &[4, 0, 0, 0]);
+    let echo = Smb2Decoder::default().decode(&echo).unwrap().remove(0);
+    assert_eq!(echo.content, Smb2Content::Other);
+}
+#[test]
+fn malformed_message_in_a_compound() {
+    let mut body = 41u16.to_le_bytes().to_vec();
+    body.extend([SMB2_0_INFO_FILE, FILE_BASIC_INFORMATION]);
+    let truncated = encode(SMB2_QUERY_INFO, false, 2, 0, &body);
+    let echo = encode(0x0D, false, 3, 0, &[4, 0, 0, 0]);
+    let mut body = vec![24, 0, 1, 0, 0, 0, 0, 0];
+    body.extend(file_id());
+    let close = encode(SMB2_CLOSE, false, 1, 0, &body);
+    let messages = Smb2Decoder::default()
+        .decode(&compound(&[close.clone(), truncated, echo.clone()]))
+        .unwrap();
+    assert_eq!(messages.len(), 3);
+    assert!(matches!(
+        messages[0].content,
+        Smb2Content::CloseRequest {
+            postquery: true,
+            ..
+        }
+    ));
+    assert!(matches!(messages[1].content, Smb2Content::Malformed { .. }));
+    assert_eq!(messages[2].content, Smb2Content::Other);
+    // The messages before one that can't be framed are still given
+    let mut payload = compound(&[close, echo]);
+    let second = payload.len() - 68;
+    payload[second] = 0xFF;
+    let messages = Smb2Decoder::default().decode(&payload).unwrap();
+    assert_eq!(messages.len(), 1);
+}
+#[test]
+fn requests_without_responses() {
+    let mut body = 41u16.to_le_bytes().to_vec();
+    body.extend([SMB2_0_INFO_FILE, FILE_ATTRIBUTE_TAG_INFORMATION]);
+    body.resize(24, 0);
+    body.extend(file_id());
+    body.push(0);
+    // A capture of only the client side
+    let mut decoder = Smb2Decoder::default();
+    for message_id in 0..=8192 {
+        decoder
+            .decode(&encode(SMB2_QUERY_INFO, false, message_id, 0, &body))
+            .unwrap();
+    }
+    let class = |decoder: &mut Smb2Decoder, message_id| {
+        let response = encode(
+            SMB2_QUERY_INFO,
+            true,
+            message_id,
+            0,
+            &[9, 0, 72, 0, 0, 0, 0, 0],
+        );
+        match decoder.decode(&response).unwrap().remove(0).content {
+            Smb2Content::QueryInfoResponse { class, .. } => class,
+            content => panic!("{:?}", content),
+        }
+    };
+    // The oldest request was forgotten
+    assert_eq!(class(&mut decoder, 0), None);
+    assert_eq!(
+        class(&mut decoder, 8192),
+        Some(FILE_ATTRIBUTE_TAG_INFORMATION)
+    );
+}
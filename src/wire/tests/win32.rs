@@ -0,0 +1,86 @@
+use crate::props::constants::*;
+use crate::wire::win32::*;
+use crate::Props;
+
+fn utf16(text: &str) -> Vec<u8> {
+    text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+/// The start every structure shares: the attributes and the times
+fn header(attributes: u32) -> Vec<u8> {
+    let mut bytes = attributes.to_le_bytes().to_vec();
+    for time in [100u64, 200, 300] {
+        bytes.extend(time.to_le_bytes());
+    }
+    bytes
+}
+
+#[test]
+fn find_data() {
+    let mut bytes = header(ARCHIVE | HIDDEN | REPARSE);
+    bytes.extend(1u32.to_le_bytes());
+    bytes.extend(2u32.to_le_bytes());
+    // IO_REPARSE_TAG_SYMLINK
+    bytes.extend(0xA000_000Cu32.to_le_bytes());
+    bytes.extend(0u32.to_le_bytes());
+    bytes.extend(utf16("Program Files Link"));
+    bytes.resize(564, 0);
+    bytes.extend(utf16("PROGRA~1"));
+    bytes.resize(FindData::SIZE, 0);
+    // The captures can have more after it
+    bytes.extend([0xFF; 8]);
+    let data = FindData::parse(&bytes).unwrap();
+    assert_eq!(data.bytes().len(), FindData::SIZE);
+    assert_eq!(data.props(), Props::from(ARCHIVE | HIDDEN | REPARSE));
+    assert_eq!(
+        data.times(),
+        Win32Times {
+            creation: 100,
+            last_access: 200,
+            last_write: 300
+        }
+    );
+    assert_eq!(data.size(), 0x1_0000_0002);
+    assert_eq!(data.reparse_tag(), Some(0xA000_000C));
+    assert_eq!(data.file_name(), "Program Files Link");
+    assert_eq!(data.file_name_utf16().count(), 18);
+    assert_eq!(data.alternate_file_name(), "PROGRA~1");
+
+    bytes[..4].copy_from_slice(&READ_ONLY.to_le_bytes());
+    let data = FindData::parse(&bytes).unwrap();
+    // Windows doesn't always set the archive bit of files
+    assert_eq!(data.props(), Props::from(ARCHIVE | READ_ONLY));
+    assert_eq!(data.reparse_tag(), None);
+    assert!(FindData::parse(&bytes[..591]).is_err());
+}
+#[test]
+fn file_attribute_data() {
+    let mut bytes = header(DIRECTORY | SYSTEM);
+    bytes.extend(0u32.to_le_bytes());
+    bytes.extend(4096u32.to_le_bytes());
+    let data = FileAttributeData::parse(&bytes).unwrap();
+    assert_eq!(data.props(), Props::from(DIRECTORY | SYSTEM));
+    assert_eq!(data.attributes(), DIRECTORY | SYSTEM);
+    assert_eq!(data.size(), 4096);
+    assert_eq!(data.times().last_write, 300);
+    assert!(FileAttributeData::parse(&bytes[..30]).is_err());
+}
+#[test]
+fn by_handle_file_information() {
+    let mut bytes = header(ARCHIVE | NOT_CONTENT_INDEXED);
+    bytes.extend(0xDEAD_BEEFu32.to_le_bytes());
+    bytes.extend(0u32.to_le_bytes());
+    bytes.extend(10u32.to_le_bytes());
+    bytes.extend(2u32.to_le_bytes());
+    bytes.extend(0x0005_0000u32.to_le_bytes());
+    bytes.extend(0x24u32.to_le_bytes());
+    let information = ByHandleFileInformation::parse(&bytes).unwrap();
+    assert_eq!(
+        information.props(),
+        Props::from(ARCHIVE | NOT_CONTENT_INDEXED)
+    );
+    assert_eq!(information.volume_serial_number(), 0xDEAD_BEEF);
+    assert_eq!(information.size(), 10);
+    assert_eq!(information.number_of_links(), 2);
+    assert_eq!(information.file_index(), 0x0005_0000_0000_0024);
+    assert_eq!(information.times().creation, 100);
+}
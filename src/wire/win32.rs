@@ -0,0 +1,199 @@
+//! Reads the structures `FindFirstFileW`, `GetFileAttributesExW` and
+//! `GetFileInformationByHandle` fill: `WIN32_FIND_DATAW`, `WIN32_FILE_ATTRIBUTE_DATA` and
+//! `BY_HANDLE_FILE_INFORMATION`. The parsers only check the size and borrow the bytes, reading
+//! every field when it's asked for.
+//!
+//! Windows splits the sizes in two `DWORD`s with the high one first, while the times are
+//! FILETIME values with the low part first, and both are joined here.
+//! ```
+//! use keypropdecode::props;
+//! use keypropdecode::wire::win32::FileAttributeData;
+//!
+//! let mut bytes = [0; 36];
+//! bytes[0] = 0x22;
+//! bytes[32] = 0x10;
+//! let data = FileAttributeData::parse(&bytes).unwrap();
+//! assert_eq!(data.props(), props!(file: hidden));
+//! assert_eq!(data.size(), 16);
+//! ```
+use super::{check_size, props_of, u32_at, u64_at, utf16_until_nul};
+use crate::error::*;
+use crate::props::constants::REPARSE;
+use crate::Props;
+
+/// The times every structure has, as FILETIME values
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Win32Times {
+    /// When the element was created
+    pub creation: u64,
+    /// When the element was last read or written
+    pub last_access: u64,
+    /// When the element was last written
+    pub last_write: u64,
+}
+impl Win32Times {
+    /// Reads the times right after the attributes, where every structure has them
+    fn read(bytes: &[u8]) -> Self {
+        Win32Times {
+            creation: u64_at(bytes, 4),
+            last_access: u64_at(bytes, 12),
+            last_write: u64_at(bytes, 20),
+        }
+    }
+}
+
+/// Joins a size that Windows gives with the high `DWORD` first
+fn split_size(bytes: &[u8], offset: usize) -> u64 {
+    (u32_at(bytes, offset) as u64) << 32 | u32_at(bytes, offset + 4) as u64
+}
+
+/// Decodes UTF-16 characters, replacing the ones that aren't valid
+fn decode(characters: impl Iterator<Item = u16>) -> String {
+    char::decode_utf16(characters)
+        .map(|character| character.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+/// A `WIN32_FIND_DATAW`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct FindData<'a> {
+    bytes: &'a [u8],
+}
+impl<'a> FindData<'a> {
+    /// The size of the structure
+    pub const SIZE: usize = 592;
+    /// Borrows the structure at the start of `bytes`
+    pub fn parse(bytes: &'a [u8]) -> Result<Self> {
+        check_size(bytes, Self::SIZE, "WIN32_FIND_DATAW")?;
+        Ok(FindData {
+            bytes: &bytes[..Self::SIZE],
+        })
+    }
+    /// Gets the bytes of the structure
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+    /// Gets `dwFileAttributes`
+    pub fn attributes(&self) -> u32 {
+        u32_at(self.bytes, 0)
+    }
+    /// Gets the Props of `dwFileAttributes`
+    pub fn props(&self) -> Props {
+        props_of(self.attributes())
+    }
+    /// Gets the times
+    pub fn times(&self) -> Win32Times {
+        Win32Times::read(self.bytes)
+    }
+    /// Gets the size of the data
+    pub fn size(&self) -> u64 {
+        split_size(self.bytes, 28)
+    }
+    /// Gets `dwReserved0`, which has the reparse tag when the element is a reparse point
+    pub fn reserved0(&self) -> u32 {
+        u32_at(self.bytes, 36)
+    }
+    /// Gets the reparse tag, like `IO_REPARSE_TAG_SYMLINK`, of reparse points
+    pub fn reparse_tag(&self) -> Option<u32> {
+        (self.attributes() & REPARSE != 0).then(|| self.reserved0())
+    }
+    /// Gets the UTF-16 characters of `cFileName`, up to the nul one
+    pub fn file_name_utf16(&self) -> impl Iterator<Item = u16> + 'a {
+        utf16_until_nul(&self.bytes[44..564])
+    }
+    /// Gets `cFileName`, replacing the characters that aren't valid UTF-16
+    pub fn file_name(&self) -> String {
+        decode(self.file_name_utf16())
+    }
+    /// Gets `cAlternateFileName`, the 8.3 name, which is empty when the name is already one
+    pub fn alternate_file_name(&self) -> String {
+        decode(utf16_until_nul(&self.bytes[564..592]))
+    }
+}
+
+/// A `WIN32_FILE_ATTRIBUTE_DATA`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct FileAttributeData<'a> {
+    bytes: &'a [u8],
+}
+impl<'a> FileAttributeData<'a> {
+    /// The size of the structure
+    pub const SIZE: usize = 36;
+    /// Borrows the structure at the start of `bytes`
+    pub fn parse(bytes: &'a [u8]) -> Result<Self> {
+        check_size(bytes, Self::SIZE, "WIN32_FILE_ATTRIBUTE_DATA")?;
+        Ok(FileAttributeData {
+            bytes: &bytes[..Self::SIZE],
+        })
+    }
+    /// Gets the bytes of the structure
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+    /// Gets `dwFileAttributes`
+    pub fn attributes(&self) -> u32 {
+        u32_at(self.bytes, 0)
+    }
+    /// Gets the Props of `dwFileAttributes`
+    pub fn props(&self) -> Props {
+        props_of(self.attributes())
+    }
+    /// Gets the times
+    pub fn times(&self) -> Win32Times {
+        Win32Times::read(self.bytes)
+    }
+    /// Gets the size of the data
+    pub fn size(&self) -> u64 {
+        split_size(self.bytes, 28)
+    }
+}
+
+/// A `BY_HANDLE_FILE_INFORMATION`
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct ByHandleFileInformation<'a> {
+    bytes: &'a [u8],
+}
+impl<'a> ByHandleFileInformation<'a> {
+    /// The size of the structure
+    pub const SIZE: usize = 52;
+    /// Borrows the structure at the start of `bytes`
+    pub fn parse(bytes: &'a [u8]) -> Result<Self> {
+        check_size(bytes, Self::SIZE, "BY_HANDLE_FILE_INFORMATION")?;
+        Ok(ByHandleFileInformation {
+            bytes: &bytes[..Self::SIZE],
+        })
+    }
+    /// Gets the bytes of the structure
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+    /// Gets `dwFileAttributes`
+    pub fn attributes(&self) -> u32 {
+        u32_at(self.bytes, 0)
+    }
+    /// Gets the Props of `dwFileAttributes`
+    pub fn props(&self) -> Props {
+        props_of(self.attributes())
+    }
+    /// Gets the times
+    pub fn times(&self) -> Win32Times {
+        Win32Times::read(self.bytes)
+    }
+    /// Gets the serial number of the volume the element is in
+    pub fn volume_serial_number(&self) -> u32 {
+        u32_at(self.bytes, 28)
+    }
+    /// Gets the size of the data
+    pub fn size(&self) -> u64 {
+        split_size(self.bytes, 32)
+    }
+    /// Gets the number of hard links of the element
+    pub fn number_of_links(&self) -> u32 {
+        u32_at(self.bytes, 40)
+    }
+    /// Gets the file index, which is the MFT reference on NTFS and, with the serial number,
+    /// tells apart every element
+    pub fn file_index(&self) -> u64 {
+        split_size(self.bytes, 44)
+    }
+}
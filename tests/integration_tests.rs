@@ -1,3 +1,4 @@
+#![allow(clippy::bool_assert_comparison)]
 use keypropdecode::props::*;
 #[test]
 fn read_only_and_hidden_archive_from_number() {
@@ -40,3 +41,32 @@ fn readme_test() {
     props.hidden(true);
     assert_eq!(Props::try_from(r"hidden_file_example.txt").unwrap(), props);
 }
+#[test]
+fn props_macro_file() {
+    let mut props = Props::default();
+    props.change_element_type(ArcDir::Archive(ArchiveProps::default()));
+    props.hidden(true);
+    props.read_only(true).unwrap();
+    props.temporary(true).unwrap();
+    assert_eq!(
+        keypropdecode::props!(file: hidden, read_only, temporary),
+        props
+    );
+}
+#[test]
+fn props_macro_directory() {
+    let props = keypropdecode::props!(dir: hidden, system);
+    assert!(props.is_directory());
+    assert!(props.is_hidden());
+    assert!(props.is_system());
+    assert_eq!(u32::from(props), 0b10110);
+}
+#[test]
+fn props_macro_normal_and_empty() {
+    let mut props = Props::default();
+    props.change_element_type(ArcDir::Archive(ArchiveProps::default()));
+    assert_eq!(keypropdecode::props!(file), props);
+    props.normal(true).unwrap();
+    assert_eq!(keypropdecode::props!(file: normal), props);
+    assert_eq!(keypropdecode::props!(dir), Props::default());
+}